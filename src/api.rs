@@ -1,4 +1,12 @@
-mod server;
-mod client;
-mod errors;
-mod structs;
+mod broadcast;
+pub mod server;
+pub mod client;
+pub mod config;
+pub mod errors;
+mod metrics;
+mod middleware;
+pub mod structs;
+mod sync;
+#[cfg(feature = "tls")]
+mod tls;
+mod ws;
@@ -0,0 +1,186 @@
+use crate::api::client::APIClient;
+use crate::api::structs::State;
+use crate::blockchain::block::Block;
+use crate::peers::MemberEntry;
+use async_std::task;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub const NODE_ID_HEADER: &str = "X-Node-Id";
+
+#[derive(Debug, Default)]
+pub struct BroadcastStats {
+    attempts: AtomicU64,
+    failures: AtomicU64,
+}
+
+impl BroadcastStats {
+    pub fn attempts(&self) -> u64 {
+        self.attempts.load(Ordering::Relaxed)
+    }
+    pub fn failures(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+}
+
+// Fire and forget: the response to the poster never waits on other peers.
+// `origin` is the sender's node id, which is the URL it is registered under,
+// though not necessarily spelled the way it was stored.
+pub fn spawn_broadcast(state: &State, block: Block, origin: Option<String>) {
+    let origin = origin.map(|peer| MemberEntry { peer: peer }.normalized());
+    let targets: Vec<MemberEntry> = state
+        .lock_peers()
+        .members
+        .iter()
+        .filter(|member| origin.as_ref() != Some(*member))
+        .cloned()
+        .collect();
+    if targets.is_empty() {
+        return;
+    }
+    let stats = state.broadcast.clone();
+    let node_id = state.node_id.clone();
+    task::spawn(async move {
+        for member in targets {
+            stats.attempts.fetch_add(1, Ordering::Relaxed);
+            let client = match &node_id {
+                Some(node_id) => APIClient::new(member.peer.clone()).with_node_id(node_id.as_str()),
+                None => APIClient::new(member.peer.clone()),
+            };
+            if let Err(error) = client.send_block(block.clone()).await {
+                stats.failures.fetch_add(1, Ordering::Relaxed);
+                tide::log::warn!("broadcast of block {} to {} failed: {:?}", block.index, member.peer, error);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::server::create_app_with_genesis;
+    use crate::blockchain::block::message_as_json;
+    use std::time::Duration;
+    use tide::http::{Method, Request, Url};
+    use tide::listener::Listener;
+    use tide::Server;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn arrange_running_node(genesis: &Block) -> (String, State) {
+        let app = create_app_with_genesis(String::from(""), Some(genesis.clone())).unwrap();
+        let state = app.state().clone();
+        let mut listener = app.bind("127.0.0.1:0").await.unwrap();
+        let url = listener.info()[0].connection().to_string();
+        task::spawn(async move { listener.accept().await });
+        (url, state)
+    }
+
+    async fn request_add_block(block: &Block, origin: Option<&str>, app: &Server<State>) -> u16 {
+        let url = Url::parse("https://example.com/blocks").unwrap();
+        let mut req = Request::new(Method::Post, url);
+        req.set_body(serde_json::to_string(block).unwrap());
+        if let Some(origin) = origin {
+            req.insert_header(NODE_ID_HEADER, origin);
+        }
+        let res: tide::http::Response = app.respond(req).await.unwrap();
+        res.status().into()
+    }
+
+    #[async_std::test]
+    async fn relay_skips_the_client_that_sent_the_block() {
+        let origin = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(0)
+            .mount(&origin)
+            .await;
+        let genesis = Block::genesis(message_as_json("Shared genesis"), 1000);
+        let (url, node) = arrange_running_node(&genesis).await;
+        node.add_peer(MemberEntry { peer: origin.uri() }).unwrap();
+
+        let block = Block::builder().after(&genesis).data_message("From origin").build();
+        let client = APIClient::new(url).with_node_id(origin.uri());
+        assert_eq!(client.send_block(block).await.unwrap().index, 1);
+        task::sleep(Duration::from_millis(200)).await;
+        assert_eq!(node.broadcast.attempts(), 0);
+        origin.verify().await;
+    }
+
+    async fn wait_until<F, Fut>(state: &State, condition: F)
+    where
+        F: Fn(State) -> Fut,
+        Fut: std::future::Future<Output = bool>,
+    {
+        for _ in 0..100 {
+            if condition(state.clone()).await {
+                return;
+            }
+            task::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("condition not met in time");
+    }
+
+    #[async_std::test]
+    async fn second_node_receives_posted_block() {
+        let genesis = Block::genesis(message_as_json("Shared genesis"), 1000);
+        let (url, second) = arrange_running_node(&genesis).await;
+        let first = create_app_with_genesis(String::from(""), Some(genesis.clone())).unwrap();
+        first.state().add_peer(MemberEntry { peer: url }).unwrap();
+
+        let block = Block::builder().after(&genesis).data_message("Gossiped block").build();
+        assert_eq!(request_add_block(&block, None, &first).await, 201);
+        wait_until(&second, |state| async move { state.chain.len().await == 2 }).await;
+        assert_eq!(second.chain.last().await.unwrap(), block);
+        let status = first.state().status().await;
+        assert_eq!(status.broadcast_attempts, 1);
+        assert_eq!(status.broadcast_failures, 0);
+    }
+
+    #[async_std::test]
+    async fn skips_origin_and_counts_failures() {
+        let origin = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/blocks"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(0)
+            .mount(&origin)
+            .await;
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        app.state().add_peer(MemberEntry { peer: origin.uri() }).unwrap();
+        app.state()
+            .add_peer(MemberEntry { peer: String::from("http://127.0.0.1:9") })
+            .unwrap();
+
+        let block = Block::builder()
+            .after(&app.state().chain.last().await.unwrap())
+            .data_message("Block from origin")
+            .build();
+        assert_eq!(request_add_block(&block, Some(&origin.uri()), &app).await, 201);
+        let state = app.state().clone();
+        wait_until(&state, |state| async move { state.broadcast.failures() == 1 }).await;
+        assert_eq!(state.broadcast.attempts(), 1);
+        origin.verify().await;
+    }
+
+    #[async_std::test]
+    async fn skips_origin_spelled_differently() {
+        let origin = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(0)
+            .mount(&origin)
+            .await;
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        app.state().add_peer(MemberEntry { peer: origin.uri() }).unwrap();
+
+        let block = Block::builder()
+            .after(&app.state().chain.last().await.unwrap())
+            .data_message("Block from origin")
+            .build();
+        let spelled_differently = format!("{}/", origin.uri().to_uppercase());
+        assert_eq!(request_add_block(&block, Some(&spelled_differently), &app).await, 201);
+        task::sleep(Duration::from_millis(200)).await;
+        assert_eq!(app.state().broadcast.attempts(), 0);
+        origin.verify().await;
+    }
+}
@@ -1,84 +1,637 @@
+use crate::api::broadcast::NODE_ID_HEADER;
 use crate::api::errors::APIErrorAndReason;
 use crate::peers::{EntryRejectedErr, MemberEntry};
-use crate::api::structs::{BlockList, Limits};
-use crate::blockchain::block::{get_epoch_ms, message_as_json, Block};
-use crate::blockchain::InvalidBlockErr;
-use surf::{Error, Response};
+use crate::api::structs::{BatchReport, BlockList, Limits, NodeStatus, PagedBlockList, PeerList, CHAIN_HEIGHT_HEADER};
+use crate::blockchain::block::{get_epoch_ms, Block};
+use crate::blockchain::{Chain, InvalidBlockErr, DEFAULT_MAX_DATA_BYTES};
+use async_std::io::prelude::BufReadExt;
+use futures::{future, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use surf::{RequestBuilder, Response, StatusCode};
+#[cfg(feature = "tls")]
+use crate::api::tls::{PemFile, TlsOptions};
 
-struct APIClient {
+const DEFAULT_MAX_BLOCK_BYTES: usize = DEFAULT_MAX_DATA_BYTES + 4 * 1024;
+const DEFAULT_API_VERSION: &str = "v1";
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const BROADCAST_CONCURRENCY: usize = 8;
+
+#[derive(Debug)]
+pub enum APIClientError {
+    Transport(surf::Error),
+    Decode(String),
+    Api(APIErrorAndReason),
+    UnexpectedStatus(u16),
+    EmptyChain,
+    Timeout(Duration),
+    StalledPagination(u64),
+    Block(InvalidBlockErr),
+    BatchRejected { offset: usize, block_index: u64, source: InvalidBlockErr },
+    Peer(EntryRejectedErr),
+    InvalidSequence { at_index: u64, source: InvalidBlockErr },
+}
+
+impl fmt::Display for APIClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            APIClientError::Transport(error) => write!(f, "request failed: {}", error),
+            APIClientError::Decode(reason) => write!(f, "unexpected response body: {}", reason),
+            APIClientError::Api(error) => write!(f, "{}: {}", error.error, error.reason),
+            APIClientError::UnexpectedStatus(status) => write!(f, "unexpected response status {}", status),
+            APIClientError::Timeout(after) => write!(f, "no response within {:?}", after),
+            APIClientError::StalledPagination(from_index) => {
+                write!(f, "page requested from index {} does not advance", from_index)
+            }
+            APIClientError::EmptyChain => write!(f, "remote chain has no blocks"),
+            APIClientError::Block(error) => write!(f, "block rejected: {}", error),
+            APIClientError::BatchRejected { offset, block_index, source } => {
+                write!(f, "batch rejected at offset {} (block {}): {}", offset, block_index, source)
+            }
+            APIClientError::Peer(error) => write!(f, "peer rejected: {}", error),
+            APIClientError::InvalidSequence { at_index, source } => {
+                write!(f, "received block {} does not follow the one before: {}", at_index, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for APIClientError {}
+
+impl From<surf::Error> for APIClientError {
+    fn from(error: surf::Error) -> Self {
+        APIClientError::Transport(error)
+    }
+}
+
+#[derive(Debug)]
+pub enum SyncError {
+    Client(APIClientError),
+    Diverged { at_index: u64 },
+    Rejected { at_index: u64, source: InvalidBlockErr },
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::Client(error) => write!(f, "{}", error),
+            SyncError::Diverged { at_index } => write!(f, "remote chain diverges from ours at index {}", at_index),
+            SyncError::Rejected { at_index, source } => write!(f, "block {} rejected: {}", at_index, source),
+        }
+    }
+}
+
+impl std::error::Error for SyncError {}
+
+impl From<APIClientError> for SyncError {
+    fn from(error: APIClientError) -> Self {
+        SyncError::Client(error)
+    }
+}
+
+#[derive(Debug)]
+pub enum MirrorError {
+    Client(APIClientError),
+    InvalidBlock(u64, InvalidBlockErr),
+}
+
+impl fmt::Display for MirrorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MirrorError::Client(error) => write!(f, "{}", error),
+            MirrorError::InvalidBlock(index, error) => write!(f, "block {} is invalid: {}", index, error),
+        }
+    }
+}
+
+impl std::error::Error for MirrorError {}
+
+impl From<APIClientError> for MirrorError {
+    fn from(error: APIClientError) -> Self {
+        MirrorError::Client(error)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PeerRegistration {
+    Added(MemberEntry),
+    AlreadyMember(MemberEntry),
+}
+
+#[derive(Debug, Default)]
+pub struct BroadcastReport {
+    pub accepted: Vec<String>,
+    pub rejected: Vec<(String, InvalidBlockErr)>,
+    pub unreachable: Vec<String>,
+}
+
+pub struct APIClient {
     host_url: String,
+    http: surf::Client,
+    headers: Vec<(String, String)>,
+    max_block_bytes: usize,
+    api_version: Option<String>,
+    timeout: Duration,
+    verify: bool,
+    #[cfg(feature = "tls")]
+    tls: TlsOptions,
 }
 
 impl APIClient {
-    fn new(host_url: String) -> Self {
-        Self { host_url: host_url }
+    /// Creates a client for the node whose API is served at `host_url`, the
+    /// scheme and authority without a trailing slash.
+    ///
+    /// ```
+    /// use rustychain::APIClient;
+    ///
+    /// let client = APIClient::new("http://127.0.0.1:8080");
+    /// assert_eq!(client.host_url(), "http://127.0.0.1:8080");
+    /// ```
+    pub fn new(host_url: impl Into<String>) -> Self {
+        Self {
+            host_url: host_url.into(),
+            http: surf::Client::new(),
+            headers: vec![
+                (String::from("User-Agent"), format!("rustychain/{}", env!("CARGO_PKG_VERSION"))),
+                (String::from(NODE_ID_HEADER), random_node_id()),
+            ],
+            max_block_bytes: DEFAULT_MAX_BLOCK_BYTES,
+            api_version: Some(String::from(DEFAULT_API_VERSION)),
+            timeout: DEFAULT_TIMEOUT,
+            verify: false,
+            #[cfg(feature = "tls")]
+            tls: TlsOptions::default(),
+        }
     }
-    async fn get_all_blocks(&self) -> Result<BlockList, Error> {
-        let mut response: Response = surf::get(format!("{}/blocks", &self.host_url))
-            .await
-            .unwrap();
-        let list: BlockList = response.body_json().await?;
-        Ok(list)
+    pub fn host_url(&self) -> &str {
+        &self.host_url
     }
-    async fn get_blocks(&self, from_index: usize) -> Result<BlockList, Error> {
-        let limits = Limits {
-            from_index: from_index,
-        };
-        let mut response: Response =
-            surf::get(format!("{}/blocks?{}", &self.host_url, limits.as_query()))
-                .await
-                .unwrap();
-        let list: BlockList = response.body_json().await?;
-        Ok(list)
+    // Sent on every request, replacing any earlier value for the same name.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        let name = name.into();
+        self.headers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(&name));
+        self.headers.push((name, value.into()));
+        self
     }
-    async fn send_block(&self, block: Block) -> Result<Block, InvalidBlockErr> {
-        let mut response: Response = surf::post(format!("{}/blocks", &self.host_url))
-            .body_json(&block)
-            .unwrap()
-            .await
-            .unwrap();
-        match response.status().is_success() {
-            true => {
-                let confirmed: Block = response.body_json().await.unwrap();
-                Ok(confirmed)
+    // Nodes pass the URL they are registered under so peers can skip them
+    // when relaying; otherwise a random id generated in `new` is sent.
+    pub fn with_node_id(self, node_id: impl Into<String>) -> Self {
+        self.with_header(NODE_ID_HEADER, node_id)
+    }
+    pub fn node_id(&self) -> &str {
+        self.headers
+            .iter()
+            .find(|(name, _)| name == NODE_ID_HEADER)
+            .map_or("", |(_, value)| value.as_str())
+    }
+    pub fn with_token(self, token: impl Into<String>) -> Self {
+        self.with_header("Authorization", format!("Bearer {}", token.into()))
+    }
+    // `None` talks to the unversioned paths of nodes that predate /v1.
+    pub fn with_api_version(mut self, api_version: Option<String>) -> Self {
+        self.api_version = api_version;
+        self
+    }
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    // Checks that received block lists link up before returning them. The
+    // first block of a list not starting at genesis is taken as given.
+    pub fn with_verification(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+    // Only meant for test networks: any certificate the peer presents is trusted.
+    #[cfg(feature = "tls")]
+    pub fn danger_accept_invalid_certs(mut self, accept_invalid_certs: bool) -> Result<Self, APIClientError> {
+        self.tls.accept_invalid_certs = accept_invalid_certs;
+        self.http = self.tls.client()?;
+        Ok(self)
+    }
+    // Trusts the PEM encoded CA bundle on top of the system roots, for nodes
+    // behind terminators with a private CA.
+    #[cfg(feature = "tls")]
+    pub fn with_root_certificate(mut self, pem: impl AsRef<[u8]>) -> Result<Self, APIClientError> {
+        let pem_file = PemFile::write(pem.as_ref()).map_err(surf::Error::from)?;
+        self.tls.root_certificate = Some(pem_file);
+        self.http = self.tls.client()?;
+        Ok(self)
+    }
+    fn verified(&self, items: Vec<Block>) -> Result<Vec<Block>, APIClientError> {
+        if self.verify {
+            verify_sequence(&items)?;
+        }
+        Ok(items)
+    }
+    async fn send(&self, request: RequestBuilder) -> Result<Response, APIClientError> {
+        let request = self
+            .headers
+            .iter()
+            .fold(request, |request, (name, value)| request.header(name.as_str(), value.as_str()));
+        match async_std::future::timeout(self.timeout, request).await {
+            Ok(response) => Ok(response?),
+            Err(_) => Err(APIClientError::Timeout(self.timeout)),
+        }
+    }
+    fn url(&self, path: &str) -> String {
+        match &self.api_version {
+            Some(version) => format!("{}/{}{}", &self.host_url, version, path),
+            None => format!("{}{}", &self.host_url, path),
+        }
+    }
+    pub fn with_max_block_bytes(mut self, max_block_bytes: usize) -> Self {
+        self.max_block_bytes = max_block_bytes;
+        self
+    }
+    pub async fn get_all_blocks(&self) -> Result<BlockList, APIClientError> {
+        let mut response: Response = self.send(self.http.get(self.url("/blocks"))).await?;
+        let first: PagedBlockList = read_json(&mut response).await?;
+        let mut items = first.items;
+        if let Some(next_index) = first.next_index {
+            items.extend(self.get_blocks_after(next_index).await?);
+        }
+        Ok(BlockList {
+            items: self.verified(items)?,
+        })
+    }
+    pub async fn get_blocks_paged(&self, from_index: usize, page_size: usize) -> Result<Vec<Block>, APIClientError> {
+        let items = self.follow_pages(from_index as u64, Some(page_size)).await?;
+        self.verified(items)
+    }
+    async fn get_blocks_after(&self, from_index: u64) -> Result<Vec<Block>, APIClientError> {
+        self.follow_pages(from_index, None).await
+    }
+    // A page pointing back at itself (or earlier) would loop forever.
+    async fn follow_pages(&self, from_index: u64, page_size: Option<usize>) -> Result<Vec<Block>, APIClientError> {
+        let mut items = vec![];
+        let mut next_index = Some(from_index);
+        while let Some(from_index) = next_index {
+            let limits = Limits {
+                from_index: from_index as usize,
+                limit: page_size,
+            };
+            let page = self.get_page(&limits).await?;
+            next_index = match page.next_index {
+                Some(next) if next <= from_index => return Err(APIClientError::StalledPagination(from_index)),
+                next => next,
+            };
+            items.extend(page.items);
+        }
+        Ok(items)
+    }
+    // Validates page by page, so only one page is held besides the chain.
+    pub async fn mirror(&self) -> Result<Chain, MirrorError> {
+        let mut chain: Option<Chain> = None;
+        let mut next_index = Some(0);
+        while let Some(from_index) = next_index {
+            let limits = Limits {
+                from_index: from_index as usize,
+                limit: None,
+            };
+            let page = self.get_page(&limits).await?;
+            next_index = match page.next_index {
+                Some(next) if next <= from_index => return Err(APIClientError::StalledPagination(from_index).into()),
+                next => next,
+            };
+            let mut blocks = page.items.into_iter();
+            let chain = match &mut chain {
+                Some(chain) => chain,
+                None => {
+                    let genesis = blocks
+                        .next()
+                        .ok_or(MirrorError::InvalidBlock(0, InvalidBlockErr::GenesisBlockNotFound))?;
+                    let index = genesis.index;
+                    let genesis_chain = Chain::with_genesis(genesis).map_err(|error| MirrorError::InvalidBlock(index, error))?;
+                    chain.insert(genesis_chain)
+                }
+            };
+            let blocks: Vec<Block> = blocks.collect();
+            let first_index = blocks.first().map_or(0, |block| block.index);
+            chain
+                .append_batch(blocks)
+                .map_err(|(offset, error)| MirrorError::InvalidBlock(first_index + offset as u64, error))?;
+        }
+        chain.ok_or(MirrorError::InvalidBlock(0, InvalidBlockErr::GenesisBlockNotFound))
+    }
+    // Appends whatever the remote has past our tip. A first block that does
+    // not link to our tip means the chains forked, not that it is invalid.
+    pub async fn sync_into(&self, chain: &mut Chain) -> Result<usize, SyncError> {
+        let local = chain.info();
+        let missing = self.get_blocks_after(local.height).await?;
+        match missing.first() {
+            None => return Ok(0),
+            Some(first) if local.height > 0 && first.previous_hash != local.last_hash => {
+                return Err(SyncError::Diverged { at_index: first.index });
             }
-            _ => {
-                let api_error: APIErrorAndReason = response.body_json().await.unwrap();
-                let error: InvalidBlockErr = api_error.into();
-                Err(error)
+            Some(_) => {}
+        }
+        chain.append_batch(missing).map_err(|(offset, error)| SyncError::Rejected {
+            at_index: local.height + offset as u64,
+            source: error,
+        })
+    }
+    // Yields blocks as their lines arrive instead of buffering the whole chain.
+    pub async fn stream_blocks(
+        &self,
+        from_index: usize,
+    ) -> Result<impl Stream<Item = Result<Block, APIClientError>>, APIClientError> {
+        let request = self.http.get(self.url(&format!("/blocks?from_index={}", from_index)))
+            .header("Accept", "application/x-ndjson");
+        let response: Response = self.send(request).await?;
+        if !response.status().is_success() {
+            return Err(APIClientError::UnexpectedStatus(response.status().into()));
+        }
+        let blocks = response
+            .lines()
+            .filter(|line| future::ready(!matches!(line, Ok(line) if line.is_empty())))
+            .map(|line| {
+                let line = line.map_err(|error| APIClientError::Transport(error.into()))?;
+                serde_json::from_str(&line).map_err(|error| APIClientError::Decode(error.to_string()))
+            });
+        Ok(blocks)
+    }
+    // Server-sent events: `data:` lines accumulate until a blank line ends the
+    // event. Comments (heartbeats) and other fields are skipped. The stream
+    // ends when the server closes; a broken connection is yielded as an error.
+    pub async fn subscribe_blocks(
+        &self,
+        from_index: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<Block, APIClientError>>, APIClientError> {
+        let path = match from_index {
+            Some(from_index) => format!("/blocks/stream?from_index={}", from_index),
+            None => String::from("/blocks/stream"),
+        };
+        let request = self.http.get(self.url(&path)).header("Accept", "text/event-stream");
+        let response: Response = self.send(request).await?;
+        if !response.status().is_success() {
+            return Err(APIClientError::UnexpectedStatus(response.status().into()));
+        }
+        let blocks = response
+            .lines()
+            .scan(String::new(), |data, line| {
+                let event = match line {
+                    Err(error) => Some(Err(APIClientError::Transport(error.into()))),
+                    Ok(line) if line.is_empty() && !data.is_empty() => Some(
+                        serde_json::from_str(&std::mem::take(data))
+                            .map_err(|error| APIClientError::Decode(error.to_string())),
+                    ),
+                    Ok(line) => {
+                        if let Some(value) = line.strip_prefix("data:") {
+                            if !data.is_empty() {
+                                data.push('\n');
+                            }
+                            data.push_str(value.strip_prefix(' ').unwrap_or(value));
+                        }
+                        None
+                    }
+                };
+                future::ready(Some(event))
+            })
+            .filter_map(future::ready);
+        Ok(blocks)
+    }
+    pub async fn get_height(&self) -> Result<u64, APIClientError> {
+        let response: Response = self.send(self.http.head(self.url("/blocks"))).await?;
+        if !response.status().is_success() {
+            return Err(APIClientError::UnexpectedStatus(response.status().into()));
+        }
+        let height = response
+            .header(CHAIN_HEIGHT_HEADER)
+            .and_then(|values| values.as_str().parse().ok())
+            .ok_or_else(|| APIClientError::Decode(format!("missing or invalid {} header", CHAIN_HEIGHT_HEADER)))?;
+        Ok(height)
+    }
+    pub async fn get_last_block(&self) -> Result<Block, APIClientError> {
+        let mut response: Response = self.send(self.http.get(self.url("/blocks/last"))).await?;
+        if response.status() == StatusCode::NotFound {
+            return Err(APIClientError::EmptyChain);
+        }
+        read_json(&mut response).await
+    }
+    pub async fn get_block(&self, index: u64) -> Result<Option<Block>, APIClientError> {
+        let mut response: Response = self.send(self.http.get(self.url(&format!("/blocks/{}", index)))).await?;
+        read_optional_json(&mut response).await
+    }
+    pub async fn get_block_by_hash(&self, hash: &str) -> Result<Option<Block>, APIClientError> {
+        let path = format!("/blocks/hash/{}", encode_path_segment(hash));
+        let mut response: Response = self.send(self.http.get(self.url(&path))).await?;
+        read_optional_json(&mut response).await
+    }
+    pub async fn get_page(&self, limits: &Limits) -> Result<PagedBlockList, APIClientError> {
+        let mut response: Response = self.send(self.http.get(self.url(&format!("/blocks?{}", limits.as_query())))).await?;
+        read_json(&mut response).await
+    }
+    // The node caps every page, so the rest of the chain comes from next_index.
+    pub async fn get_blocks(&self, from_index: usize) -> Result<BlockList, APIClientError> {
+        let items = self.get_blocks_after(from_index as u64).await?;
+        Ok(BlockList {
+            items: self.verified(items)?,
+        })
+    }
+    pub async fn send_block(&self, block: Block) -> Result<Block, APIClientError> {
+        block
+            .validate_size(self.max_block_bytes)
+            .map_err(|too_large| APIClientError::Block(InvalidBlockErr::DataTooLarge(too_large.size, too_large.max)))?;
+        let request = self.http.post(self.url("/blocks")).body_json(&block)?;
+        let mut response: Response = self.send(request).await?;
+        match read_json(&mut response).await {
+            Err(APIClientError::Api(error)) => match InvalidBlockErr::from(error.clone()) {
+                InvalidBlockErr::Unknown { .. } => Err(APIClientError::Api(error)),
+                rejected => Err(APIClientError::Block(rejected)),
+            },
+            result => result,
+        }
+    }
+    // Someone else appending between our read of the tip and our post shows
+    // up as a link error; those are retried on the fresh tip.
+    pub async fn generate_and_send(&self, message: &str, max_retries: usize) -> Result<Block, APIClientError> {
+        let mut retries = 0;
+        loop {
+            let tip = self.get_last_block().await?;
+            match self.send_block(tip.generate_next(String::from(message))).await {
+                Err(APIClientError::Block(InvalidBlockErr::NotCorrelated(..) | InvalidBlockErr::HashNotMatching(..)))
+                    if retries < max_retries =>
+                {
+                    retries += 1;
+                }
+                result => return result,
             }
         }
     }
-    async fn send_peer(&self, peer: MemberEntry) -> Result<MemberEntry, EntryRejectedErr> {
-        let mut response: Response = surf::post(format!("{}/peers", &self.host_url))
-            .body_json(&peer)
-            .unwrap()
-            .await
-            .unwrap();
-        match response.status().is_success() {
-            true => {
-                let confirmed: MemberEntry = response.body_json().await.unwrap();
-                Ok(confirmed)
+    // One failing peer never stops the others; every peer ends up in exactly
+    // one list of the report.
+    pub async fn broadcast_block(peers: &PeerList, block: Block) -> BroadcastReport {
+        let outcomes: Vec<(String, Result<Block, APIClientError>)> = futures::stream::iter(peers.items.iter())
+            .map(|member| {
+                let block = block.clone();
+                async move {
+                    let client = APIClient::new(member.peer.clone());
+                    (member.peer.clone(), client.send_block(block).await)
+                }
+            })
+            .buffer_unordered(BROADCAST_CONCURRENCY)
+            .collect()
+            .await;
+        let mut report = BroadcastReport::default();
+        for (peer, outcome) in outcomes {
+            match outcome {
+                Ok(_) => report.accepted.push(peer),
+                Err(APIClientError::Block(error)) => report.rejected.push((peer, error)),
+                Err(_) => report.unreachable.push(peer),
             }
-            _ => {
-                let error: APIErrorAndReason = response.body_json().await.unwrap();
-                let nat_error: EntryRejectedErr = error.into();
-                match nat_error {
-                    EntryRejectedErr::AlreadyPresent(confirmed) => Ok(confirmed),
-                    _ => Err(nat_error),
+        }
+        report
+    }
+    // Servers without the batch route get the blocks one by one, stopping at
+    // the first rejection. Offsets count from the lowest index sent.
+    pub async fn send_blocks(&self, blocks: Vec<Block>) -> Result<usize, APIClientError> {
+        let list = BlockList { items: blocks }.sorted();
+        let request = self.http.post(self.url("/blocks/batch")).body_json(&list)?;
+        let mut response: Response = self.send(request).await?;
+        if matches!(response.status(), StatusCode::NotFound | StatusCode::MethodNotAllowed) {
+            return self.send_blocks_one_by_one(list.items).await;
+        }
+        match read_json::<BatchReport>(&mut response).await {
+            Ok(report) => Ok(report.appended),
+            Err(APIClientError::Api(error)) => match error.batch_offset() {
+                Some((offset, located)) => Err(APIClientError::BatchRejected {
+                    offset: offset,
+                    block_index: located
+                        .block_index()
+                        .or_else(|| list.items.get(offset).map(|block| block.index))
+                        .unwrap_or_default(),
+                    source: located.into(),
+                }),
+                None => Err(APIClientError::Api(error)),
+            },
+            Err(error) => Err(error),
+        }
+    }
+    async fn send_blocks_one_by_one(&self, blocks: Vec<Block>) -> Result<usize, APIClientError> {
+        let mut appended = 0;
+        for (offset, block) in blocks.into_iter().enumerate() {
+            let block_index = block.index;
+            match self.send_block(block).await {
+                Ok(_) => appended += 1,
+                Err(APIClientError::Block(error)) => {
+                    return Err(APIClientError::BatchRejected {
+                        offset: offset,
+                        block_index: block_index,
+                        source: error,
+                    })
                 }
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(appended)
+    }
+    pub async fn get_status(&self) -> Result<NodeStatus, APIClientError> {
+        let mut response: Response = self.send(self.http.get(self.url("/status"))).await?;
+        read_json(&mut response).await
+    }
+    pub async fn get_peers(&self) -> Result<PeerList, APIClientError> {
+        let mut response: Response = self.send(self.http.get(self.url("/peers"))).await?;
+        read_json(&mut response).await
+    }
+    pub async fn register_peer(&self, peer: MemberEntry) -> Result<PeerRegistration, APIClientError> {
+        let request = self.http.post(self.url("/peers")).body_json(&peer)?;
+        let mut response: Response = self.send(request).await?;
+        match read_json(&mut response).await {
+            Ok(added) => Ok(PeerRegistration::Added(added)),
+            // Conflict is only ever used for an entry that is already listed.
+            Err(APIClientError::Api(error)) if error.status() == StatusCode::Conflict => {
+                Ok(PeerRegistration::AlreadyMember(peer))
             }
+            Err(APIClientError::Api(error)) => match EntryRejectedErr::from(error.clone()) {
+                EntryRejectedErr::Unknown { .. } => Err(APIClientError::Api(error)),
+                rejected => Err(APIClientError::Peer(rejected)),
+            },
+            Err(error) => Err(error),
+        }
+    }
+    // For callers that do not care whether the peer was new.
+    pub async fn send_peer(&self, peer: MemberEntry) -> Result<MemberEntry, APIClientError> {
+        match self.register_peer(peer).await? {
+            PeerRegistration::Added(entry) | PeerRegistration::AlreadyMember(entry) => Ok(entry),
         }
     }
 }
 
+// Error bodies the node did not produce itself (proxies, HTML pages) only
+// tell us the status.
+async fn read_json<T: DeserializeOwned>(response: &mut Response) -> Result<T, APIClientError> {
+    if response.status().is_success() {
+        return response
+            .body_json()
+            .await
+            .map_err(|error| APIClientError::Decode(error.to_string()));
+    }
+    let status = response.status().into();
+    match response.body_json::<APIErrorAndReason>().await {
+        Ok(error) => Err(APIClientError::Api(error)),
+        Err(_) => Err(APIClientError::UnexpectedStatus(status)),
+    }
+}
+
+// Not a real UUID, only needs to be unlikely to collide between clients.
+fn random_node_id() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(get_epoch_ms());
+    let high = hasher.finish();
+    hasher.write_u64(high);
+    let low = hasher.finish();
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0xffff,
+        low >> 48,
+        low & 0xffff_ffff_ffff
+    )
+}
+
+async fn read_optional_json<T: DeserializeOwned>(response: &mut Response) -> Result<Option<T>, APIClientError> {
+    if response.status() == StatusCode::NotFound {
+        return Ok(None);
+    }
+    read_json(response).await.map(Some)
+}
+
+fn verify_sequence(items: &[Block]) -> Result<(), APIClientError> {
+    for pair in items.windows(2) {
+        pair[1]
+            .verify_against(&pair[0])
+            .map_err(|source| APIClientError::InvalidSequence {
+                at_index: pair[1].index,
+                source: source,
+            })?;
+    }
+    Ok(())
+}
+
+// Hashes are hex, but anything handed in by a caller must not be able to
+// escape its path segment.
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
     use crate::blockchain::InvalidBlockErr;
     use wiremock::http::Method;
-    use wiremock::matchers::{method, path};
+    use wiremock::matchers::{header, method, path, query_param};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
     // Start a background HTTP server on a random local port
@@ -92,7 +645,7 @@ mod tests {
 
         // Arrange the behaviour of the MockServer adding a Mock:
         Mock::given(method("GET"))
-            .and(path("/blocks"))
+            .and(path("/v1/blocks"))
             .respond_with(ResponseTemplate::new(200).set_body_json(sample))
             // Mounting the mock on the mock server - it's now effective!
             .mount(&mock_server)
@@ -106,7 +659,7 @@ mod tests {
 
         // Arrange the behaviour of the MockServer adding a Mock:
         Mock::given(method("POST"))
-            .and(path("/blocks"))
+            .and(path("/v1/blocks"))
             .respond_with(ResponseTemplate::new(201).set_body_json(block))
             // Mounting the mock on the mock server - it's now effective!
             .mount(&mock_server)
@@ -119,7 +672,7 @@ mod tests {
 
         // Arrange the behaviour of the MockServer adding a Mock:
         Mock::given(method("POST"))
-            .and(path("/peers"))
+            .and(path("/v1/peers"))
             .respond_with(ResponseTemplate::new(201).set_body_json(peer))
             // Mounting the mock on the mock server - it's now effective!
             .mount(&mock_server)
@@ -132,8 +685,8 @@ mod tests {
 
         // Arrange the behaviour of the MockServer adding a Mock:
         Mock::given(method("POST"))
-            .and(path("/blocks"))
-            .respond_with(ResponseTemplate::new(400).set_body_json(error))
+            .and(path("/v1/blocks"))
+            .respond_with(ResponseTemplate::new(error.status() as u16).set_body_json(error))
             // Mounting the mock on the mock server - it's now effective!
             .mount(&mock_server)
             .await;
@@ -145,8 +698,8 @@ mod tests {
 
         // Arrange the behaviour of the MockServer adding a Mock:
         Mock::given(method("POST"))
-            .and(path("/peers"))
-            .respond_with(ResponseTemplate::new(400).set_body_json(error))
+            .and(path("/v1/peers"))
+            .respond_with(ResponseTemplate::new(error.status() as u16).set_body_json(error))
             // Mounting the mock on the mock server - it's now effective!
             .mount(&mock_server)
             .await;
@@ -165,18 +718,13 @@ mod tests {
     #[async_std::test]
     async fn get_all_blocks_being_two() -> Result<(), Box<dyn std::error::Error>> {
         // Start a background HTTP server on a random local port
-        let genesis_block = Block {
-            index: 0,
-            previous_hash: String::from(""),
-            timestamp: get_epoch_ms(),
-            data: message_as_json("Genesis block"),
-        };
-        let second_block = Block {
-            index: 1,
-            previous_hash: genesis_block.hash(),
-            timestamp: genesis_block.timestamp + 100,
-            data: message_as_json("Second block data"),
-        };
+        let genesis_block = Block::builder()
+            .data_message("Genesis block")
+            .build();
+        let second_block = Block::builder()
+            .after(&genesis_block)
+            .data_message("Second block data")
+            .build();
         let items: Vec<Block> = [genesis_block, second_block].to_vec();
         let mock_server = arrange_server_mock_get_blocks(Some(items)).await;
 
@@ -189,18 +737,13 @@ mod tests {
     #[async_std::test]
     async fn get_blocks_from_index_1() -> Result<(), Box<dyn std::error::Error>> {
         // Start a background HTTP server on a random local port
-        let genesis_block = Block {
-            index: 0,
-            previous_hash: String::from(""),
-            timestamp: get_epoch_ms(),
-            data: message_as_json("Genesis block"),
-        };
-        let second_block = Block {
-            index: 1,
-            previous_hash: genesis_block.hash(),
-            timestamp: genesis_block.timestamp + 100,
-            data: message_as_json("Second block data"),
-        };
+        let genesis_block = Block::builder()
+            .data_message("Genesis block")
+            .build();
+        let second_block = Block::builder()
+            .after(&genesis_block)
+            .data_message("Second block data")
+            .build();
         let items: Vec<Block> = [genesis_block, second_block].to_vec();
         let mock_server = arrange_server_mock_get_blocks(Some(items)).await;
 
@@ -216,15 +759,211 @@ mod tests {
         Ok(())
     }
 
+    #[async_std::test]
+    async fn get_blocks_follows_capped_pages() -> Result<(), Box<dyn std::error::Error>> {
+        let mut blocks = vec![Block::builder().data_message("Genesis block").build()];
+        for n in 1..6 {
+            let next = blocks[n - 1].generate_next(format!("Block {}", n));
+            blocks.push(next);
+        }
+        let mock_server = MockServer::start().await;
+        for (from_index, next_index) in [(1, Some(3)), (3, Some(5)), (5, None)] {
+            let page = PagedBlockList {
+                items: blocks[from_index..].iter().take(2).cloned().collect(),
+                total: blocks.len() as u64,
+                from_index: from_index as u64,
+                next_index: next_index,
+            };
+            Mock::given(method("GET"))
+                .and(path("/v1/blocks"))
+                .and(query_param("from_index", from_index.to_string()))
+                .respond_with(ResponseTemplate::new(200).set_body_json(page))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+        }
+        let client = APIClient::new(mock_server.uri());
+        assert_eq!(client.get_blocks(1).await?.items, blocks[1..]);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn requests_target_the_configured_api_version() -> Result<(), Box<dyn std::error::Error>> {
+        let genesis_block = Block::builder().data_message("Genesis block").build();
+        let mock_server = arrange_server_mock_receive_block(genesis_block.clone()).await;
+        APIClient::new(mock_server.uri()).send_block(genesis_block.clone()).await.unwrap();
+        let unversioned = APIClient::new(mock_server.uri()).with_api_version(None);
+        assert!(unversioned.send_block(genesis_block).await.is_err());
+        let received_requests = mock_server.received_requests().await.unwrap();
+        assert_eq!(received_requests.len(), 2);
+        assert_eq!(received_requests[0].url.path(), "/v1/blocks");
+        assert_eq!(received_requests[1].url.path(), "/blocks");
+        Ok(())
+    }
+
+    // The mock server leaves out Content-Length on bodiless HEAD replies and
+    // the client would then wait for the connection to close.
+    fn head_response() -> ResponseTemplate {
+        ResponseTemplate::new(200).insert_header("Content-Length", "0")
+    }
+
+    #[async_std::test]
+    async fn get_height_uses_head() -> Result<(), Box<dyn std::error::Error>> {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/v1/blocks"))
+            .respond_with(head_response().insert_header(CHAIN_HEIGHT_HEADER, "42"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        assert_eq!(client.get_height().await?, 42);
+        let received_requests = mock_server.received_requests().await.unwrap();
+        assert_eq!(received_requests[0].method, Method::Head);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn get_height_without_header_fails() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .respond_with(head_response())
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        assert!(client.get_height().await.is_err());
+    }
+
+    async fn arrange_server_mock_paged_blocks(blocks: &[Block], page_size: usize) -> MockServer {
+        let mock_server = MockServer::start().await;
+        for from_index in (0..blocks.len()).step_by(page_size) {
+            let items = blocks[from_index..].iter().take(page_size).cloned().collect();
+            let next_index = from_index + page_size;
+            let page = PagedBlockList {
+                items: items,
+                total: blocks.len() as u64,
+                from_index: from_index as u64,
+                next_index: Some(next_index as u64).filter(|_| next_index < blocks.len()),
+            };
+            Mock::given(method("GET"))
+                .and(path("/v1/blocks"))
+                .and(query_param("from_index", from_index.to_string()))
+                .and(query_param("limit", page_size.to_string()))
+                .respond_with(ResponseTemplate::new(200).set_body_json(page))
+                .mount(&mock_server)
+                .await;
+        }
+        mock_server
+    }
+
+    #[async_std::test]
+    async fn get_blocks_paged_follows_every_page() -> Result<(), Box<dyn std::error::Error>> {
+        let mut blocks = vec![Block::builder().data_message("Genesis block").build()];
+        for n in 1..9 {
+            let next = blocks[n - 1].generate_next(format!("Block {}", n));
+            blocks.push(next);
+        }
+        let mock_server = arrange_server_mock_paged_blocks(&blocks, 3).await;
+        let client = APIClient::new(mock_server.uri());
+        assert_eq!(client.get_blocks_paged(0, 3).await?, blocks);
+        assert_eq!(mock_server.received_requests().await.unwrap().len(), 3);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn get_blocks_paged_stops_on_a_stalled_page() {
+        let genesis_block = Block::builder().data_message("Genesis block").build();
+        let page = PagedBlockList {
+            items: vec![genesis_block],
+            total: 5,
+            from_index: 0,
+            next_index: Some(0),
+        };
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/blocks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(page))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        assert!(matches!(client.get_blocks_paged(0, 2).await, Err(APIClientError::StalledPagination(0))));
+    }
+
+    #[async_std::test]
+    async fn get_blocks_in_pages_of_three() -> Result<(), Box<dyn std::error::Error>> {
+        let mut blocks = vec![Block::builder().data_message("Genesis block").build()];
+        for n in 1..10 {
+            let next = blocks[n - 1].generate_next(format!("Block {}", n));
+            blocks.push(next);
+        }
+        let mock_server = arrange_server_mock_paged_blocks(&blocks, 3).await;
+        let client = APIClient::new(mock_server.uri());
+        let mut limits = Limits {
+            from_index: 0,
+            limit: Some(3),
+        };
+        let mut received: Vec<Block> = vec![];
+        loop {
+            let page = client.get_page(&limits).await?;
+            let page_len = page.items.len();
+            received.extend(page.items);
+            match page.next_index {
+                Some(next_index) => {
+                    assert_eq!(page_len, 3);
+                    limits.from_index = next_index as usize;
+                }
+                None => {
+                    assert_eq!(page_len, 1);
+                    break;
+                }
+            }
+        }
+        assert_eq!(received, blocks);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn stream_blocks_as_ndjson() -> Result<(), Box<dyn std::error::Error>> {
+        let genesis_block = Block::builder().data_message("Genesis block").build();
+        let mut blocks = vec![genesis_block];
+        for n in 0..5 {
+            let next = Block::builder()
+                .after(blocks.last().unwrap())
+                .data_message(&format!("block {}", n))
+                .build();
+            blocks.push(next);
+        }
+        let body: String = blocks
+            .iter()
+            .map(|block| serde_json::to_string(block).unwrap() + "\n")
+            .collect();
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/blocks"))
+            .and(header("Accept", "application/x-ndjson"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/x-ndjson"))
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        let received: Vec<Block> = client
+            .stream_blocks(0)
+            .await?
+            .map(|block| block.unwrap())
+            .collect()
+            .await;
+        assert_eq!(received, blocks);
+        Ok(())
+    }
+
     #[async_std::test]
     async fn test_sent_block_accepted() -> Result<(), Box<dyn std::error::Error>> {
         // Start a background HTTP server on a random local port
-        let second_block = Block {
-            index: 1,
-            previous_hash: String::from("not important"),
-            timestamp: get_epoch_ms(),
-            data: message_as_json("Second block data"),
-        };
+        let second_block = Block::builder()
+            .index(1)
+            .previous_hash("not important")
+            .data_message("Second block data")
+            .build();
         let mock_server = arrange_server_mock_receive_block(second_block.clone()).await;
 
         let client = APIClient::new(mock_server.uri());
@@ -239,6 +978,23 @@ mod tests {
         Ok(())
     }
 
+    #[async_std::test]
+    async fn test_sent_block_with_token() -> Result<(), Box<dyn std::error::Error>> {
+        let block = Block::builder().index(1).previous_hash("abc").data_message("signed").build();
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/blocks"))
+            .and(header("Authorization", "Bearer s3cr3t"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(block.clone()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri()).with_token("s3cr3t");
+        assert_eq!(client.send_block(block.clone()).await.unwrap(), block);
+        mock_server.verify().await;
+        Ok(())
+    }
+
     #[async_std::test]
     async fn test_sent_block_rejected_because_hash() -> Result<(), ()> {
         // Start a background HTTP server on a random local port
@@ -248,12 +1004,10 @@ mod tests {
         );
         let api_error = APIErrorAndReason::from(error.clone());
 
-        let second_block = Block {
-            index: 0,
-            previous_hash: String::from("reallydoesntmatter"),
-            timestamp: get_epoch_ms(),
-            data: message_as_json("Sample second block"),
-        };
+        let second_block = Block::builder()
+            .previous_hash("reallydoesntmatter")
+            .data_message("Sample second block")
+            .build();
         let mock_server = arrange_server_mock_reject_block(api_error).await;
 
         let client = APIClient::new(mock_server.uri());
@@ -263,7 +1017,7 @@ mod tests {
         let received_request = &received_requests[0];
         assert_eq!(received_requests.len(), 1);
         assert_eq!(received_request.method, Method::Post);
-        assert_eq!(failure, error);
+        assert!(matches!(failure, APIClientError::Block(rejected) if rejected == error));
         Ok(())
     }
 
@@ -273,12 +1027,10 @@ mod tests {
         let error = InvalidBlockErr::NotPosterior(1000, 2000);
         let api_error = APIErrorAndReason::from(error.clone());
 
-        let second_block = Block {
-            index: 0,
-            previous_hash: String::from("reallydoesntmatter"),
-            timestamp: get_epoch_ms(),
-            data: message_as_json("Sample second block"),
-        };
+        let second_block = Block::builder()
+            .previous_hash("reallydoesntmatter")
+            .data_message("Sample second block")
+            .build();
         let mock_server = arrange_server_mock_reject_block(api_error).await;
 
         let client = APIClient::new(mock_server.uri());
@@ -288,7 +1040,7 @@ mod tests {
         let received_request = &received_requests[0];
         assert_eq!(received_requests.len(), 1);
         assert_eq!(received_request.method, Method::Post);
-        assert_eq!(failure, error);
+        assert!(matches!(failure, APIClientError::Block(rejected) if rejected == error));
         Ok(())
     }
 
@@ -298,12 +1050,10 @@ mod tests {
         let error = InvalidBlockErr::NotCorrelated(1, 2);
         let api_error: APIErrorAndReason = APIErrorAndReason::from(error.clone());
 
-        let second_block = Block {
-            index: 0,
-            previous_hash: String::from("reallydoesntmatter"),
-            timestamp: get_epoch_ms(),
-            data: message_as_json("Sample second block"),
-        };
+        let second_block = Block::builder()
+            .previous_hash("reallydoesntmatter")
+            .data_message("Sample second block")
+            .build();
         let mock_server = arrange_server_mock_reject_block(api_error).await;
 
         let client = APIClient::new(mock_server.uri());
@@ -313,66 +1063,957 @@ mod tests {
         let received_request = &received_requests[0];
         assert_eq!(received_requests.len(), 1);
         assert_eq!(received_request.method, Method::Post);
-        assert_eq!(failure, error);
+        assert!(matches!(failure, APIClientError::Block(rejected) if rejected == error));
         Ok(())
     }
 
     #[async_std::test]
-    async fn test_sent_peer_accepted() -> Result<(), Box<dyn std::error::Error>> {
+    async fn test_sent_block_rejected_because_data_size() -> Result<(), ()> {
         // Start a background HTTP server on a random local port
-        let new_member = MemberEntry {
-            peer: String::from("ws://localhost:5055"),
-        };
-        let mock_server = arrange_server_mock_receive_peer(new_member.clone()).await;
+        let error = InvalidBlockErr::DataTooLarge(70000, 65536);
+        let api_error: APIErrorAndReason = APIErrorAndReason::from(error.clone());
+
+        let second_block = Block::builder()
+            .previous_hash("reallydoesntmatter")
+            .data_message("Sample second block")
+            .build();
+        let mock_server = arrange_server_mock_reject_block(api_error).await;
+
         let client = APIClient::new(mock_server.uri());
 
-        let expected_new_member = new_member.clone();
-        let result: MemberEntry = client.send_peer(new_member).await.unwrap();
+        let failure = client.send_block(second_block).await.unwrap_err();
         let received_requests = mock_server.received_requests().await.unwrap();
-        let received_request = &received_requests[0];
-        assert_eq!(result, expected_new_member);
         assert_eq!(received_requests.len(), 1);
-        assert_eq!(received_request.method, Method::Post);
+        assert!(matches!(failure, APIClientError::Block(rejected) if rejected == error));
         Ok(())
     }
 
     #[async_std::test]
-    async fn test_sent_peer_rejected_malformed() -> Result<(), Box<dyn std::error::Error>> {
-        // Start a background HTTP server on a random local port
-        let url = String::from("ws://localhost:5055");
-        let new_member = MemberEntry {
-            peer: url.clone()
-        };
-        let error = EntryRejectedErr::InvalidURL(url.clone());
+    async fn test_sent_block_rejected_because_empty_data() -> Result<(), ()> {
+        let error = InvalidBlockErr::EmptyData;
         let api_error: APIErrorAndReason = APIErrorAndReason::from(error.clone());
-        let mock_server = arrange_server_mock_reject_peer(api_error).await;
+        let second_block = Block::builder().previous_hash("reallydoesntmatter").build();
+        let mock_server = arrange_server_mock_reject_block(api_error).await;
+
         let client = APIClient::new(mock_server.uri());
 
-        let failure = client.send_peer(new_member).await.unwrap_err();
-        let received_requests = mock_server.received_requests().await.unwrap();
-        let received_request = &received_requests[0];
-        assert!(matches!(failure, EntryRejectedErr::InvalidURL(error_url) if url == error_url));
-        assert_eq!(received_requests.len(), 1);
-        assert_eq!(received_request.method, Method::Post);
+        let failure = client.send_block(second_block).await.unwrap_err();
+        assert!(matches!(failure, APIClientError::Block(rejected) if rejected == error));
         Ok(())
     }
 
     #[async_std::test]
-    async fn test_sent_peer_already_present() -> Result<(), Box<dyn std::error::Error>> {
+    async fn test_sent_block_rejected_because_own_hash() -> Result<(), ()> {
         // Start a background HTTP server on a random local port
-        let new_member = MemberEntry {
-            peer: String::from("ws://localhost:5055"),
-        };
-        let mock_server = arrange_server_mock_receive_peer(new_member.clone()).await;
+        let second_block = Block::builder()
+            .previous_hash("reallydoesntmatter")
+            .data_message("Sample second block")
+            .build();
+        let error = InvalidBlockErr::SelfHashMismatch(String::from("c4f3c4f3c4f3"), second_block.hash());
+        let api_error: APIErrorAndReason = APIErrorAndReason::from(error.clone());
+        let mock_server = arrange_server_mock_reject_block(api_error).await;
+
         let client = APIClient::new(mock_server.uri());
 
-        let expected_new_member = new_member.clone();
-        let result: MemberEntry = client.send_peer(new_member).await.unwrap();
+        let failure = client.send_block(second_block).await.unwrap_err();
         let received_requests = mock_server.received_requests().await.unwrap();
-        let received_request = &received_requests[0];
-        assert_eq!(result, expected_new_member);
         assert_eq!(received_requests.len(), 1);
-        assert_eq!(received_request.method, Method::Post);
+        assert!(matches!(failure, APIClientError::Block(rejected) if rejected == error));
         Ok(())
     }
+
+    #[async_std::test]
+    async fn test_sent_block_rejected_because_work() -> Result<(), ()> {
+        // Start a background HTTP server on a random local port
+        let error = InvalidBlockErr::InsufficientWork(3);
+        let api_error: APIErrorAndReason = APIErrorAndReason::from(error.clone());
+        let second_block = Block::builder()
+            .previous_hash("reallydoesntmatter")
+            .data_message("Sample second block")
+            .build();
+        let mock_server = arrange_server_mock_reject_block(api_error).await;
+
+        let client = APIClient::new(mock_server.uri());
+
+        let failure = client.send_block(second_block).await.unwrap_err();
+        assert!(matches!(failure, APIClientError::Block(rejected) if rejected == error));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn test_sent_block_too_large_fails_fast() -> Result<(), ()> {
+        let block = Block::builder().index(1).previous_hash("abc").data_message("big").build();
+        let limit = block.size_bytes() - 1;
+        let client = APIClient::new(String::from("http://127.0.0.1:9")).with_max_block_bytes(limit);
+        let obtained_error = client.send_block(block.clone()).await.unwrap_err();
+        let expected = InvalidBlockErr::DataTooLarge(block.size_bytes(), limit);
+        assert!(matches!(obtained_error, APIClientError::Block(rejected) if rejected == expected));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn test_sent_block_at_size_limit() -> Result<(), Box<dyn std::error::Error>> {
+        let block = Block::builder().index(1).previous_hash("abc").data_message("fits").build();
+        let mock_server = arrange_server_mock_receive_block(block.clone()).await;
+        let client = APIClient::new(mock_server.uri()).with_max_block_bytes(block.size_bytes());
+        let confirmed = client.send_block(block.clone()).await.unwrap();
+        assert_eq!(confirmed, block);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn test_sent_peer_accepted() -> Result<(), Box<dyn std::error::Error>> {
+        // Start a background HTTP server on a random local port
+        let new_member = MemberEntry {
+            peer: String::from("ws://localhost:5055"),
+        };
+        let mock_server = arrange_server_mock_receive_peer(new_member.clone()).await;
+        let client = APIClient::new(mock_server.uri());
+
+        let expected_new_member = new_member.clone();
+        let result: MemberEntry = client.send_peer(new_member).await.unwrap();
+        let received_requests = mock_server.received_requests().await.unwrap();
+        let received_request = &received_requests[0];
+        assert_eq!(result, expected_new_member);
+        assert_eq!(received_requests.len(), 1);
+        assert_eq!(received_request.method, Method::Post);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn test_sent_peer_rejected_malformed() -> Result<(), Box<dyn std::error::Error>> {
+        // Start a background HTTP server on a random local port
+        let url = String::from("ws://localhost:5055");
+        let new_member = MemberEntry {
+            peer: url.clone()
+        };
+        let error = EntryRejectedErr::InvalidURL(url.clone());
+        let api_error: APIErrorAndReason = APIErrorAndReason::from(error.clone());
+        let mock_server = arrange_server_mock_reject_peer(api_error).await;
+        let client = APIClient::new(mock_server.uri());
+
+        let failure = client.send_peer(new_member).await.unwrap_err();
+        let received_requests = mock_server.received_requests().await.unwrap();
+        let received_request = &received_requests[0];
+        assert!(matches!(failure, APIClientError::Peer(rejected) if rejected == error));
+        assert_eq!(received_requests.len(), 1);
+        assert_eq!(received_request.method, Method::Post);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn test_sent_peer_already_present() -> Result<(), Box<dyn std::error::Error>> {
+        // Start a background HTTP server on a random local port
+        let new_member = MemberEntry {
+            peer: String::from("ws://localhost:5055"),
+        };
+        let mock_server = arrange_server_mock_receive_peer(new_member.clone()).await;
+        let client = APIClient::new(mock_server.uri());
+
+        let expected_new_member = new_member.clone();
+        let result: MemberEntry = client.send_peer(new_member).await.unwrap();
+        let received_requests = mock_server.received_requests().await.unwrap();
+        let received_request = &received_requests[0];
+        assert_eq!(result, expected_new_member);
+        assert_eq!(received_requests.len(), 1);
+        assert_eq!(received_request.method, Method::Post);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn get_last_block_returns_the_tip() -> Result<(), Box<dyn std::error::Error>> {
+        let tip = Block::builder().index(4).previous_hash("abc").data_message("Tip").build();
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/blocks/last"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(tip.clone()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        assert_eq!(client.get_last_block().await?, tip);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn get_last_block_of_empty_chain() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/blocks/last"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        assert!(matches!(client.get_last_block().await, Err(APIClientError::EmptyChain)));
+    }
+
+    async fn arrange_server_mock_get_peers(template: ResponseTemplate) -> MockServer {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/peers"))
+            .respond_with(template)
+            .mount(&mock_server)
+            .await;
+        mock_server
+    }
+
+    #[async_std::test]
+    async fn get_peers_being_empty() -> Result<(), Box<dyn std::error::Error>> {
+        let empty = PeerList { items: vec![] };
+        let mock_server = arrange_server_mock_get_peers(ResponseTemplate::new(200).set_body_json(empty)).await;
+        let client = APIClient::new(mock_server.uri());
+        assert!(client.get_peers().await?.items.is_empty());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn get_peers_being_two() -> Result<(), Box<dyn std::error::Error>> {
+        let peers = PeerList {
+            items: vec![
+                MemberEntry { peer: String::from("http://localhost:9090") },
+                MemberEntry { peer: String::from("http://localhost:9091") },
+            ],
+        };
+        let mock_server = arrange_server_mock_get_peers(ResponseTemplate::new(200).set_body_json(peers.clone())).await;
+        let client = APIClient::new(mock_server.uri());
+        assert_eq!(client.get_peers().await?, peers);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn get_peers_with_malformed_body() {
+        let template = ResponseTemplate::new(200).set_body_raw(r#"{"items": ["#, "application/json");
+        let mock_server = arrange_server_mock_get_peers(template).await;
+        let client = APIClient::new(mock_server.uri());
+        assert!(matches!(client.get_peers().await, Err(APIClientError::Decode(_))));
+    }
+
+    #[async_std::test]
+    async fn get_block_found_and_missing() -> Result<(), Box<dyn std::error::Error>> {
+        let block = Block::builder().index(2).previous_hash("abc").data_message("Second").build();
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/blocks/2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(block.clone()))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/blocks/3"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(APIErrorAndReason::block_not_found(3, 3)))
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        assert_eq!(client.get_block(2).await?, Some(block));
+        assert_eq!(client.get_block(3).await?, None);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn get_block_by_hash_found_and_missing() -> Result<(), Box<dyn std::error::Error>> {
+        let block = Block::builder().index(2).previous_hash("abc").data_message("Second").build();
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(format!("/v1/blocks/hash/{}", block.hash())))
+            .respond_with(ResponseTemplate::new(200).set_body_json(block.clone()))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        assert_eq!(client.get_block_by_hash(&block.hash()).await?, Some(block));
+        assert_eq!(client.get_block_by_hash("../peers?x=1").await?, None);
+        let received_requests = mock_server.received_requests().await.unwrap();
+        assert_eq!(received_requests[1].url.path(), "/v1/blocks/hash/..%2Fpeers%3Fx%3D1");
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn get_block_with_server_error() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        assert!(matches!(client.get_block(1).await, Err(APIClientError::UnexpectedStatus(500))));
+        assert!(matches!(client.get_block_by_hash("abc").await, Err(APIClientError::UnexpectedStatus(500))));
+    }
+
+    #[async_std::test]
+    async fn hanging_peer_times_out() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(10)))
+            .mount(&mock_server)
+            .await;
+        let timeout = Duration::from_millis(200);
+        let client = APIClient::new(mock_server.uri()).with_timeout(timeout);
+        let started = std::time::Instant::now();
+        assert!(matches!(client.get_all_blocks().await, Err(APIClientError::Timeout(after)) if after == timeout));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    fn arrange_three_blocks() -> Vec<Block> {
+        let genesis_block = Block::builder().data_message("Genesis block").build();
+        let second_block = genesis_block.generate_next(String::from("Second block"));
+        let third_block = second_block.generate_next(String::from("Third block"));
+        vec![genesis_block, second_block, third_block]
+    }
+
+    #[async_std::test]
+    async fn send_blocks_as_a_batch() -> Result<(), Box<dyn std::error::Error>> {
+        let blocks = arrange_three_blocks();
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/blocks/batch"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(BatchReport { appended: 3 }))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        assert_eq!(client.send_blocks(blocks.clone()).await?, 3);
+        let received_requests = mock_server.received_requests().await.unwrap();
+        let sent: BlockList = serde_json::from_slice(&received_requests[0].body)?;
+        assert_eq!(sent.items, blocks);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn send_blocks_falls_back_without_batch_route() -> Result<(), Box<dyn std::error::Error>> {
+        let blocks = arrange_three_blocks();
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/blocks/batch"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/blocks"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(blocks[0].clone()))
+            .expect(3)
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        assert_eq!(client.send_blocks(blocks).await?, 3);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn send_blocks_reports_the_rejected_offset() {
+        let blocks = arrange_three_blocks();
+        let error = InvalidBlockErr::NotPosterior(1000, 2000);
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/blocks/batch"))
+            .respond_with(
+                ResponseTemplate::new(422).set_body_json(APIErrorAndReason::at_batch_offset(1, error.clone())),
+            )
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        let failure = client.send_blocks(blocks).await.unwrap_err();
+        assert!(matches!(failure, APIClientError::BatchRejected { offset: 1, block_index: 1, source } if source == error));
+    }
+
+    #[async_std::test]
+    async fn send_blocks_fallback_stops_at_first_rejection() {
+        let blocks = arrange_three_blocks();
+        let error = InvalidBlockErr::NotCorrelated(1, 0);
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/blocks/batch"))
+            .respond_with(ResponseTemplate::new(405))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/blocks"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(blocks[0].clone()))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/blocks"))
+            .respond_with(ResponseTemplate::new(422).set_body_json(APIErrorAndReason::from(error.clone())))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        let failure = client.send_blocks(blocks).await.unwrap_err();
+        assert!(matches!(failure, APIClientError::BatchRejected { offset: 1, block_index: 1, source } if source == error));
+    }
+
+    async fn arrange_server_mock_continuation(from_index: u64, items: Vec<Block>) -> MockServer {
+        let mock_server = MockServer::start().await;
+        let page = PagedBlockList {
+            total: from_index + items.len() as u64,
+            items: items,
+            from_index: from_index,
+            next_index: None,
+        };
+        Mock::given(method("GET"))
+            .and(path("/v1/blocks"))
+            .and(query_param("from_index", from_index.to_string()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(page))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        mock_server
+    }
+
+    fn arrange_continuation(chain: &Chain, count: usize) -> Vec<Block> {
+        let mut parent = chain.get_last_block().unwrap().clone();
+        let mut blocks = vec![];
+        for n in 0..count {
+            parent = parent.generate_next(format!("Remote block {}", n));
+            blocks.push(parent.clone());
+        }
+        blocks
+    }
+
+    #[async_std::test]
+    async fn sync_into_appends_the_continuation() -> Result<(), Box<dyn std::error::Error>> {
+        let mut chain = Chain::new(String::from("Genesis block"));
+        let continuation = arrange_continuation(&chain, 3);
+        let mock_server = arrange_server_mock_continuation(1, continuation.clone()).await;
+        let client = APIClient::new(mock_server.uri());
+        assert_eq!(client.sync_into(&mut chain).await?, 3);
+        assert_eq!(chain.len(), 4);
+        assert_eq!(chain.get_last_block(), continuation.last());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn sync_into_with_nothing_new() -> Result<(), Box<dyn std::error::Error>> {
+        let mut chain = Chain::new(String::from("Genesis block"));
+        let mock_server = arrange_server_mock_continuation(1, vec![]).await;
+        let client = APIClient::new(mock_server.uri());
+        assert_eq!(client.sync_into(&mut chain).await?, 0);
+        assert_eq!(chain.len(), 1);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn sync_into_reports_divergence() {
+        let mut chain = Chain::new(String::from("Genesis block"));
+        let other = Chain::new(String::from("Another genesis"));
+        let continuation = arrange_continuation(&other, 3);
+        let mock_server = arrange_server_mock_continuation(1, continuation).await;
+        let client = APIClient::new(mock_server.uri());
+        let failure = client.sync_into(&mut chain).await.unwrap_err();
+        assert!(matches!(failure, SyncError::Diverged { at_index: 1 }));
+        assert_eq!(chain.len(), 1);
+    }
+
+    async fn arrange_server_mock_tip(mock_server: &MockServer, tip: &Block) {
+        Mock::given(method("GET"))
+            .and(path("/v1/blocks/last"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(tip.clone()))
+            .mount(mock_server)
+            .await;
+    }
+
+    fn conflict() -> ResponseTemplate {
+        let error = APIErrorAndReason::from(InvalidBlockErr::NotCorrelated(1, 1));
+        ResponseTemplate::new(422).set_body_json(error)
+    }
+
+    #[async_std::test]
+    async fn generate_and_send_on_remote_tip() -> Result<(), Box<dyn std::error::Error>> {
+        let tip = Block::builder().data_message("Genesis block").build();
+        let mock_server = MockServer::start().await;
+        arrange_server_mock_tip(&mock_server, &tip).await;
+        Mock::given(method("POST"))
+            .and(path("/v1/blocks"))
+            .respond_with(|req: &wiremock::Request| ResponseTemplate::new(201).set_body_bytes(req.body.clone()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        let sent = client.generate_and_send("Hello", 2).await?;
+        assert_eq!(sent.index, 1);
+        assert_eq!(sent.previous_hash, tip.hash());
+        assert_eq!(sent.message().unwrap(), "Hello");
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn generate_and_send_retries_after_a_conflict() -> Result<(), Box<dyn std::error::Error>> {
+        let tip = Block::builder().data_message("Genesis block").build();
+        let mock_server = MockServer::start().await;
+        arrange_server_mock_tip(&mock_server, &tip).await;
+        Mock::given(method("POST"))
+            .respond_with(conflict())
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(|req: &wiremock::Request| ResponseTemplate::new(201).set_body_bytes(req.body.clone()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        assert_eq!(client.generate_and_send("Hello", 2).await?.index, 1);
+        let received_requests = mock_server.received_requests().await.unwrap();
+        let tip_reads = received_requests.iter().filter(|req| req.method == Method::Get).count();
+        assert_eq!(tip_reads, 2);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn generate_and_send_gives_up_after_max_retries() {
+        let tip = Block::builder().data_message("Genesis block").build();
+        let mock_server = MockServer::start().await;
+        arrange_server_mock_tip(&mock_server, &tip).await;
+        Mock::given(method("POST"))
+            .respond_with(conflict())
+            .expect(3)
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        let failure = client.generate_and_send("Hello", 2).await.unwrap_err();
+        assert!(matches!(failure, APIClientError::Block(InvalidBlockErr::NotCorrelated(1, 1))));
+    }
+
+    #[async_std::test]
+    async fn broadcast_block_reports_every_peer() {
+        let block = Block::builder().index(1).previous_hash("abc").data_message("Gossip").build();
+        let accepting = arrange_server_mock_receive_block(block.clone()).await;
+        let error = InvalidBlockErr::HashNotMatching("0".repeat(32), "1".repeat(32));
+        let rejecting = arrange_server_mock_reject_block(APIErrorAndReason::from(error.clone())).await;
+        let unreachable = String::from("http://127.0.0.1:9");
+        let peers = PeerList {
+            items: vec![
+                MemberEntry { peer: accepting.uri() },
+                MemberEntry { peer: rejecting.uri() },
+                MemberEntry { peer: unreachable.clone() },
+            ],
+        };
+        let report = APIClient::broadcast_block(&peers, block).await;
+        assert_eq!(report.accepted, vec![accepting.uri()]);
+        assert_eq!(report.rejected, vec![(rejecting.uri(), error)]);
+        assert_eq!(report.unreachable, vec![unreachable]);
+    }
+
+    #[async_std::test]
+    async fn default_headers_go_on_every_request() -> Result<(), Box<dyn std::error::Error>> {
+        let block = Block::builder().index(1).previous_hash("abc").data_message("Headers").build();
+        let mock_server = arrange_server_mock_receive_block(block.clone()).await;
+        Mock::given(method("GET"))
+            .and(path("/v1/blocks/last"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(block.clone()))
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri())
+            .with_node_id("http://node-a:8080")
+            .with_token("s3cr3t")
+            .with_header("X-Trace", "first")
+            .with_header("x-trace", "second");
+        client.get_last_block().await?;
+        client.send_block(block.clone()).await?;
+        client.get_last_block().await?;
+        let received_requests = mock_server.received_requests().await.unwrap();
+        assert_eq!(received_requests.len(), 3);
+        let user_agent = format!("rustychain/{}", env!("CARGO_PKG_VERSION"));
+        for received in received_requests {
+            let header_of = |name: &str| received.headers.get(&name.into()).map(|values| values.as_str().to_string());
+            assert_eq!(header_of(NODE_ID_HEADER).as_deref(), Some("http://node-a:8080"));
+            assert_eq!(header_of("Authorization").as_deref(), Some("Bearer s3cr3t"));
+            assert_eq!(header_of("X-Trace").as_deref(), Some("second"));
+            assert_eq!(header_of("User-Agent"), Some(user_agent.clone()));
+        }
+        Ok(())
+    }
+
+    async fn arrange_server_mock_status(template: ResponseTemplate) -> MockServer {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/status"))
+            .respond_with(template)
+            .mount(&mock_server)
+            .await;
+        mock_server
+    }
+
+    #[async_std::test]
+    async fn get_status_of_healthy_node() -> Result<(), Box<dyn std::error::Error>> {
+        let status = NodeStatus {
+            height: 3,
+            last_index: 2,
+            last_hash: String::from("c4f3"),
+            genesis_hash: String::from("g3n"),
+            peers: 2,
+            started_at: 1000,
+            uptime_ms: 500,
+            version: String::from("0.2.0"),
+            broadcast_attempts: 4,
+            broadcast_failures: 1,
+        };
+        let mock_server = arrange_server_mock_status(ResponseTemplate::new(200).set_body_json(status.clone())).await;
+        let client = APIClient::new(mock_server.uri());
+        assert_eq!(client.get_status().await?, status);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn get_status_with_server_error() {
+        let mock_server = arrange_server_mock_status(ResponseTemplate::new(500)).await;
+        let client = APIClient::new(mock_server.uri());
+        assert!(matches!(client.get_status().await, Err(APIClientError::UnexpectedStatus(500))));
+    }
+
+    #[async_std::test]
+    async fn get_status_from_older_node() -> Result<(), Box<dyn std::error::Error>> {
+        let body = serde_json::json!({"height": 3, "last_index": 2, "last_hash": "c4f3", "peers": 0});
+        let mock_server = arrange_server_mock_status(ResponseTemplate::new(200).set_body_json(body)).await;
+        let client = APIClient::new(mock_server.uri());
+        let status = client.get_status().await?;
+        assert_eq!(status.height, 3);
+        assert_eq!(status.version, "");
+        assert_eq!(status.uptime_ms, 0);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn subscribe_blocks_yields_events_in_order() -> Result<(), Box<dyn std::error::Error>> {
+        let first = Block::builder().index(3).previous_hash("abc").data_message("First").build();
+        let second = first.generate_next(String::from("Second"));
+        let body = format!(
+            ": heartbeat\n\nevent: block\nid: 3\ndata: {}\n\n: heartbeat\n\nevent: block\nid: 4\ndata: {}\n\n",
+            serde_json::to_string(&first)?,
+            serde_json::to_string(&second)?
+        );
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/blocks/stream"))
+            .and(query_param("from_index", "3"))
+            .and(header("Accept", "text/event-stream"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        let received: Vec<Block> = client
+            .subscribe_blocks(Some(3))
+            .await?
+            .map(|block| block.unwrap())
+            .collect()
+            .await;
+        assert_eq!(received, vec![first, second]);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn register_peer_tells_new_from_known() -> Result<(), Box<dyn std::error::Error>> {
+        let new_member = MemberEntry {
+            peer: String::from("http://localhost:5055"),
+        };
+        let added = arrange_server_mock_receive_peer(new_member.clone()).await;
+        let client = APIClient::new(added.uri());
+        assert_eq!(client.register_peer(new_member.clone()).await?, PeerRegistration::Added(new_member.clone()));
+
+        let error = EntryRejectedErr::AlreadyPresent(new_member.clone());
+        let known = arrange_server_mock_reject_peer(APIErrorAndReason::from(error)).await;
+        let client = APIClient::new(known.uri());
+        assert_eq!(
+            client.register_peer(new_member.clone()).await?,
+            PeerRegistration::AlreadyMember(new_member.clone())
+        );
+        assert_eq!(client.send_peer(new_member.clone()).await?, new_member);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn register_peer_with_invalid_url_fails() {
+        let url = String::from("not a url");
+        let error = EntryRejectedErr::InvalidURL(url.clone());
+        let mock_server = arrange_server_mock_reject_peer(APIErrorAndReason::from(error.clone())).await;
+        let client = APIClient::new(mock_server.uri());
+        let failure = client.register_peer(MemberEntry { peer: url }).await.unwrap_err();
+        assert!(matches!(failure, APIClientError::Peer(rejected) if rejected == error));
+    }
+
+    #[async_std::test]
+    async fn node_id_defaults_to_a_random_id() -> Result<(), Box<dyn std::error::Error>> {
+        let block = Block::builder().index(1).previous_hash("abc").data_message("Identified").build();
+        let mock_server = arrange_server_mock_receive_block(block.clone()).await;
+        let client = APIClient::new(mock_server.uri());
+        assert_eq!(client.node_id().len(), 36);
+        assert_ne!(client.node_id(), APIClient::new(mock_server.uri()).node_id());
+        client.send_block(block.clone()).await?;
+        client.send_block(block).await?;
+        for received in mock_server.received_requests().await.unwrap() {
+            let sent = received.headers.get(&NODE_ID_HEADER.into()).unwrap();
+            assert_eq!(sent.as_str(), client.node_id());
+        }
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn mirror_a_remote_chain() -> Result<(), Box<dyn std::error::Error>> {
+        let mut source = Chain::new(String::from("Genesis block"));
+        source.generate_and_append("Second").unwrap();
+        source.generate_and_append("Third").unwrap();
+        let blocks: Vec<Block> = source.iter().cloned().collect();
+        let mock_server = MockServer::start().await;
+        for (from_index, next_index) in [(0, Some(2)), (2, None)] {
+            let page = PagedBlockList {
+                items: blocks[from_index as usize..].iter().take(2).cloned().collect(),
+                total: 3,
+                from_index: from_index,
+                next_index: next_index,
+            };
+            Mock::given(method("GET"))
+                .and(path("/v1/blocks"))
+                .and(query_param("from_index", from_index.to_string()))
+                .respond_with(ResponseTemplate::new(200).set_body_json(page))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+        }
+        let client = APIClient::new(mock_server.uri());
+        let mirrored = client.mirror().await?;
+        assert_eq!(mirrored.iter().cloned().collect::<Vec<Block>>(), blocks);
+        assert_eq!(mirrored.info(), source.info());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn mirror_names_the_tampered_block() {
+        let mut source = Chain::new(String::from("Genesis block"));
+        source.generate_and_append("Second").unwrap();
+        source.generate_and_append("Third").unwrap();
+        let mut blocks: Vec<Block> = source.iter().cloned().collect();
+        blocks[2].previous_hash = "0".repeat(64);
+        let mock_server = MockServer::start().await;
+        let page = PagedBlockList {
+            items: blocks,
+            total: 3,
+            from_index: 0,
+            next_index: None,
+        };
+        Mock::given(method("GET"))
+            .and(path("/v1/blocks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(page))
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        let failure = client.mirror().await.unwrap_err();
+        assert!(matches!(failure, MirrorError::InvalidBlock(2, _)));
+    }
+
+    #[async_std::test]
+    async fn verified_blocks_pass_through() -> Result<(), Box<dyn std::error::Error>> {
+        let blocks = arrange_three_blocks();
+        let mock_server = arrange_server_mock_get_blocks(Some(blocks.clone())).await;
+        let client = APIClient::new(mock_server.uri()).with_verification(true);
+        assert_eq!(client.get_all_blocks().await?.items, blocks);
+        assert_eq!(client.get_blocks(1).await?.items, blocks);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn verification_catches_a_broken_middle_link() -> Result<(), Box<dyn std::error::Error>> {
+        let mut blocks = arrange_three_blocks();
+        blocks[1].previous_hash = "0".repeat(64);
+        let mock_server = arrange_server_mock_get_blocks(Some(blocks.clone())).await;
+        let unverified = APIClient::new(mock_server.uri());
+        assert_eq!(unverified.get_all_blocks().await?.items, blocks);
+        let client = APIClient::new(mock_server.uri()).with_verification(true);
+        assert!(matches!(
+            client.get_all_blocks().await,
+            Err(APIClientError::InvalidSequence {
+                at_index: 1,
+                source: InvalidBlockErr::HashNotMatching(..)
+            })
+        ));
+        assert!(matches!(
+            client.get_blocks(0).await,
+            Err(APIClientError::InvalidSequence { at_index: 1, .. })
+        ));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn verification_takes_the_first_block_of_a_later_page_as_given() -> Result<(), Box<dyn std::error::Error>> {
+        let blocks = arrange_three_blocks()[1..].to_vec();
+        let mock_server = arrange_server_mock_get_blocks(Some(blocks.clone())).await;
+        let client = APIClient::new(mock_server.uri()).with_verification(true);
+        assert_eq!(client.get_blocks(1).await?.items, blocks);
+        Ok(())
+    }
+
+    #[test]
+    fn https_hosts_keep_their_scheme() {
+        let client = APIClient::new("https://node.example.com:8443");
+        assert_eq!(client.url("/blocks"), "https://node.example.com:8443/v1/blocks");
+        let legacy = APIClient::new("https://node.example.com").with_api_version(None);
+        assert_eq!(legacy.url("/status"), "https://node.example.com/status");
+    }
+
+    #[async_std::test]
+    async fn failed_tls_handshake_is_a_transport_error() {
+        let mock_server = arrange_server_mock_get_blocks(None).await;
+        let plain_http_as_https = mock_server.uri().replacen("http://", "https://", 1);
+        let client = APIClient::new(plain_http_as_https.as_str());
+        assert!(matches!(client.get_all_blocks().await, Err(APIClientError::Transport(_))));
+    }
+
+    #[async_std::test]
+    async fn closed_port_is_a_transport_error() {
+        let client = APIClient::new("http://127.0.0.1:9");
+        let block = Block::builder().index(1).previous_hash("abc").data_message("unreachable").build();
+        assert!(matches!(client.get_all_blocks().await, Err(APIClientError::Transport(_))));
+        assert!(matches!(client.send_block(block).await, Err(APIClientError::Transport(_))));
+    }
+
+    #[async_std::test]
+    async fn html_error_page_is_an_unexpected_status() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(500).set_body_raw("<html><h1>Bad gateway</h1></html>", "text/html"))
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        assert!(matches!(client.get_blocks(0).await, Err(APIClientError::UnexpectedStatus(500))));
+    }
+
+    #[async_std::test]
+    async fn non_json_rejection_is_an_unexpected_status() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400).set_body_raw("bad request", "text/plain"))
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        let block = Block::builder().index(1).previous_hash("abc").data_message("plain").build();
+        assert!(matches!(client.send_block(block).await, Err(APIClientError::UnexpectedStatus(400))));
+        let peer = MemberEntry {
+            peer: String::from("http://localhost:9090"),
+        };
+        assert!(matches!(client.send_peer(peer).await, Err(APIClientError::UnexpectedStatus(400))));
+    }
+
+    #[async_std::test]
+    async fn unparseable_success_is_a_decode_error() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("<html></html>", "text/html"))
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        assert!(matches!(client.get_page(&Limits::default()).await, Err(APIClientError::Decode(_))));
+    }
+
+    #[async_std::test]
+    async fn unauthorized_keeps_the_api_error() {
+        let mock_server = arrange_server_mock_reject_block(APIErrorAndReason::unauthorized(String::from("no token"))).await;
+        let client = APIClient::new(mock_server.uri());
+        let block = Block::builder().index(1).previous_hash("abc").data_message("denied").build();
+        let failure = client.send_block(block).await.unwrap_err();
+        assert!(matches!(failure, APIClientError::Api(error) if error.reason == "no token"));
+    }
+
+    #[async_std::test]
+    async fn send_blocks_reads_the_offset_from_the_context() {
+        let blocks = arrange_three_blocks();
+        let error = InvalidBlockErr::NotPosterior(1000, 2000);
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/blocks/batch"))
+            .respond_with(
+                ResponseTemplate::new(422).set_body_json(APIErrorAndReason::from(error.clone()).with_batch_offset(2)),
+            )
+            .mount(&mock_server)
+            .await;
+        let client = APIClient::new(mock_server.uri());
+        let failure = client.send_blocks(blocks).await.unwrap_err();
+        assert!(matches!(failure, APIClientError::BatchRejected { offset: 2, block_index: 2, source } if source == error));
+    }
+
+    // Runs `openssl s_server` on a freshly generated self-signed certificate,
+    // so these only build with the `tls` feature and need the openssl binary.
+    #[cfg(feature = "tls")]
+    struct TlsServer {
+        url: String,
+        dir: std::path::PathBuf,
+        process: std::process::Child,
+    }
+
+    #[cfg(feature = "tls")]
+    impl TlsServer {
+        fn start(status: &str) -> TlsServer {
+            use std::net::{TcpListener, TcpStream};
+            use std::process::{Command, Stdio};
+            let dir = std::env::temp_dir().join(format!("rustychain-tls-{}", random_node_id()));
+            std::fs::create_dir_all(dir.join("v1")).unwrap();
+            // s_server -HTTP sends the file as is, status line and headers included.
+            let response = format!("HTTP/1.0 200 ok\r\nContent-Type: application/json\r\n\r\n{}", status);
+            std::fs::write(dir.join("v1/status"), response).unwrap();
+            let generated = Command::new("openssl")
+                .args(["req", "-x509", "-newkey", "rsa:2048", "-nodes", "-days", "1"])
+                .args(["-keyout", "key.pem", "-out", "cert.pem", "-subj", "/CN=localhost"])
+                .args(["-addext", "subjectAltName=IP:127.0.0.1"])
+                .current_dir(&dir)
+                .stderr(Stdio::null())
+                .status()
+                .unwrap();
+            assert!(generated.success());
+            let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+            let process = Command::new("openssl")
+                .args(["s_server", "-HTTP", "-quiet", "-cert", "cert.pem", "-key", "key.pem"])
+                .args(["-accept", &format!("127.0.0.1:{}", port)])
+                .current_dir(&dir)
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .unwrap();
+            for _ in 0..100 {
+                if TcpStream::connect(("127.0.0.1", port)).is_ok() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            TlsServer {
+                url: format!("https://127.0.0.1:{}", port),
+                dir: dir,
+                process: process,
+            }
+        }
+        fn certificate(&self) -> Vec<u8> {
+            std::fs::read(self.dir.join("cert.pem")).unwrap()
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    impl Drop for TlsServer {
+        fn drop(&mut self) {
+            let _ = self.process.kill();
+            let _ = self.process.wait();
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    #[async_std::test]
+    async fn self_signed_certificate_needs_an_explicit_trust() -> Result<(), APIClientError> {
+        let server = TlsServer::start(r#"{"height": 1, "last_index": 0, "last_hash": "", "peers": 0}"#);
+        let strict = APIClient::new(server.url.as_str());
+        assert!(matches!(strict.get_status().await, Err(APIClientError::Transport(_))));
+        let lenient = APIClient::new(server.url.as_str()).danger_accept_invalid_certs(true)?;
+        assert_eq!(lenient.get_status().await?.height, 1);
+        let trusting = APIClient::new(server.url.as_str()).with_root_certificate(server.certificate())?;
+        assert_eq!(trusting.get_status().await?.height, 1);
+        Ok(())
+    }
+
+    #[cfg(feature = "tls")]
+    #[async_std::test]
+    async fn unusable_root_certificate_is_a_transport_error() {
+        let server = TlsServer::start("{}");
+        let client = APIClient::new(server.url.as_str()).with_root_certificate("not a certificate").unwrap();
+        assert!(matches!(client.get_status().await, Err(APIClientError::Transport(_))));
+    }
 }
@@ -0,0 +1,162 @@
+use crate::api::structs::DEFAULT_MAX_PAGE_SIZE;
+use crate::blockchain::block::Block;
+use crate::blockchain::DEFAULT_MAX_DATA_BYTES;
+use std::env;
+
+pub const DEFAULT_BIND_ADDR: &str = "127.0.0.1:8080";
+pub const DEFAULT_MAX_BODY_BYTES: usize = 256 * 1024;
+
+const BIND_ENV: &str = "RUSTYCHAIN_BIND";
+const GENESIS_ENV: &str = "RUSTYCHAIN_GENESIS";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitConfig {
+    pub per_second: f64,
+    pub burst: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    pub genesis_data: String,
+    pub genesis_block: Option<Block>,
+    pub bind_addr: String,
+    pub max_body_bytes: usize,
+    pub max_data_bytes: usize,
+    pub max_page_size: usize,
+    pub difficulty: Option<u32>,
+    pub node_id: Option<String>,
+    pub tokens: Vec<String>,
+    pub rate_limit: Option<RateLimitConfig>,
+    pub cors_origins: Vec<String>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            genesis_data: String::new(),
+            genesis_block: None,
+            bind_addr: String::from(DEFAULT_BIND_ADDR),
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            max_data_bytes: DEFAULT_MAX_DATA_BYTES,
+            max_page_size: DEFAULT_MAX_PAGE_SIZE,
+            difficulty: None,
+            node_id: None,
+            tokens: vec![],
+            rate_limit: None,
+            cors_origins: vec![],
+        }
+    }
+}
+
+impl AppConfig {
+    pub fn builder() -> AppConfigBuilder {
+        AppConfigBuilder::new()
+    }
+    pub fn from_env() -> Self {
+        Self::builder().from_env().build()
+    }
+}
+
+#[derive(Default)]
+pub struct AppConfigBuilder {
+    config: AppConfig,
+}
+
+impl AppConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn from_env(mut self) -> Self {
+        if let Ok(bind_addr) = env::var(BIND_ENV) {
+            self.config.bind_addr = bind_addr;
+        }
+        if let Ok(genesis_data) = env::var(GENESIS_ENV) {
+            self.config.genesis_data = genesis_data;
+        }
+        self
+    }
+    pub fn genesis_data(mut self, genesis_data: impl Into<String>) -> Self {
+        self.config.genesis_data = genesis_data.into();
+        self
+    }
+    pub fn genesis_block(mut self, genesis_block: Block) -> Self {
+        self.config.genesis_block = Some(genesis_block);
+        self
+    }
+    pub fn bind_addr(mut self, bind_addr: impl Into<String>) -> Self {
+        self.config.bind_addr = bind_addr.into();
+        self
+    }
+    pub fn max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        self.config.max_body_bytes = max_body_bytes;
+        self
+    }
+    pub fn max_data_bytes(mut self, max_data_bytes: usize) -> Self {
+        self.config.max_data_bytes = max_data_bytes;
+        self
+    }
+    pub fn max_page_size(mut self, max_page_size: usize) -> Self {
+        self.config.max_page_size = max_page_size;
+        self
+    }
+    pub fn difficulty(mut self, difficulty: u32) -> Self {
+        self.config.difficulty = Some(difficulty);
+        self
+    }
+    pub fn node_id(mut self, node_id: impl Into<String>) -> Self {
+        self.config.node_id = Some(node_id.into());
+        self
+    }
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.config.tokens.push(token.into());
+        self
+    }
+    pub fn rate_limit(mut self, per_second: f64, burst: u32) -> Self {
+        self.config.rate_limit = Some(RateLimitConfig {
+            per_second: per_second,
+            burst: burst,
+        });
+        self
+    }
+    // Pass "*" to allow any origin.
+    pub fn cors_origin(mut self, origin: impl Into<String>) -> Self {
+        self.config.cors_origins.push(origin.into());
+        self
+    }
+    pub fn build(self) -> AppConfig {
+        self.config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_overrides_defaults() {
+        let config = AppConfig::builder()
+            .genesis_data("Genesis block sample")
+            .bind_addr("0.0.0.0:9090")
+            .max_body_bytes(1024)
+            .difficulty(2)
+            .build();
+        assert_eq!(config.genesis_data, "Genesis block sample");
+        assert_eq!(config.bind_addr, "0.0.0.0:9090");
+        assert_eq!(config.max_body_bytes, 1024);
+        assert_eq!(config.max_page_size, DEFAULT_MAX_PAGE_SIZE);
+        assert_eq!(config.difficulty, Some(2));
+        assert_eq!(AppConfig::default().bind_addr, DEFAULT_BIND_ADDR);
+    }
+
+    #[test]
+    fn environment_provides_defaults() {
+        env::set_var(BIND_ENV, "0.0.0.0:7070");
+        env::set_var(GENESIS_ENV, "Genesis from env");
+        let config = AppConfig::builder().from_env().max_body_bytes(1024).build();
+        env::remove_var(BIND_ENV);
+        env::remove_var(GENESIS_ENV);
+        assert_eq!(config.bind_addr, "0.0.0.0:7070");
+        assert_eq!(config.genesis_data, "Genesis from env");
+        assert_eq!(config.max_body_bytes, 1024);
+    }
+}
@@ -1,169 +1,966 @@
 use crate::peers::{EntryRejectedErr, MemberEntry};
 use crate::blockchain::InvalidBlockErr;
 use lazy_static::lazy_static;
-use regex::Regex;
+use regex::{Captures, Regex};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::str::FromStr;
+use tide::{Body, Response, StatusCode};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct APIErrorAndReason {
     pub error: String,
+    // Stable identifier for `error`, which is free to be reworded. Older
+    // nodes only send the label, see `error_code`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
     pub reason: String,
+    // Machine-readable values behind `reason`. Older nodes leave it out and
+    // clients fall back to parsing `reason`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<ErrorContext>,
 }
 
-const HASH_NOT_MATCHING_LABEL: &str = "Previous hash not matching";
-const INDEX_NOT_CORRELATIVE_LABEL: &str = "New block index is not correlative";
-const TIMESTAMP_NOT_LATER_LABEL: &str = "New block timestamp must be later to previous";
+// Which block or peer a request failed on, so clients can resume from there.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ErrorContext {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_index: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_offset: Option<usize>,
+}
+
+impl APIErrorAndReason {
+    pub fn at_batch_offset(offset: usize, error: InvalidBlockErr) -> Self {
+        let located = APIErrorAndReason::from(error);
+        APIErrorAndReason {
+            error: located.error,
+            code: located.code,
+            reason: format!("batch offset {}: {}", offset, located.reason),
+            params: located.params,
+            context: located.context,
+        }
+        .with_batch_offset(offset)
+    }
+    // Undoes `at_batch_offset`, giving back the offset and the bare error.
+    // Older nodes only state the offset in `reason`.
+    pub fn batch_offset(&self) -> Option<(usize, APIErrorAndReason)> {
+        let caps = BATCH_OFFSET_DESC_REGEX.captures(&self.reason);
+        let offset = match self.context.as_ref().and_then(|context| context.batch_offset) {
+            Some(offset) => offset,
+            None => caps.as_ref()?.get(1)?.as_str().parse().ok()?,
+        };
+        let reason = match &caps {
+            Some(caps) => String::from(caps.get(2).map_or("", |m| m.as_str())),
+            None => self.reason.clone(),
+        };
+        let located = APIErrorAndReason {
+            error: self.error.clone(),
+            code: self.code.clone(),
+            reason: reason,
+            params: self.params.clone(),
+            context: self.context.clone(),
+        };
+        Some((offset, located))
+    }
+    pub fn at_block(index: u64, error: InvalidBlockErr) -> Self {
+        let located = APIErrorAndReason::from(error);
+        APIErrorAndReason {
+            error: located.error,
+            code: located.code,
+            reason: format!("block {}: {}", index, located.reason),
+            params: located.params,
+            context: located.context,
+        }
+        .with_block_index(index)
+    }
+    pub fn with_block_index(mut self, index: u64) -> Self {
+        self.context.get_or_insert_with(ErrorContext::default).block_index = Some(index);
+        self
+    }
+    pub fn with_peer(mut self, peer: impl Into<String>) -> Self {
+        self.context.get_or_insert_with(ErrorContext::default).peer = Some(peer.into());
+        self
+    }
+    pub fn with_batch_offset(mut self, offset: usize) -> Self {
+        self.context.get_or_insert_with(ErrorContext::default).batch_offset = Some(offset);
+        self
+    }
+    pub fn block_index(&self) -> Option<u64> {
+        self.context.as_ref()?.block_index
+    }
+    pub fn peer(&self) -> Option<&str> {
+        self.context.as_ref()?.peer.as_deref()
+    }
+    pub fn body_too_large(limit: usize) -> Self {
+        APIErrorAndReason {
+            error: String::from(BODY_TOO_LARGE_LABEL),
+            code: Some(String::from(BODY_TOO_LARGE_CODE)),
+            reason: format!("request body exceeds the limit of {} bytes", limit),
+            params: None,
+            context: None,
+        }
+    }
+    pub fn block_not_found(index: u64, height: u64) -> Self {
+        APIErrorAndReason {
+            error: String::from(BLOCK_NOT_FOUND_LABEL),
+            code: Some(String::from(BLOCK_NOT_FOUND_CODE)),
+            reason: format!("no block with index {}, chain height is {}", index, height),
+            params: None,
+            context: None,
+        }
+    }
+    pub fn invalid_query(reason: String) -> Self {
+        APIErrorAndReason {
+            error: String::from(INVALID_QUERY_LABEL),
+            code: Some(String::from(INVALID_QUERY_CODE)),
+            reason: reason,
+            params: None,
+            context: None,
+        }
+    }
+    pub fn malformed_block(reason: String) -> Self {
+        APIErrorAndReason {
+            error: String::from(MALFORMED_BLOCK_LABEL),
+            code: Some(String::from(MALFORMED_BLOCK_CODE)),
+            reason: reason,
+            params: None,
+            context: None,
+        }
+    }
+    pub fn malformed_body(reason: String) -> Self {
+        APIErrorAndReason {
+            error: String::from(MALFORMED_BODY_LABEL),
+            code: Some(String::from(MALFORMED_BODY_CODE)),
+            reason: reason,
+            params: None,
+            context: None,
+        }
+    }
+    pub fn rate_limited(retry_after: u64) -> Self {
+        APIErrorAndReason {
+            error: String::from(RATE_LIMITED_LABEL),
+            code: Some(String::from(RATE_LIMITED_CODE)),
+            reason: format!("request budget exhausted, retry in {} seconds", retry_after),
+            params: None,
+            context: None,
+        }
+    }
+    pub fn unauthorized(reason: String) -> Self {
+        APIErrorAndReason {
+            error: String::from(UNAUTHORIZED_LABEL),
+            code: Some(String::from(UNAUTHORIZED_CODE)),
+            reason: reason,
+            params: None,
+            context: None,
+        }
+    }
+    // The code sent along, or the one its label stands for when talking to a
+    // node that predates codes. Empty when neither is recognized.
+    pub fn error_code(&self) -> &str {
+        match &self.code {
+            Some(code) => code,
+            None => CODES_BY_LABEL
+                .iter()
+                .find(|(label, _)| *label == self.error)
+                .map_or("", |(_, code)| code),
+        }
+    }
+    pub fn status(&self) -> StatusCode {
+        match self.error_code() {
+            HASH_NOT_MATCHING_CODE | INDEX_NOT_CORRELATIVE_CODE | TIMESTAMP_NOT_LATER_CODE | TOO_FAR_IN_FUTURE_CODE => {
+                StatusCode::UnprocessableEntity
+            }
+            ENTRY_ALREADY_PRESENT_CODE => StatusCode::Conflict,
+            BODY_TOO_LARGE_CODE => StatusCode::PayloadTooLarge,
+            UNAUTHORIZED_CODE => StatusCode::Unauthorized,
+            RATE_LIMITED_CODE => StatusCode::TooManyRequests,
+            BLOCK_NOT_FOUND_CODE | ENTRY_NOT_FOUND_CODE => StatusCode::NotFound,
+            UNKNOWN_ERROR_CODE | GENESIS_NOT_FOUND_CODE => StatusCode::InternalServerError,
+            _ => StatusCode::BadRequest,
+        }
+    }
+}
+
+// Handlers and middleware answer every failure through here, so the status
+// always follows the error.
+pub fn error_response(error: impl Into<APIErrorAndReason>) -> tide::Result<Response> {
+    let error = error.into();
+    let mut res = Response::new(error.status());
+    res.set_body(Body::from_json(&error)?);
+    Ok(res)
+}
+
+pub(crate) const HASH_NOT_MATCHING_LABEL: &str = "Previous hash not matching";
+pub(crate) const INDEX_NOT_CORRELATIVE_LABEL: &str = "New block index is not correlative";
+pub(crate) const TIMESTAMP_NOT_LATER_LABEL: &str = "New block timestamp must be later to previous";
+pub(crate) const TOO_FAR_IN_FUTURE_LABEL: &str = "New block timestamp is too far in the future";
+pub(crate) const EMPTY_DATA_LABEL: &str = "New block data is empty";
+pub(crate) const DATA_TOO_LARGE_LABEL: &str = "New block data is too large";
+pub(crate) const BAD_SIGNATURE_LABEL: &str = "Block signature is missing or invalid";
+pub(crate) const INSUFFICIENT_WORK_LABEL: &str = "Block does not meet the required difficulty";
+pub(crate) const INVALID_ENTRIES_LABEL: &str = "Block entries are invalid";
+pub(crate) const SELF_HASH_MISMATCH_LABEL: &str = "Block hash does not match its content";
+pub(crate) const BLOCK_NOT_FOUND_LABEL: &str = "Block not found";
+pub(crate) const INVALID_QUERY_LABEL: &str = "Invalid query parameters";
+pub(crate) const BODY_TOO_LARGE_LABEL: &str = "Request body is too large";
+pub(crate) const MALFORMED_BLOCK_LABEL: &str = "Block is malformed";
+pub(crate) const MALFORMED_BODY_LABEL: &str = "Malformed request body";
+pub(crate) const UNSUPPORTED_VERSION_LABEL: &str = "Block format version is not supported";
+
+pub(crate) const RATE_LIMITED_LABEL: &str = "Too many requests";
+pub(crate) const UNAUTHORIZED_LABEL: &str = "Authentication required";
+pub(crate) const UNKNOWN_ERROR_LABEL: &str = "Unknown error";
+
+pub(crate) const ENTRY_ALREADY_PRESENT_LABEL: &str = "Entry is already on list";
+pub(crate) const ENTRY_URL_INVALID_LABEL: &str = "Invalid entry URL";
+pub(crate) const ENTRY_NOT_FOUND_LABEL: &str = "Peer not found";
+
+const HASH_NOT_MATCHING_CODE: &str = "block.hash_mismatch";
+const INDEX_NOT_CORRELATIVE_CODE: &str = "block.index_not_correlative";
+const TIMESTAMP_NOT_LATER_CODE: &str = "block.timestamp_not_later";
+const TOO_FAR_IN_FUTURE_CODE: &str = "block.timestamp_too_far_in_future";
+const EMPTY_DATA_CODE: &str = "block.empty_data";
+const DATA_TOO_LARGE_CODE: &str = "block.data_too_large";
+const BAD_SIGNATURE_CODE: &str = "block.bad_signature";
+const INSUFFICIENT_WORK_CODE: &str = "block.insufficient_work";
+const INVALID_ENTRIES_CODE: &str = "block.invalid_entries";
+const SELF_HASH_MISMATCH_CODE: &str = "block.self_hash_mismatch";
+const UNSUPPORTED_VERSION_CODE: &str = "block.unsupported_version";
+const MALFORMED_BLOCK_CODE: &str = "block.malformed";
+const BLOCK_NOT_FOUND_CODE: &str = "block.not_found";
+const GENESIS_NOT_FOUND_CODE: &str = "chain.genesis_not_found";
+const INVALID_QUERY_CODE: &str = "request.invalid_query";
+const BODY_TOO_LARGE_CODE: &str = "request.body_too_large";
+const MALFORMED_BODY_CODE: &str = "request.malformed_body";
+const RATE_LIMITED_CODE: &str = "request.rate_limited";
+const UNAUTHORIZED_CODE: &str = "request.unauthorized";
+const UNKNOWN_ERROR_CODE: &str = "unknown";
+
+const ENTRY_ALREADY_PRESENT_CODE: &str = "peer.already_present";
+const ENTRY_URL_INVALID_CODE: &str = "peer.invalid_url";
+const ENTRY_NOT_FOUND_CODE: &str = "peer.not_found";
 
-const ENTRY_ALREADY_PRESENT_LABEL: &str = "Entry is already on list";
-const ENTRY_URL_INVALID_LABEL: &str = "Invalid entry URL";
+const CODES_BY_LABEL: [(&str, &str); 22] = [
+    (HASH_NOT_MATCHING_LABEL, HASH_NOT_MATCHING_CODE),
+    (INDEX_NOT_CORRELATIVE_LABEL, INDEX_NOT_CORRELATIVE_CODE),
+    (TIMESTAMP_NOT_LATER_LABEL, TIMESTAMP_NOT_LATER_CODE),
+    (TOO_FAR_IN_FUTURE_LABEL, TOO_FAR_IN_FUTURE_CODE),
+    (EMPTY_DATA_LABEL, EMPTY_DATA_CODE),
+    (DATA_TOO_LARGE_LABEL, DATA_TOO_LARGE_CODE),
+    (BAD_SIGNATURE_LABEL, BAD_SIGNATURE_CODE),
+    (INSUFFICIENT_WORK_LABEL, INSUFFICIENT_WORK_CODE),
+    (INVALID_ENTRIES_LABEL, INVALID_ENTRIES_CODE),
+    (SELF_HASH_MISMATCH_LABEL, SELF_HASH_MISMATCH_CODE),
+    (BLOCK_NOT_FOUND_LABEL, BLOCK_NOT_FOUND_CODE),
+    (INVALID_QUERY_LABEL, INVALID_QUERY_CODE),
+    (BODY_TOO_LARGE_LABEL, BODY_TOO_LARGE_CODE),
+    (MALFORMED_BLOCK_LABEL, MALFORMED_BLOCK_CODE),
+    (MALFORMED_BODY_LABEL, MALFORMED_BODY_CODE),
+    (UNSUPPORTED_VERSION_LABEL, UNSUPPORTED_VERSION_CODE),
+    (RATE_LIMITED_LABEL, RATE_LIMITED_CODE),
+    (UNAUTHORIZED_LABEL, UNAUTHORIZED_CODE),
+    (UNKNOWN_ERROR_LABEL, UNKNOWN_ERROR_CODE),
+    (ENTRY_ALREADY_PRESENT_LABEL, ENTRY_ALREADY_PRESENT_CODE),
+    (ENTRY_URL_INVALID_LABEL, ENTRY_URL_INVALID_CODE),
+    (ENTRY_NOT_FOUND_LABEL, ENTRY_NOT_FOUND_CODE),
+];
 
 lazy_static! {
+    pub static ref BATCH_OFFSET_DESC_REGEX: Regex =
+        Regex::new(r"^batch offset (\d+): (.*)$").unwrap();
     pub static ref HASH_NOT_MATCHING_DESC_REGEX: Regex =
-        Regex::new(r"previous hash is ([a-f0-9]{32}) but ([a-f0-9]{32}) was provided").unwrap();
+        Regex::new(r"previous hash is ([a-f0-9]+) but (\S*) was provided").unwrap();
     pub static ref NOT_CORRELATIVE_DESC_REGEX: Regex =
         Regex::new(r"expected index (\d+) but received (\d+) which is not inmediate next").unwrap();
     pub static ref NOT_POSTERIOR_DESC_REGEX: Regex =
         Regex::new(r"Given timestamp (\d+) is not later to (\d+)").unwrap();
+    pub static ref TOO_FAR_IN_FUTURE_DESC_REGEX: Regex =
+        Regex::new(r"Given timestamp (\d+) is too far ahead of (\d+)").unwrap();
+    pub static ref SELF_HASH_MISMATCH_DESC_REGEX: Regex =
+        Regex::new(r"block hash was given as (\S*) but content hashes to ([a-f0-9]+)").unwrap();
+    pub static ref INSUFFICIENT_WORK_DESC_REGEX: Regex =
+        Regex::new(r"block hash needs (\d+) leading zeros").unwrap();
+    pub static ref UNSUPPORTED_VERSION_DESC_REGEX: Regex =
+        Regex::new(r"block version (\d+) is not supported").unwrap();
+    pub static ref DATA_TOO_LARGE_DESC_REGEX: Regex =
+        Regex::new(r"Block data takes (\d+) bytes but only (\d+) are allowed").unwrap();
     pub static ref ENTRY_ALREADY_PRESENT_DESC_REGEX: Regex =
         Regex::new(r"Entry is already a member: (.*)$").unwrap();
+    pub static ref ENTRY_NOT_FOUND_DESC_REGEX: Regex =
+        Regex::new(r"Entry is not a member: (.*)$").unwrap();
     pub static ref ENTRY_INVALID_URL_DESC_REGEX: Regex =
         Regex::new(r"Entry URL is invalid: (.*)$").unwrap();
 }
 
-fn params_for_hash_not_matching(reason: String) -> (String, String) {
-    let caps = HASH_NOT_MATCHING_DESC_REGEX.captures(&*reason).unwrap();
-    (
-        String::from(caps.get(2).map_or("", |m| m.as_str())),
-        String::from(caps.get(1).map_or("", |m| m.as_str())),
-    )
+// Numbers may arrive as JSON numbers or as strings, see NotPosterior.
+fn param<T: FromStr>(params: &Option<Value>, name: &str) -> Option<T> {
+    match params.as_ref()?.get(name)? {
+        Value::String(value) => value.parse().ok(),
+        value => value.to_string().parse().ok(),
+    }
 }
 
-fn params_for_not_correlative(reason: String) -> (u64, u64) {
-    let caps = NOT_CORRELATIVE_DESC_REGEX.captures(&*reason).unwrap();
-    (
-        caps.get(2)
-            .map_or(0, |m| m.as_str().parse::<u64>().unwrap()),
-        caps.get(1)
-            .map_or(0, |m| m.as_str().parse::<u64>().unwrap()),
-    )
+fn param_pair<A: FromStr, B: FromStr>(params: &Option<Value>, first: &str, second: &str) -> Option<(A, B)> {
+    Some((param(params, first)?, param(params, second)?))
 }
 
-fn params_for_not_posterior(reason: String) -> (u128, u128) {
-    let caps = NOT_POSTERIOR_DESC_REGEX.captures(&*reason).unwrap();
-    (
-        caps.get(1)
-            .map_or(0, |m| m.as_str().parse::<u128>().unwrap()),
-        caps.get(2)
-            .map_or(0, |m| m.as_str().parse::<u128>().unwrap()),
-    )
+fn captured<T: FromStr>(caps: &Captures, group: usize) -> Option<T> {
+    caps.get(group)?.as_str().parse().ok()
 }
 
-fn param_for_entry_invalid_url(reason: String) -> String {
-    let caps = ENTRY_INVALID_URL_DESC_REGEX.captures(&*reason).unwrap();
-    let input: &str = caps.get(1).unwrap().as_str();
-    String::from(input)
+fn params_for_hash_not_matching(reason: &str) -> Option<(String, String)> {
+    let caps = HASH_NOT_MATCHING_DESC_REGEX.captures(reason)?;
+    Some((captured(&caps, 2)?, captured(&caps, 1)?))
 }
 
-fn param_for_entry_already_present(reason: String) -> MemberEntry {
-    let caps = ENTRY_INVALID_URL_DESC_REGEX.captures(&*reason).unwrap();
-    let input: &str = caps.get(1).unwrap().as_str();
-    MemberEntry {
-        peer: String::from(input),
-    }
+fn params_for_not_correlative(reason: &str) -> Option<(u64, u64)> {
+    let caps = NOT_CORRELATIVE_DESC_REGEX.captures(reason)?;
+    Some((captured(&caps, 2)?, captured(&caps, 1)?))
+}
+
+fn params_for_not_posterior(reason: &str) -> Option<(u128, u128)> {
+    let caps = NOT_POSTERIOR_DESC_REGEX.captures(reason)?;
+    Some((captured(&caps, 1)?, captured(&caps, 2)?))
+}
+
+fn params_for_too_far_in_future(reason: &str) -> Option<(u128, u128)> {
+    let caps = TOO_FAR_IN_FUTURE_DESC_REGEX.captures(reason)?;
+    Some((captured(&caps, 1)?, captured(&caps, 2)?))
+}
+
+fn params_for_self_hash_mismatch(reason: &str) -> Option<(String, String)> {
+    let caps = SELF_HASH_MISMATCH_DESC_REGEX.captures(reason)?;
+    Some((captured(&caps, 1)?, captured(&caps, 2)?))
+}
+
+fn param_for_insufficient_work(reason: &str) -> Option<u32> {
+    captured(&INSUFFICIENT_WORK_DESC_REGEX.captures(reason)?, 1)
+}
+
+fn param_for_unsupported_version(reason: &str) -> Option<u16> {
+    captured(&UNSUPPORTED_VERSION_DESC_REGEX.captures(reason)?, 1)
+}
+
+fn params_for_data_too_large(reason: &str) -> Option<(usize, usize)> {
+    let caps = DATA_TOO_LARGE_DESC_REGEX.captures(reason)?;
+    Some((captured(&caps, 1)?, captured(&caps, 2)?))
+}
+
+fn param_for_entry_invalid_url(reason: &str) -> Option<String> {
+    captured(&ENTRY_INVALID_URL_DESC_REGEX.captures(reason)?, 1)
+}
+
+fn param_for_entry_already_present(reason: &str) -> Option<String> {
+    captured(&ENTRY_ALREADY_PRESENT_DESC_REGEX.captures(reason)?, 1)
+}
+
+fn param_for_entry_not_found(reason: &str) -> Option<String> {
+    captured(&ENTRY_NOT_FOUND_DESC_REGEX.captures(reason)?, 1)
 }
 
 impl From<InvalidBlockErr> for APIErrorAndReason {
     fn from(native_error: InvalidBlockErr) -> Self {
-        match native_error {
-            InvalidBlockErr::HashNotMatching(given, expected) => {
-                let reason = format!("previous hash is {} but {} was provided", expected, given);
-                APIErrorAndReason {
-                    error: String::from(HASH_NOT_MATCHING_LABEL),
-                    reason: String::from(reason),
-                }
-            }
-            InvalidBlockErr::NotCorrelated(given, expected) => {
-                let reason = format!(
-                    "expected index {} but received {} which is not inmediate next",
-                    expected, given
-                );
-                APIErrorAndReason {
-                    error: String::from(INDEX_NOT_CORRELATIVE_LABEL),
-                    reason: String::from(reason),
+        let reason = native_error.to_string();
+        let (label, code, params) = match &native_error {
+            InvalidBlockErr::HashNotMatching(given, expected) => (
+                HASH_NOT_MATCHING_LABEL,
+                HASH_NOT_MATCHING_CODE,
+                Some(json!({ "given": given, "expected": expected })),
+            ),
+            InvalidBlockErr::NotCorrelated(given, expected) => (
+                INDEX_NOT_CORRELATIVE_LABEL,
+                INDEX_NOT_CORRELATIVE_CODE,
+                Some(json!({ "given": given, "expected": expected })),
+            ),
+            // u128 timestamps may not fit a JSON number, so they travel as strings.
+            InvalidBlockErr::NotPosterior(given, expected) => (
+                TIMESTAMP_NOT_LATER_LABEL,
+                TIMESTAMP_NOT_LATER_CODE,
+                Some(json!({ "given": given.to_string(), "expected": expected.to_string() })),
+            ),
+            InvalidBlockErr::TooFarInFuture(given, now) => (
+                TOO_FAR_IN_FUTURE_LABEL,
+                TOO_FAR_IN_FUTURE_CODE,
+                Some(json!({ "given": given.to_string(), "now": now.to_string() })),
+            ),
+            InvalidBlockErr::SelfHashMismatch(given, computed) => (
+                SELF_HASH_MISMATCH_LABEL,
+                SELF_HASH_MISMATCH_CODE,
+                Some(json!({ "given": given, "computed": computed })),
+            ),
+            InvalidBlockErr::BadSignature => (BAD_SIGNATURE_LABEL, BAD_SIGNATURE_CODE, None),
+            InvalidBlockErr::InsufficientWork(difficulty) => (
+                INSUFFICIENT_WORK_LABEL,
+                INSUFFICIENT_WORK_CODE,
+                Some(json!({ "difficulty": difficulty })),
+            ),
+            InvalidBlockErr::UnsupportedVersion(version) => (
+                UNSUPPORTED_VERSION_LABEL,
+                UNSUPPORTED_VERSION_CODE,
+                Some(json!({ "version": version })),
+            ),
+            InvalidBlockErr::InvalidEntries(_) => (INVALID_ENTRIES_LABEL, INVALID_ENTRIES_CODE, None),
+            InvalidBlockErr::EmptyData => (EMPTY_DATA_LABEL, EMPTY_DATA_CODE, None),
+            InvalidBlockErr::DataTooLarge(given, allowed) => (
+                DATA_TOO_LARGE_LABEL,
+                DATA_TOO_LARGE_CODE,
+                Some(json!({ "given": given, "allowed": allowed })),
+            ),
+            InvalidBlockErr::GenesisBlockNotFound => (UNKNOWN_ERROR_LABEL, GENESIS_NOT_FOUND_CODE, None),
+            InvalidBlockErr::Unknown { error, .. } => {
+                return APIErrorAndReason {
+                    error: error.clone(),
+                    code: None,
+                    reason: reason,
+                    params: None,
+                    context: None,
                 }
             }
-            InvalidBlockErr::NotPosterior(given, expected) => {
-                let reason = format!("Given timestamp {} is not later to {}", given, expected);
-                APIErrorAndReason {
-                    error: String::from(TIMESTAMP_NOT_LATER_LABEL),
-                    reason: String::from(reason),
-                }
-            }
-            _ => APIErrorAndReason {
-                error: String::from("Unknown error"),
-                reason: String::from("reason"),
-            },
+        };
+        APIErrorAndReason {
+            error: String::from(label),
+            code: Some(String::from(code)),
+            reason: reason,
+            params: params,
+            context: None,
         }
     }
 }
 
+// Labels or reasons from other node versions that do not parse become
+// Unknown, keeping the original strings rather than panicking on the
+// client's error path.
 impl From<APIErrorAndReason> for InvalidBlockErr {
     fn from(api_error: APIErrorAndReason) -> Self {
-        match &*api_error.error {
-            HASH_NOT_MATCHING_LABEL => {
-                let (expected, given) = params_for_hash_not_matching(api_error.reason);
-                InvalidBlockErr::HashNotMatching(expected, given)
-            }
-            INDEX_NOT_CORRELATIVE_LABEL => {
-                let (expected, given) = params_for_not_correlative(api_error.reason);
-                InvalidBlockErr::NotCorrelated(expected, given)
-            }
-            TIMESTAMP_NOT_LATER_LABEL => {
-                let (expected, given) = params_for_not_posterior(api_error.reason);
-                InvalidBlockErr::NotPosterior(expected, given)
-            }
-            _ => InvalidBlockErr::Unkown,
-        }
+        let params = &api_error.params;
+        let reason = &*api_error.reason;
+        let parsed = match api_error.error_code() {
+            HASH_NOT_MATCHING_CODE => param_pair(params, "given", "expected")
+                .or_else(|| params_for_hash_not_matching(reason))
+                .map(|(given, expected)| InvalidBlockErr::HashNotMatching(given, expected)),
+            INDEX_NOT_CORRELATIVE_CODE => param_pair(params, "given", "expected")
+                .or_else(|| params_for_not_correlative(reason))
+                .map(|(given, expected)| InvalidBlockErr::NotCorrelated(given, expected)),
+            TIMESTAMP_NOT_LATER_CODE => param_pair(params, "given", "expected")
+                .or_else(|| params_for_not_posterior(reason))
+                .map(|(given, expected)| InvalidBlockErr::NotPosterior(given, expected)),
+            SELF_HASH_MISMATCH_CODE => param_pair(params, "given", "computed")
+                .or_else(|| params_for_self_hash_mismatch(reason))
+                .map(|(given, computed)| InvalidBlockErr::SelfHashMismatch(given, computed)),
+            TOO_FAR_IN_FUTURE_CODE => param_pair(params, "given", "now")
+                .or_else(|| params_for_too_far_in_future(reason))
+                .map(|(given, now)| InvalidBlockErr::TooFarInFuture(given, now)),
+            BAD_SIGNATURE_CODE => Some(InvalidBlockErr::BadSignature),
+            INSUFFICIENT_WORK_CODE => param(params, "difficulty")
+                .or_else(|| param_for_insufficient_work(reason))
+                .map(InvalidBlockErr::InsufficientWork),
+            UNSUPPORTED_VERSION_CODE => param(params, "version")
+                .or_else(|| param_for_unsupported_version(reason))
+                .map(InvalidBlockErr::UnsupportedVersion),
+            INVALID_ENTRIES_CODE => Some(InvalidBlockErr::InvalidEntries(String::from(reason))),
+            GENESIS_NOT_FOUND_CODE => Some(InvalidBlockErr::GenesisBlockNotFound),
+            EMPTY_DATA_CODE => Some(InvalidBlockErr::EmptyData),
+            DATA_TOO_LARGE_CODE => param_pair(params, "given", "allowed")
+                .or_else(|| params_for_data_too_large(reason))
+                .map(|(given, allowed)| InvalidBlockErr::DataTooLarge(given, allowed)),
+            _ => None,
+        };
+        parsed.unwrap_or_else(|| InvalidBlockErr::Unknown {
+            error: api_error.error.clone(),
+            reason: api_error.reason.clone(),
+        })
     }
 }
 
 impl From<APIErrorAndReason> for EntryRejectedErr {
     fn from(api_error: APIErrorAndReason) -> Self {
-        match &*api_error.error {
-            ENTRY_URL_INVALID_LABEL => {
-                let expected = param_for_entry_invalid_url(api_error.reason);
-                EntryRejectedErr::InvalidURL(expected)
-            }
-            ENTRY_ALREADY_PRESENT_LABEL => {
-                let expected = param_for_entry_already_present(api_error.reason);
-                EntryRejectedErr::AlreadyPresent(expected)
-            }
-            _ => EntryRejectedErr::Unknown,
-        }
+        let params = &api_error.params;
+        let reason = &*api_error.reason;
+        let member = |peer| MemberEntry { peer: peer };
+        let parsed = match api_error.error_code() {
+            ENTRY_URL_INVALID_CODE => param(params, "url")
+                .or_else(|| param_for_entry_invalid_url(reason))
+                .map(EntryRejectedErr::InvalidURL),
+            ENTRY_NOT_FOUND_CODE => param(params, "peer")
+                .or_else(|| param_for_entry_not_found(reason))
+                .map(|peer| EntryRejectedErr::NotFound(member(peer))),
+            ENTRY_ALREADY_PRESENT_CODE => param(params, "peer")
+                .or_else(|| param_for_entry_already_present(reason))
+                .map(|peer| EntryRejectedErr::AlreadyPresent(member(peer))),
+            _ => None,
+        };
+        parsed.unwrap_or_else(|| EntryRejectedErr::Unknown {
+            error: api_error.error.clone(),
+            reason: api_error.reason.clone(),
+        })
     }
 }
 
 impl From<EntryRejectedErr> for APIErrorAndReason {
     fn from(native_error: EntryRejectedErr) -> Self {
-        match native_error {
-            EntryRejectedErr::AlreadyPresent(given) => {
-                let reason = format!("Entry is already a member: {}", given.peer);
-                APIErrorAndReason {
-                    error: String::from(ENTRY_ALREADY_PRESENT_LABEL),
-                    reason: String::from(reason),
-                }
+        let reason = native_error.to_string();
+        let (label, code, params) = match &native_error {
+            EntryRejectedErr::AlreadyPresent(given) => (
+                ENTRY_ALREADY_PRESENT_LABEL,
+                ENTRY_ALREADY_PRESENT_CODE,
+                Some(json!({ "peer": given.peer })),
+            ),
+            EntryRejectedErr::NotFound(given) => {
+                (ENTRY_NOT_FOUND_LABEL, ENTRY_NOT_FOUND_CODE, Some(json!({ "peer": given.peer })))
             }
             EntryRejectedErr::InvalidURL(given) => {
-                let reason = format!("Entry URL is invalid: {}", given);
-                APIErrorAndReason {
-                    error: String::from(ENTRY_URL_INVALID_LABEL),
-                    reason: String::from(reason),
+                (ENTRY_URL_INVALID_LABEL, ENTRY_URL_INVALID_CODE, Some(json!({ "url": given })))
+            }
+            EntryRejectedErr::Unknown { error, .. } => {
+                return APIErrorAndReason {
+                    error: error.clone(),
+                    code: None,
+                    reason: reason,
+                    params: None,
+                    context: None,
                 }
             }
-            _ => APIErrorAndReason {
-                error: String::from("Unknown error"),
-                reason: String::from("reason"),
+        };
+        APIErrorAndReason {
+            error: String::from(label),
+            code: Some(String::from(code)),
+            reason: reason,
+            params: params,
+            context: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::block::Block;
+    use crate::blockchain::Chain;
+
+    #[test]
+    fn batch_offset_round_trip() {
+        let error = InvalidBlockErr::NotPosterior(1000, 2000);
+        let located = APIErrorAndReason::at_batch_offset(3, error.clone());
+        let (offset, bare) = located.batch_offset().unwrap();
+        assert_eq!(offset, 3);
+        assert_eq!(located.context.as_ref().unwrap().batch_offset, Some(3));
+        assert_eq!(InvalidBlockErr::from(bare), error);
+        assert!(APIErrorAndReason::from(error).batch_offset().is_none());
+    }
+
+    #[test]
+    fn batch_offset_from_older_nodes() {
+        let error = InvalidBlockErr::NotPosterior(1000, 2000);
+        let mut located = APIErrorAndReason::at_batch_offset(3, error.clone());
+        located.context = None;
+        let (offset, bare) = located.batch_offset().unwrap();
+        assert_eq!(offset, 3);
+        assert_eq!(bare.reason, APIErrorAndReason::from(error).reason);
+    }
+
+    #[test]
+    fn batch_offset_from_context_alone() {
+        let error = InvalidBlockErr::NotPosterior(1000, 2000);
+        let located = APIErrorAndReason::from(error.clone()).with_batch_offset(2);
+        let (offset, bare) = located.batch_offset().unwrap();
+        assert_eq!(offset, 2);
+        assert_eq!(InvalidBlockErr::from(bare), error);
+    }
+
+    fn every_block_error() -> Vec<InvalidBlockErr> {
+        vec![
+            InvalidBlockErr::HashNotMatching("c4f3".repeat(8), "0".repeat(32)),
+            InvalidBlockErr::NotCorrelated(3, 1),
+            InvalidBlockErr::NotPosterior(1, u128::MAX),
+            InvalidBlockErr::EmptyData,
+            InvalidBlockErr::DataTooLarge(2, 1),
+            InvalidBlockErr::SelfHashMismatch(String::from("forged"), "0".repeat(64)),
+            InvalidBlockErr::InvalidEntries(String::from("duplicated key")),
+            InvalidBlockErr::InsufficientWork(4),
+            InvalidBlockErr::UnsupportedVersion(9),
+            InvalidBlockErr::BadSignature,
+        ]
+    }
+
+    fn through_json(api_error: APIErrorAndReason, keep_params: bool) -> APIErrorAndReason {
+        let mut received: APIErrorAndReason = serde_json::from_str(&serde_json::to_string(&api_error).unwrap()).unwrap();
+        if !keep_params {
+            received.params = None;
+        }
+        received
+    }
+
+    #[test]
+    fn block_errors_round_trip_through_json() {
+        for keep_params in [true, false] {
+            for error in every_block_error() {
+                let received = through_json(APIErrorAndReason::from(error.clone()), keep_params);
+                assert_eq!(InvalidBlockErr::from(received), error);
+            }
+        }
+    }
+
+    #[test]
+    fn entry_errors_round_trip_through_json() {
+        let entry = MemberEntry {
+            peer: String::from("http://localhost:9090"),
+        };
+        let with_params = [
+            EntryRejectedErr::AlreadyPresent(entry.clone()),
+            EntryRejectedErr::NotFound(entry.clone()),
+            EntryRejectedErr::InvalidURL(String::from("not a url")),
+        ];
+        for error in with_params {
+            let received = through_json(APIErrorAndReason::from(error.clone()), true);
+            assert_eq!(EntryRejectedErr::from(received), error);
+        }
+        let without_params = [
+            EntryRejectedErr::AlreadyPresent(entry.clone()),
+            EntryRejectedErr::NotFound(entry),
+            EntryRejectedErr::InvalidURL(String::from("not a url")),
+        ];
+        for error in without_params {
+            let received = through_json(APIErrorAndReason::from(error.clone()), false);
+            assert_eq!(EntryRejectedErr::from(received), error);
+        }
+    }
+
+    // Adding a variant breaks these matches, so it cannot be left out below.
+    fn block_variant_listed(error: &InvalidBlockErr) {
+        match error {
+            InvalidBlockErr::NotCorrelated(..)
+            | InvalidBlockErr::NotPosterior(..)
+            | InvalidBlockErr::HashNotMatching(..)
+            | InvalidBlockErr::TooFarInFuture(..)
+            | InvalidBlockErr::EmptyData
+            | InvalidBlockErr::DataTooLarge(..)
+            | InvalidBlockErr::SelfHashMismatch(..)
+            | InvalidBlockErr::InvalidEntries(..)
+            | InvalidBlockErr::InsufficientWork(..)
+            | InvalidBlockErr::UnsupportedVersion(..)
+            | InvalidBlockErr::BadSignature
+            | InvalidBlockErr::GenesisBlockNotFound
+            | InvalidBlockErr::Unknown { .. } => {}
+        }
+    }
+
+    fn entry_variant_listed(error: &EntryRejectedErr) {
+        match error {
+            EntryRejectedErr::AlreadyPresent(..)
+            | EntryRejectedErr::InvalidURL(..)
+            | EntryRejectedErr::NotFound(..)
+            | EntryRejectedErr::Unknown { .. } => {}
+        }
+    }
+
+    #[test]
+    fn every_variant_round_trips_exactly() {
+        let mut block_errors = every_block_error();
+        block_errors.extend([
+            InvalidBlockErr::HashNotMatching(String::from("not even hex"), "ab".repeat(32)),
+            InvalidBlockErr::NotPosterior(u128::MAX, 0),
+            InvalidBlockErr::TooFarInFuture(u128::MAX, 1),
+            InvalidBlockErr::InvalidEntries(String::new()),
+            InvalidBlockErr::GenesisBlockNotFound,
+            InvalidBlockErr::Unknown {
+                error: String::from("I'm a teapot"),
+                reason: String::from("short and stout"),
+            },
+            InvalidBlockErr::Unknown {
+                error: String::from(UNKNOWN_ERROR_LABEL),
+                reason: String::new(),
+            },
+        ]);
+        for error in block_errors {
+            block_variant_listed(&error);
+            assert_eq!(InvalidBlockErr::from(through_json(APIErrorAndReason::from(error.clone()), true)), error);
+        }
+        let entry = MemberEntry {
+            peer: String::from("ws://localhost:5055/path?with=query"),
+        };
+        let entry_errors = [
+            EntryRejectedErr::AlreadyPresent(entry.clone()),
+            EntryRejectedErr::InvalidURL(String::from("not a url: really")),
+            EntryRejectedErr::InvalidURL(String::new()),
+            EntryRejectedErr::NotFound(entry),
+            EntryRejectedErr::Unknown {
+                error: String::from("I'm a teapot"),
+                reason: String::from("short and stout"),
+            },
+        ];
+        for error in entry_errors {
+            entry_variant_listed(&error);
+            assert_eq!(EntryRejectedErr::from(through_json(APIErrorAndReason::from(error.clone()), true)), error);
+        }
+    }
+
+    #[test]
+    fn real_hash_mismatch_round_trips() {
+        let mut chain = Chain::new(String::from("Genesis block"));
+        let other = Chain::new(String::from("Another genesis block"));
+        let forked = Block::builder().after(other.get_last_block().unwrap()).data_message("Forked").build();
+        let error = chain.append(forked).unwrap_err();
+        assert!(matches!(&error, InvalidBlockErr::HashNotMatching(given, expected) if given.len() == 64 && expected.len() == 64));
+        for keep_params in [true, false] {
+            let received = through_json(APIErrorAndReason::from(error.clone()), keep_params);
+            assert_eq!(InvalidBlockErr::from(received), error);
+        }
+    }
+
+    fn legacy(label: &str, reason: &str) -> APIErrorAndReason {
+        APIErrorAndReason {
+            error: String::from(label),
+            code: None,
+            reason: String::from(reason),
+            params: None,
+            context: None,
+        }
+    }
+
+    #[test]
+    fn unparseable_block_reasons_are_unknown() {
+        let too_big = "9".repeat(40);
+        let overflowing = [
+            (HASH_NOT_MATCHING_LABEL, String::from("previous hash is not hex but abc was provided")),
+            (INDEX_NOT_CORRELATIVE_LABEL, format!("expected index 1 but received {} which is not inmediate next", too_big)),
+            (TIMESTAMP_NOT_LATER_LABEL, format!("Given timestamp {} is not later to 1", too_big)),
+            (TOO_FAR_IN_FUTURE_LABEL, format!("Given timestamp {} is too far ahead of 1", "9".repeat(50))),
+            (SELF_HASH_MISMATCH_LABEL, String::from("block hash was given as abc but content hashes to XYZ")),
+            (INSUFFICIENT_WORK_LABEL, String::from("block hash needs 4294967296 leading zeros")),
+            (UNSUPPORTED_VERSION_LABEL, String::from("block version 65536 is not supported")),
+            (DATA_TOO_LARGE_LABEL, format!("Block data takes {} bytes but only 1 are allowed", too_big)),
+        ];
+        for (label, reason) in overflowing {
+            for reason in [reason.as_str(), "", "Le hash précédent ne correspond pas", "previous hash is"] {
+                let unknown = InvalidBlockErr::Unknown {
+                    error: String::from(label),
+                    reason: String::from(reason),
+                };
+                assert_eq!(InvalidBlockErr::from(legacy(label, reason)), unknown);
+            }
+        }
+        let garbled_params = APIErrorAndReason {
+            params: Some(json!({ "given": "three", "expected": 1 })),
+            ..legacy(INDEX_NOT_CORRELATIVE_LABEL, "")
+        };
+        assert!(matches!(InvalidBlockErr::from(garbled_params), InvalidBlockErr::Unknown { .. }));
+    }
+
+    #[test]
+    fn unparseable_entry_reasons_are_unknown() {
+        for label in [ENTRY_URL_INVALID_LABEL, ENTRY_NOT_FOUND_LABEL, ENTRY_ALREADY_PRESENT_LABEL] {
+            for reason in ["", "garbage", "Entry is"] {
+                assert!(matches!(
+                    EntryRejectedErr::from(legacy(label, reason)),
+                    EntryRejectedErr::Unknown { error, reason: unparsed } if error == label && unparsed == reason
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn every_error_has_a_unique_stable_code() {
+        let entry = MemberEntry {
+            peer: String::from("http://localhost:9090"),
+        };
+        let mut emitted = vec![
+            APIErrorAndReason::from(InvalidBlockErr::TooFarInFuture(2, 1)),
+            APIErrorAndReason::from(InvalidBlockErr::GenesisBlockNotFound),
+            APIErrorAndReason::from(EntryRejectedErr::AlreadyPresent(entry.clone())),
+            APIErrorAndReason::from(EntryRejectedErr::NotFound(entry)),
+            APIErrorAndReason::from(EntryRejectedErr::InvalidURL(String::from("nope"))),
+            APIErrorAndReason::block_not_found(5, 1),
+            APIErrorAndReason::body_too_large(512),
+            APIErrorAndReason::invalid_query(String::from("limit")),
+            APIErrorAndReason::malformed_block(String::from("eof")),
+            APIErrorAndReason::malformed_body(String::from("eof")),
+            APIErrorAndReason::rate_limited(1),
+            APIErrorAndReason::unauthorized(String::from("no token")),
+        ];
+        emitted.extend(every_block_error().into_iter().map(APIErrorAndReason::from));
+        let codes: Vec<&str> = emitted.iter().map(|error| error.code.as_deref().unwrap()).collect();
+        assert_eq!(
+            codes,
+            [
+                "block.timestamp_too_far_in_future",
+                "chain.genesis_not_found",
+                "peer.already_present",
+                "peer.not_found",
+                "peer.invalid_url",
+                "block.not_found",
+                "request.body_too_large",
+                "request.invalid_query",
+                "block.malformed",
+                "request.malformed_body",
+                "request.rate_limited",
+                "request.unauthorized",
+                "block.hash_mismatch",
+                "block.index_not_correlative",
+                "block.timestamp_not_later",
+                "block.empty_data",
+                "block.data_too_large",
+                "block.self_hash_mismatch",
+                "block.invalid_entries",
+                "block.insufficient_work",
+                "block.unsupported_version",
+                "block.bad_signature",
+            ]
+        );
+        let mut unique = codes.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), codes.len());
+        for error in [InvalidBlockErr::TooFarInFuture(2, 1), InvalidBlockErr::GenesisBlockNotFound] {
+            assert_eq!(InvalidBlockErr::from(through_json(APIErrorAndReason::from(error.clone()), true)), error);
+        }
+    }
+
+    #[test]
+    fn code_wins_over_a_relabelled_error() {
+        let mut api_error = APIErrorAndReason::from(InvalidBlockErr::InsufficientWork(4));
+        api_error.error = String::from("Not enough work");
+        assert_eq!(api_error.status(), StatusCode::BadRequest);
+        assert_eq!(InvalidBlockErr::from(api_error), InvalidBlockErr::InsufficientWork(4));
+    }
+
+    #[test]
+    fn bodies_without_code_still_parse() {
+        let body = r#"{"error":"Entry is already on list","reason":"Entry is already a member: http://localhost:9090","params":{"peer":"http://localhost:9090"}}"#;
+        let api_error: APIErrorAndReason = serde_json::from_str(body).unwrap();
+        assert_eq!(api_error.code, None);
+        assert_eq!(api_error.error_code(), "peer.already_present");
+        assert_eq!(api_error.status(), StatusCode::Conflict);
+        let entry = MemberEntry {
+            peer: String::from("http://localhost:9090"),
+        };
+        assert_eq!(EntryRejectedErr::from(api_error), EntryRejectedErr::AlreadyPresent(entry));
+        let body = r#"{"error":"New block index is not correlative","reason":"expected index 1 but received 3 which is not inmediate next"}"#;
+        let api_error: APIErrorAndReason = serde_json::from_str(body).unwrap();
+        assert_eq!(InvalidBlockErr::from(api_error), InvalidBlockErr::NotCorrelated(3, 1));
+    }
+
+    #[test]
+    fn unrecognized_labels_round_trip_losslessly() {
+        let from_newer_node = APIErrorAndReason {
+            error: String::from("Block is from the future"),
+            code: Some(String::from("block.from_the_future")),
+            reason: String::from("timestamp 99 is after 42"),
+            params: None,
+            context: None,
+        };
+        for keep_params in [true, false] {
+            let received = through_json(from_newer_node.clone(), keep_params);
+            let back = APIErrorAndReason::from(InvalidBlockErr::from(received.clone()));
+            assert_eq!((back.error, back.reason), (from_newer_node.error.clone(), from_newer_node.reason.clone()));
+            let back = APIErrorAndReason::from(EntryRejectedErr::from(received));
+            assert_eq!((back.error, back.reason), (from_newer_node.error.clone(), from_newer_node.reason.clone()));
+        }
+    }
+
+    #[test]
+    fn params_win_over_a_reworded_reason() {
+        let mut api_error = APIErrorAndReason::from(InvalidBlockErr::NotCorrelated(7, 5));
+        api_error.reason = String::from("index 7 does not follow 5");
+        assert_eq!(InvalidBlockErr::from(api_error), InvalidBlockErr::NotCorrelated(7, 5));
+    }
+
+    #[test]
+    fn display_is_the_api_reason() {
+        let mut errors = every_block_error();
+        errors.push(InvalidBlockErr::TooFarInFuture(2, 1));
+        errors.push(InvalidBlockErr::GenesisBlockNotFound);
+        errors.push(InvalidBlockErr::Unknown {
+            error: String::from("I'm a teapot"),
+            reason: String::from("short and stout"),
+        });
+        for error in errors {
+            assert_eq!(error.to_string(), APIErrorAndReason::from(error.clone()).reason);
+        }
+        let entry = MemberEntry {
+            peer: String::from("http://localhost:9090"),
+        };
+        for error in [
+            EntryRejectedErr::AlreadyPresent(entry.clone()),
+            EntryRejectedErr::NotFound(entry),
+            EntryRejectedErr::InvalidURL(String::from("not a url")),
+            EntryRejectedErr::Unknown {
+                error: String::from("I'm a teapot"),
+                reason: String::from("short and stout"),
             },
+        ] {
+            assert_eq!(error.to_string(), APIErrorAndReason::from(error.clone()).reason);
         }
+        assert_eq!(
+            InvalidBlockErr::NotCorrelated(3, 1).to_string(),
+            "expected index 1 but received 3 which is not inmediate next"
+        );
+        let boxed: Box<dyn std::error::Error> = Box::new(EntryRejectedErr::InvalidURL(String::from("nope")));
+        assert_eq!(boxed.to_string(), "Entry URL is invalid: nope");
+    }
+
+    #[async_std::test]
+    async fn error_response_uses_the_error_status() -> tide::Result<()> {
+        let error = InvalidBlockErr::NotCorrelated(3, 1);
+        let mut res = error_response(error.clone())?;
+        assert_eq!(res.status(), StatusCode::UnprocessableEntity);
+        assert_eq!(res.content_type().unwrap().essence(), "application/json");
+        let sent: APIErrorAndReason = res.take_body().into_json().await?;
+        assert_eq!(InvalidBlockErr::from(sent), error);
+        let res = error_response(APIErrorAndReason::rate_limited(3))?;
+        assert_eq!(res.status(), StatusCode::TooManyRequests);
+        Ok(())
+    }
+
+    fn status_of<E: Into<APIErrorAndReason>>(error: E) -> StatusCode {
+        error.into().status()
+    }
+
+    #[test]
+    fn link_errors_are_unprocessable() {
+        let hash = "c4f3".repeat(16);
+        assert_eq!(
+            status_of(InvalidBlockErr::HashNotMatching(hash.clone(), hash)),
+            StatusCode::UnprocessableEntity
+        );
+        assert_eq!(status_of(InvalidBlockErr::NotCorrelated(3, 1)), StatusCode::UnprocessableEntity);
+        assert_eq!(status_of(InvalidBlockErr::NotPosterior(1, 2)), StatusCode::UnprocessableEntity);
+    }
+
+    #[test]
+    fn peer_errors_map_to_their_statuses() {
+        let entry = MemberEntry {
+            peer: String::from("http://localhost:9090"),
+        };
+        assert_eq!(status_of(EntryRejectedErr::AlreadyPresent(entry.clone())), StatusCode::Conflict);
+        assert_eq!(status_of(EntryRejectedErr::NotFound(entry)), StatusCode::NotFound);
+        assert_eq!(status_of(EntryRejectedErr::InvalidURL(String::from("nope"))), StatusCode::BadRequest);
+        assert_eq!(status_of(EntryRejectedErr::Unknown {
+            error: String::from(UNKNOWN_ERROR_LABEL),
+            reason: String::new(),
+        }), StatusCode::InternalServerError);
+    }
+
+    #[test]
+    fn other_errors_keep_their_statuses() {
+        assert_eq!(APIErrorAndReason::block_not_found(5, 1).status(), StatusCode::NotFound);
+        assert_eq!(APIErrorAndReason::body_too_large(512).status(), StatusCode::PayloadTooLarge);
+        assert_eq!(APIErrorAndReason::unauthorized(String::from("no token")).status(), StatusCode::Unauthorized);
+        assert_eq!(APIErrorAndReason::rate_limited(1).status(), StatusCode::TooManyRequests);
+        assert_eq!(APIErrorAndReason::malformed_block(String::from("eof")).status(), StatusCode::BadRequest);
+        assert_eq!(APIErrorAndReason::malformed_body(String::from("eof")).status(), StatusCode::BadRequest);
+        assert_eq!(APIErrorAndReason::invalid_query(String::from("limit")).status(), StatusCode::BadRequest);
+        assert_eq!(status_of(InvalidBlockErr::EmptyData), StatusCode::BadRequest);
+        assert_eq!(status_of(InvalidBlockErr::DataTooLarge(2, 1)), StatusCode::BadRequest);
+        assert_eq!(status_of(InvalidBlockErr::TooFarInFuture(2, 1)), StatusCode::UnprocessableEntity);
+        assert_eq!(status_of(InvalidBlockErr::GenesisBlockNotFound), StatusCode::InternalServerError);
+        assert_eq!(
+            APIErrorAndReason::at_batch_offset(1, InvalidBlockErr::NotCorrelated(3, 1)).status(),
+            StatusCode::UnprocessableEntity
+        );
     }
 }
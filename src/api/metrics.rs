@@ -0,0 +1,213 @@
+use crate::api::middleware::V1_PREFIX;
+use crate::api::structs::State;
+use crate::blockchain::InvalidBlockErr;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tide::{Middleware, Next, Request};
+
+pub const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    blocks_appended: AtomicU64,
+    rejections: Mutex<BTreeMap<&'static str, u64>>,
+    requests: Mutex<BTreeMap<(String, String, u16), u64>>,
+}
+
+impl Metrics {
+    pub fn record_appended(&self, count: usize) {
+        self.blocks_appended.fetch_add(count as u64, Ordering::Relaxed);
+    }
+    pub fn record_rejection(&self, error: &InvalidBlockErr) {
+        let mut rejections = self.rejections.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *rejections.entry(rejection_kind(error)).or_insert(0) += 1;
+    }
+    pub fn record_request(&self, route: String, method: String, status: u16) {
+        let mut requests = self.requests.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *requests.entry((route, method, status)).or_insert(0) += 1;
+    }
+}
+
+fn rejection_kind(error: &InvalidBlockErr) -> &'static str {
+    match error {
+        InvalidBlockErr::NotCorrelated(..) => "not_correlated",
+        InvalidBlockErr::NotPosterior(..) => "not_posterior",
+        InvalidBlockErr::HashNotMatching(..) => "hash_not_matching",
+        InvalidBlockErr::TooFarInFuture(..) => "too_far_in_future",
+        InvalidBlockErr::EmptyData => "empty_data",
+        InvalidBlockErr::DataTooLarge(..) => "data_too_large",
+        InvalidBlockErr::SelfHashMismatch(..) => "self_hash_mismatch",
+        InvalidBlockErr::InvalidEntries(..) => "invalid_entries",
+        InvalidBlockErr::InsufficientWork(..) => "insufficient_work",
+        InvalidBlockErr::UnsupportedVersion(..) => "unsupported_version",
+        InvalidBlockErr::BadSignature => "bad_signature",
+        InvalidBlockErr::GenesisBlockNotFound => "genesis_block_not_found",
+        InvalidBlockErr::Unknown { .. } => "unknown",
+    }
+}
+
+// Same paths as server::v1_routes, literal segments ahead of the parameter
+// that would also match them.
+const ROUTE_TEMPLATES: [&str; 10] = [
+    "/blocks/last",
+    "/blocks/batch",
+    "/blocks/stream",
+    "/blocks/:index",
+    "/blocks",
+    "/peers",
+    "/status",
+    "/metrics",
+    "/ws",
+    "/chain/validate",
+];
+const UNMATCHED_ROUTE: &str = "other";
+
+fn matches_template(path: &str, template: &str) -> bool {
+    let mut segments = path.split('/');
+    let mut expected = template.split('/');
+    loop {
+        match (segments.next(), expected.next()) {
+            (None, None) => return true,
+            (Some(segment), Some(pattern)) if pattern.starts_with(':') && !segment.is_empty() => {}
+            (Some(segment), Some(pattern)) if segment == pattern => {}
+            _ => return false,
+        }
+    }
+}
+
+// Series are labelled by route template, never by the raw path, so the
+// number of series stays fixed whatever clients ask for.
+fn route_of(path: &str) -> String {
+    let (prefix, rest) = match path.strip_prefix(V1_PREFIX) {
+        Some(rest) if rest.starts_with('/') => (V1_PREFIX, rest),
+        _ => ("", path),
+    };
+    match ROUTE_TEMPLATES.iter().find(|template| matches_template(rest, template)) {
+        Some(template) => format!("{}{}", prefix, template),
+        None => String::from(UNMATCHED_ROUTE),
+    }
+}
+
+fn write_metric(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+}
+
+pub async fn render(state: &State) -> String {
+    let metrics = &state.metrics;
+    let mut out = String::new();
+    write_metric(&mut out, "rustychain_chain_height", "gauge", "Number of blocks in the chain.");
+    writeln!(out, "rustychain_chain_height {}", state.chain.info().await.height).unwrap();
+    write_metric(&mut out, "rustychain_blocks_appended_total", "counter", "Blocks appended since start.");
+    writeln!(out, "rustychain_blocks_appended_total {}", metrics.blocks_appended.load(Ordering::Relaxed)).unwrap();
+    write_metric(&mut out, "rustychain_append_rejections_total", "counter", "Blocks rejected, by error.");
+    for (kind, count) in metrics.rejections.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).iter() {
+        writeln!(out, "rustychain_append_rejections_total{{error=\"{}\"}} {}", kind, count).unwrap();
+    }
+    write_metric(&mut out, "rustychain_peers", "gauge", "Registered peers.");
+    writeln!(out, "rustychain_peers {}", state.lock_peers().len()).unwrap();
+    write_metric(&mut out, "rustychain_http_requests_total", "counter", "HTTP requests, by route and status.");
+    for ((route, method, status), count) in metrics.requests.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).iter() {
+        writeln!(
+            out,
+            "rustychain_http_requests_total{{route=\"{}\",method=\"{}\",status=\"{}\"}} {}",
+            route, method, status, count
+        )
+        .unwrap();
+    }
+    write_metric(&mut out, "rustychain_broadcast_failures_total", "counter", "Failed block broadcasts to peers.");
+    writeln!(out, "rustychain_broadcast_failures_total {}", state.broadcast.failures()).unwrap();
+    out
+}
+
+pub struct RequestMetrics;
+
+#[tide::utils::async_trait]
+impl Middleware<State> for RequestMetrics {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> tide::Result {
+        let metrics = req.state().metrics.clone();
+        let route = route_of(req.url().path());
+        let method = req.method().to_string();
+        let res = next.run(req).await;
+        metrics.record_request(route, method, res.status().into());
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::server::create_app_with_genesis;
+    use crate::blockchain::block::Block;
+    use crate::peers::MemberEntry;
+    use tide::http::{Method, Request, Response, Url};
+    use tide::Server;
+
+    async fn request(method: Method, path: &str, body: Option<String>, app: &Server<State>) -> Response {
+        let url = Url::parse(&format!("https://example.com{}", path)).unwrap();
+        let mut req = Request::new(method, url);
+        if let Some(body) = body {
+            req.set_body(body);
+        }
+        app.respond(req).await.unwrap()
+    }
+
+    #[test]
+    fn routes_collapse_indexes() {
+        assert_eq!(route_of("/blocks/42"), "/blocks/:index");
+        assert_eq!(route_of("/blocks/last"), "/blocks/last");
+        assert_eq!(route_of("/v1/blocks/42"), "/v1/blocks/:index");
+        assert_eq!(route_of("/blocks/hash/c4f3"), "other");
+        assert_eq!(route_of("/v1"), "other");
+        assert_eq!(route_of("/blocks/"), "other");
+    }
+
+    #[async_std::test]
+    async fn unknown_paths_share_one_series() {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        assert_eq!(404, request(Method::Get, "/wp-admin", None, &app).await.status());
+        assert_eq!(404, request(Method::Get, "/blocks/hash/c4f3c4f3", None, &app).await.status());
+        let text = request(Method::Get, "/metrics", None, &app).await.body_string().await.unwrap();
+        let series: Vec<&str> = text.lines().filter(|line| line.starts_with("rustychain_http_requests_total{")).collect();
+        assert_eq!(series, ["rustychain_http_requests_total{route=\"other\",method=\"GET\",status=\"404\"} 2"]);
+    }
+
+    #[async_std::test]
+    async fn scrape_after_a_few_operations() {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let genesis = app.state().chain.last().await.unwrap();
+        let second = Block::builder().after(&genesis).data_message("Second").build();
+        let stale = Block::builder().after(&genesis).data_message("Stale").build();
+        let body = serde_json::to_string(&second).unwrap();
+        assert_eq!(201, request(Method::Post, "/blocks", Some(body), &app).await.status());
+        let body = serde_json::to_string(&stale).unwrap();
+        assert_eq!(422, request(Method::Post, "/blocks", Some(body), &app).await.status());
+        let member = serde_json::to_string(&MemberEntry {
+            peer: String::from("http://localhost:9090"),
+        })
+        .unwrap();
+        assert_eq!(201, request(Method::Post, "/peers", Some(member), &app).await.status());
+        assert_eq!(200, request(Method::Get, "/blocks/1", None, &app).await.status());
+
+        let mut scraped = request(Method::Get, "/metrics", None, &app).await;
+        assert_eq!(200, scraped.status());
+        assert_eq!(scraped.content_type().unwrap().essence(), "text/plain");
+        let text = scraped.body_string().await.unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        for expected in [
+            "# TYPE rustychain_chain_height gauge",
+            "rustychain_chain_height 2",
+            "rustychain_blocks_appended_total 1",
+            "rustychain_append_rejections_total{error=\"not_correlated\"} 1",
+            "rustychain_peers 1",
+            "rustychain_http_requests_total{route=\"/blocks\",method=\"POST\",status=\"201\"} 1",
+            "rustychain_http_requests_total{route=\"/blocks\",method=\"POST\",status=\"422\"} 1",
+            "rustychain_http_requests_total{route=\"/blocks/:index\",method=\"GET\",status=\"200\"} 1",
+            "rustychain_broadcast_failures_total 0",
+        ] {
+            assert!(lines.contains(&expected), "missing {:?} in\n{}", expected, text);
+        }
+    }
+}
@@ -0,0 +1,449 @@
+use crate::api::config::RateLimitConfig;
+use crate::api::errors::{error_response, APIErrorAndReason};
+use crate::api::structs::State;
+use async_std::io::ReadExt;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::Instant;
+use tide::http::Method;
+use tide::{Middleware, Next, Request, Response, StatusCode};
+
+pub const V1_PREFIX: &str = "/v1";
+
+// Routes are mounted both under /v1 and at the root, so path based rules
+// compare against the path with the version stripped.
+fn unversioned_path(req: &Request<State>) -> &str {
+    let path = req.url().path();
+    match path.strip_prefix(V1_PREFIX) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => rest,
+        _ => path,
+    }
+}
+
+pub struct DeprecatedAliases;
+
+#[tide::utils::async_trait]
+impl Middleware<State> for DeprecatedAliases {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> tide::Result {
+        let legacy = unversioned_path(&req) == req.url().path();
+        let mut res = next.run(req).await;
+        if legacy {
+            res.insert_header("Deprecation", "true");
+        }
+        Ok(res)
+    }
+}
+
+// Content-Length is only a hint: the body is read through a reader capped one
+// byte past the limit, so a lying or missing header can't make us buffer more.
+pub struct BodyLimit;
+
+#[tide::utils::async_trait]
+impl Middleware<State> for BodyLimit {
+    async fn handle(&self, mut req: Request<State>, next: Next<'_, State>) -> tide::Result {
+        if matches!(req.method(), Method::Get | Method::Head) {
+            return Ok(next.run(req).await);
+        }
+        let limit = req.state().max_body_bytes;
+        if req.len().map_or(false, |declared| declared > limit) {
+            return body_too_large(limit);
+        }
+        let mut content = Vec::new();
+        req.take_body().take(limit as u64 + 1).read_to_end(&mut content).await?;
+        if content.len() > limit {
+            return body_too_large(limit);
+        }
+        req.set_body(content);
+        Ok(next.run(req).await)
+    }
+}
+
+// Reads stay open; anything that changes the node needs one of the
+// configured bearer tokens. The validation dry run only reads the chain.
+pub struct RequireToken;
+
+#[tide::utils::async_trait]
+impl Middleware<State> for RequireToken {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> tide::Result {
+        let tokens = &req.state().tokens;
+        let read_only = matches!(req.method(), Method::Get | Method::Head) || unversioned_path(&req) == "/chain/validate";
+        if tokens.is_empty() || read_only {
+            return Ok(next.run(req).await);
+        }
+        let presented = req
+            .header("Authorization")
+            .and_then(|values| values.as_str().strip_prefix("Bearer "))
+            .map(str::trim);
+        match presented {
+            Some(token) if tokens.iter().any(|known| known == token) => Ok(next.run(req).await),
+            Some(_) => unauthorized("bearer token is not valid"),
+            None => unauthorized("mutating requests need an Authorization: Bearer header"),
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+// One token bucket per remote IP. GET /status stays exempt so monitoring
+// keeps working while a client is being throttled.
+pub struct RateLimit {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimit {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config: config,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+    // Returns how many seconds to wait when the bucket is empty.
+    fn take(&self, client: String) -> Result<(), u64> {
+        let now = Instant::now();
+        let burst = self.config.burst as f64;
+        let mut buckets = self.buckets.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: burst,
+            refilled_at: now,
+        });
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.config.per_second).min(burst);
+        bucket.refilled_at = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(((1.0 - bucket.tokens) / self.config.per_second).ceil() as u64)
+    }
+}
+
+fn client_of(req: &Request<State>) -> String {
+    match req.peer_addr() {
+        Some(addr) => match addr.parse::<SocketAddr>() {
+            Ok(socket) => socket.ip().to_string(),
+            Err(_) => String::from(addr),
+        },
+        None => String::from("unknown"),
+    }
+}
+
+#[tide::utils::async_trait]
+impl Middleware<State> for RateLimit {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> tide::Result {
+        if req.method() == Method::Get && unversioned_path(&req) == "/status" {
+            return Ok(next.run(req).await);
+        }
+        match self.take(client_of(&req)) {
+            Ok(()) => Ok(next.run(req).await),
+            Err(retry_after) => {
+                let mut res = error_response(APIErrorAndReason::rate_limited(retry_after))?;
+                res.insert_header("Retry-After", retry_after.to_string());
+                Ok(res)
+            }
+        }
+    }
+}
+
+const CORS_ALLOWED_METHODS: &str = "GET, POST, DELETE";
+const CORS_ALLOWED_HEADERS: &str = "Authorization, Content-Type, X-Node-Id";
+
+// Requests from origins that are not listed get no CORS headers at all, which
+// is what makes the browser refuse them.
+pub struct Cors {
+    origins: Vec<String>,
+}
+
+impl Cors {
+    pub fn new(origins: Vec<String>) -> Self {
+        Self { origins: origins }
+    }
+    fn allow_origin(&self, origin: &str) -> Option<String> {
+        if self.origins.iter().any(|allowed| allowed == "*") {
+            return Some(String::from("*"));
+        }
+        self.origins
+            .iter()
+            .find(|allowed| allowed.as_str() == origin)
+            .cloned()
+    }
+}
+
+#[tide::utils::async_trait]
+impl Middleware<State> for Cors {
+    async fn handle(&self, req: Request<State>, next: Next<'_, State>) -> tide::Result {
+        let allowed = req
+            .header("Origin")
+            .and_then(|origin| self.allow_origin(origin.as_str()));
+        let allowed = match allowed {
+            Some(allowed) => allowed,
+            None => return Ok(next.run(req).await),
+        };
+        let preflight = req.method() == Method::Options && req.header("Access-Control-Request-Method").is_some();
+        let mut res = match preflight {
+            true => {
+                let mut res = Response::new(StatusCode::NoContent);
+                res.insert_header("Access-Control-Allow-Methods", CORS_ALLOWED_METHODS);
+                res.insert_header("Access-Control-Allow-Headers", CORS_ALLOWED_HEADERS);
+                res.insert_header("Access-Control-Max-Age", "86400");
+                res
+            }
+            false => next.run(req).await,
+        };
+        res.insert_header("Access-Control-Allow-Origin", allowed);
+        res.insert_header("Vary", "Origin");
+        Ok(res)
+    }
+}
+
+fn body_too_large(limit: usize) -> tide::Result {
+    error_response(APIErrorAndReason::body_too_large(limit))
+}
+
+fn unauthorized(reason: &str) -> tide::Result {
+    let mut res = error_response(APIErrorAndReason::unauthorized(String::from(reason)))?;
+    res.insert_header("WWW-Authenticate", "Bearer");
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::config::AppConfig;
+    use crate::api::server::create_app;
+    use crate::blockchain::block::Block;
+    use crate::peers::MemberEntry;
+    use async_std::io::Cursor;
+    use tide::Body;
+    use tide::http::{Request, Url};
+    use tide::Server;
+
+    fn arrange_app(max_body_bytes: usize) -> Server<State> {
+        create_app(
+            AppConfig::builder()
+                .genesis_data("Genesis block sample")
+                .max_body_bytes(max_body_bytes)
+                .build(),
+        ).unwrap()
+    }
+
+    async fn arrange_next_block(app: &Server<State>, message: &str) -> Block {
+        Block::builder()
+            .after(&app.state().chain.last().await.unwrap())
+            .data_message(message)
+            .build()
+    }
+
+    async fn request_post(path: &str, body: Body, app: &Server<State>) -> tide::http::Response {
+        let url = Url::parse(&format!("https://example.com{}", path)).unwrap();
+        let mut req = Request::new(Method::Post, url);
+        req.set_body(body);
+        app.respond(req).await.unwrap()
+    }
+
+    #[async_std::test]
+    async fn body_at_the_limit_is_accepted() {
+        let probe = arrange_app(1024);
+        let block = arrange_next_block(&probe, "fits exactly").await;
+        let content = serde_json::to_string(&block).unwrap();
+        let app = create_app(
+            AppConfig::builder()
+                .genesis_block(probe.state().chain.get(0).await.unwrap())
+                .max_body_bytes(content.len())
+                .build(),
+        ).unwrap();
+        let res = request_post("/blocks", Body::from(content), &app).await;
+        assert_eq!(201, res.status());
+    }
+
+    #[async_std::test]
+    async fn oversized_bodies_are_rejected() {
+        let app = arrange_app(256);
+        let block = arrange_next_block(&app, &"x".repeat(512)).await;
+        let mut res = request_post("/blocks", Body::from_json(&block).unwrap(), &app).await;
+        assert_eq!(413, res.status());
+        let report: APIErrorAndReason = serde_json::from_str(&res.body_string().await.unwrap()).unwrap();
+        assert_eq!(report.reason, "request body exceeds the limit of 256 bytes");
+
+        let member = MemberEntry {
+            peer: format!("http://localhost:9090/{}", "x".repeat(512)),
+        };
+        let res = request_post("/peers", Body::from_json(&member).unwrap(), &app).await;
+        assert_eq!(413, res.status());
+        assert_eq!(1, app.state().chain.len().await);
+        assert!(app.state().lock_peers().members.is_empty());
+    }
+
+    #[async_std::test]
+    async fn lying_content_length_is_cut_off() {
+        let app = arrange_app(256);
+        let block = arrange_next_block(&app, &"x".repeat(512)).await;
+        let content = serde_json::to_vec(&block).unwrap();
+
+        let understated = Body::from_reader(Cursor::new(content.clone()), Some(64));
+        let res = request_post("/blocks", understated, &app).await;
+        assert_eq!(400, res.status());
+
+        let undeclared = Body::from_reader(Cursor::new(content), None);
+        let res = request_post("/blocks", undeclared, &app).await;
+        assert_eq!(413, res.status());
+        assert_eq!(1, app.state().chain.len().await);
+    }
+
+    fn arrange_guarded_app() -> Server<State> {
+        create_app(
+            AppConfig::builder()
+                .genesis_data("Genesis block sample")
+                .token("s3cr3t")
+                .build(),
+        ).unwrap()
+    }
+
+    async fn request_post_with_token(path: &str, body: Body, token: &str, app: &Server<State>) -> tide::http::Response {
+        let url = Url::parse(&format!("https://example.com{}", path)).unwrap();
+        let mut req = Request::new(Method::Post, url);
+        req.insert_header("Authorization", format!("Bearer {}", token));
+        req.set_body(body);
+        app.respond(req).await.unwrap()
+    }
+
+    #[async_std::test]
+    async fn mutations_without_a_valid_token_are_rejected() {
+        let app = arrange_guarded_app();
+        let block = arrange_next_block(&app, "unauthorized").await;
+        let mut res = request_post("/blocks", Body::from_json(&block).unwrap(), &app).await;
+        assert_eq!(401, res.status());
+        assert_eq!(res.header("WWW-Authenticate").unwrap().as_str(), "Bearer");
+        let report: APIErrorAndReason = serde_json::from_str(&res.body_string().await.unwrap()).unwrap();
+        assert_eq!(report.error, "Authentication required");
+
+        let res = request_post_with_token("/blocks", Body::from_json(&block).unwrap(), "guess", &app).await;
+        assert_eq!(401, res.status());
+        let member = MemberEntry {
+            peer: String::from("http://localhost:9090"),
+        };
+        let res = request_post("/peers", Body::from_json(&member).unwrap(), &app).await;
+        assert_eq!(401, res.status());
+        let url = Url::parse("https://example.com/peers?peer=http://localhost:9090").unwrap();
+        let res: tide::http::Response = app.respond(Request::new(Method::Delete, url)).await.unwrap();
+        assert_eq!(401, res.status());
+        assert_eq!(1, app.state().chain.len().await);
+    }
+
+    #[async_std::test]
+    async fn mutations_with_a_token_succeed_and_reads_stay_open() {
+        let app = arrange_guarded_app();
+        let block = arrange_next_block(&app, "authorized").await;
+        let res = request_post_with_token("/blocks", Body::from_json(&block).unwrap(), "s3cr3t", &app).await;
+        assert_eq!(201, res.status());
+        let member = MemberEntry {
+            peer: String::from("http://localhost:9090"),
+        };
+        let res = request_post_with_token("/peers", Body::from_json(&member).unwrap(), "s3cr3t", &app).await;
+        assert_eq!(201, res.status());
+
+        for path in ["/blocks", "/blocks/last", "/peers", "/status"] {
+            let url = Url::parse(&format!("https://example.com{}", path)).unwrap();
+            let res: tide::http::Response = app.respond(Request::new(Method::Get, url)).await.unwrap();
+            assert_eq!(200, res.status(), "GET {}", path);
+        }
+    }
+
+    async fn request_from(client: &str, path: &str, app: &Server<State>) -> tide::http::Response {
+        let url = Url::parse(&format!("https://example.com{}", path)).unwrap();
+        let mut req = Request::new(Method::Get, url);
+        req.set_peer_addr(Some(client));
+        app.respond(req).await.unwrap()
+    }
+
+    #[async_std::test]
+    async fn rate_limit_throttles_each_client() {
+        let app = create_app(
+            AppConfig::builder()
+                .genesis_data("Genesis block sample")
+                .rate_limit(10.0, 3)
+                .build(),
+        ).unwrap();
+        for _ in 0..3 {
+            assert_eq!(200, request_from("10.0.0.1:4000", "/blocks", &app).await.status());
+        }
+        let mut res = request_from("10.0.0.1:4001", "/blocks", &app).await;
+        assert_eq!(429, res.status());
+        assert_eq!(res.header("Retry-After").unwrap().as_str(), "1");
+        let report: APIErrorAndReason = serde_json::from_str(&res.body_string().await.unwrap()).unwrap();
+        assert_eq!(report.error, "Too many requests");
+
+        assert_eq!(200, request_from("10.0.0.2:4000", "/blocks", &app).await.status());
+        assert_eq!(200, request_from("10.0.0.1:4000", "/status", &app).await.status());
+
+        async_std::task::sleep(std::time::Duration::from_millis(150)).await;
+        assert_eq!(200, request_from("10.0.0.1:4000", "/blocks", &app).await.status());
+    }
+
+    #[async_std::test]
+    async fn rate_limit_is_off_by_default() {
+        let app = arrange_app(1024);
+        for _ in 0..50 {
+            assert_eq!(200, request_from("10.0.0.1:4000", "/blocks", &app).await.status());
+        }
+    }
+
+    fn arrange_cors_app() -> Server<State> {
+        create_app(
+            AppConfig::builder()
+                .genesis_data("Genesis block sample")
+                .cors_origin("https://explorer.example.com")
+                .token("s3cr3t")
+                .build(),
+        ).unwrap()
+    }
+
+    async fn request_with_origin(method: Method, origin: &str, app: &Server<State>) -> tide::http::Response {
+        let url = Url::parse("https://example.com/blocks").unwrap();
+        let mut req = Request::new(method, url);
+        req.insert_header("Origin", origin);
+        if method == Method::Options {
+            req.insert_header("Access-Control-Request-Method", "POST");
+            req.insert_header("Access-Control-Request-Headers", "Authorization");
+        }
+        app.respond(req).await.unwrap()
+    }
+
+    #[async_std::test]
+    async fn cors_answers_allowed_origins() {
+        let app = arrange_cors_app();
+        let res = request_with_origin(Method::Options, "https://explorer.example.com", &app).await;
+        assert_eq!(204, res.status());
+        assert_eq!(res.header("Access-Control-Allow-Origin").unwrap().as_str(), "https://explorer.example.com");
+        assert_eq!(res.header("Access-Control-Allow-Methods").unwrap().as_str(), "GET, POST, DELETE");
+        assert!(res.header("Access-Control-Allow-Headers").unwrap().as_str().contains("Authorization"));
+
+        let res = request_with_origin(Method::Get, "https://explorer.example.com", &app).await;
+        assert_eq!(200, res.status());
+        assert_eq!(res.header("Access-Control-Allow-Origin").unwrap().as_str(), "https://explorer.example.com");
+    }
+
+    #[async_std::test]
+    async fn cors_ignores_other_origins() {
+        let app = arrange_cors_app();
+        let res = request_with_origin(Method::Options, "https://evil.example.com", &app).await;
+        assert_ne!(204, res.status());
+        assert!(res.header("Access-Control-Allow-Origin").is_none());
+
+        let res = request_with_origin(Method::Get, "https://evil.example.com", &app).await;
+        assert_eq!(200, res.status());
+        assert!(res.header("Access-Control-Allow-Origin").is_none());
+    }
+
+    #[async_std::test]
+    async fn cors_is_off_by_default() {
+        let app = arrange_app(1024);
+        let res = request_with_origin(Method::Get, "https://explorer.example.com", &app).await;
+        assert_eq!(200, res.status());
+        assert!(res.header("Access-Control-Allow-Origin").is_none());
+    }
+}
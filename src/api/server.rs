@@ -1,114 +1,417 @@
-use crate::api::errors::APIErrorAndReason;
-use crate::api::structs::{BlockList, Limits, PeerList, State};
-use crate::peers::{EntryRejectedErr, MemberEntry};
-use crate::blockchain::block::{message_as_json, Block};
-use std::sync::Once;
+use crate::api::broadcast::{spawn_broadcast, NODE_ID_HEADER};
+use crate::api::config::AppConfig;
+use crate::api::errors::{error_response, APIErrorAndReason};
+use crate::api::metrics::{self, RequestMetrics, METRICS_CONTENT_TYPE};
+use crate::api::middleware::{BodyLimit, Cors, DeprecatedAliases, RateLimit, RequireToken, V1_PREFIX};
+use crate::api::structs::{
+    BatchReport, BlockList, Limits, PeerList, State, SyncPending, ValidationReport, CHAIN_HEIGHT_HEADER,
+    CHAIN_TIP_HASH_HEADER,
+};
+use crate::api::sync::spawn_catch_up;
+use crate::blockchain::{Chain, InvalidBlockErr};
+use crate::peers::MemberEntry;
+use crate::blockchain::block::Block;
+use async_std::io::BufReader;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::sync::{Arc, Once};
+use tide::sse::Sender;
 use tide::{Body, Request, Response, Server, StatusCode};
 
 static INIT: Once = Once::new();
 
-#[async_std::main]
-pub async fn main() -> tide::Result<()> {
-    create_app(String::from(""))
-        .listen("127.0.0.1:8080")
-        .await?;
+pub async fn run(config: AppConfig) -> tide::Result<()> {
+    let bind_addr = config.bind_addr.clone();
+    create_app(config)?.listen(bind_addr).await?;
     Ok(())
 }
 
 async fn get_last_block(req: Request<State>) -> tide::Result<Response> {
-    let state = req.state();
-    let chain = &state.chain.lock().unwrap();
-    let block: &Block = chain.get_last_block().unwrap();
+    let block: Block = match req.state().chain.last().await {
+        Some(block) => block,
+        None => return error_response(InvalidBlockErr::GenesisBlockNotFound),
+    };
     let mut res = Response::new(tide::StatusCode::Ok);
-    res.set_body(Body::from_json(block)?);
+    res.set_body(Body::from_json(&block)?);
     Ok(res)
 }
 
-async fn list_blocks(req: Request<State>) -> tide::Result<Response> {
-    let limits: Limits = req.query()?;
+async fn get_status(req: Request<State>) -> tide::Result<Response> {
+    let mut res = Response::new(StatusCode::Ok);
+    res.set_body(Body::from_json(&req.state().status().await)?);
+    Ok(res)
+}
+
+async fn get_metrics(req: Request<State>) -> tide::Result<Response> {
+    let mut res = Response::new(StatusCode::Ok);
+    res.set_body(metrics::render(req.state()).await);
+    res.set_content_type(METRICS_CONTENT_TYPE);
+    Ok(res)
+}
+
+fn validation_response(outcome: Result<u64, (u64, InvalidBlockErr)>) -> tide::Result<Response> {
+    match outcome {
+        Ok(height) => {
+            let mut res = Response::new(StatusCode::Ok);
+            res.set_body(Body::from_json(&ValidationReport { valid: true, height: height })?);
+            Ok(res)
+        }
+        // A broken stored chain conflicts with the node's own state rather
+        // than with anything the caller sent.
+        Err((index, error)) => {
+            let mut res = error_response(APIErrorAndReason::at_block(index, error))?;
+            res.set_status(StatusCode::Conflict);
+            Ok(res)
+        }
+    }
+}
+
+async fn get_chain_validation(req: Request<State>) -> tide::Result<Response> {
+    let chain = &req.state().chain;
+    let outcome = match chain.find_broken_link().await {
+        Some(broken) => Err(broken),
+        None => Ok(chain.info().await.height),
+    };
+    validation_response(outcome)
+}
+
+async fn post_chain_validation(mut req: Request<State>) -> tide::Result<Response> {
+    let list: BlockList = match read_json(&mut req).await {
+        Ok(list) => list,
+        Err(error) => return error_response(error),
+    };
+    let chain = &req.state().chain;
+    let height = match list.items.last() {
+        Some(last) => last.index + 1,
+        None => chain.info().await.height,
+    };
+    validation_response(chain.dry_run(&list.items).await.map(|_| height))
+}
+
+async fn get_block(req: Request<State>) -> tide::Result<Response> {
+    let index: u64 = match req.param("index").map(str::parse) {
+        Ok(Ok(index)) => index,
+        _ => {
+            let reason = format!("block index must be a number or \"last\", got {:?}", req.param("index").unwrap_or(""));
+            return error_response(APIErrorAndReason::invalid_query(reason));
+        }
+    };
     let state = req.state();
+    match state.chain.get(index).await {
+        Some(block) => {
+            let mut res = Response::new(StatusCode::Ok);
+            res.set_body(Body::from_json(&block)?);
+            Ok(res)
+        }
+        None => {
+            let height = state.chain.info().await.height;
+            error_response(APIErrorAndReason::block_not_found(index, height))
+        }
+    }
+}
 
-    let chain = &state.chain.lock().unwrap();
-    let items: Vec<Block> = chain.blocks[limits.from_index..].iter().cloned().collect();
-    let blocks = BlockList { items: items };
-    let mut res = Response::new(tide::StatusCode::Ok);
-    res.set_body(Body::from_json(&blocks)?);
+async fn list_blocks(req: Request<State>) -> tide::Result<Response> {
+    let limits: Limits = match req.query() {
+        Ok(limits) => limits,
+        Err(error) => return error_response(APIErrorAndReason::invalid_query(error.to_string())),
+    };
+    let mut res = match wants_ndjson(&req) {
+        true => stream_ndjson(req.state().clone(), limits).await?,
+        false => {
+            let page = req.state().page(&limits).await;
+            let mut res = Response::new(tide::StatusCode::Ok);
+            res.set_body(Body::from_json(&page)?);
+            res
+        }
+    };
+    insert_chain_headers(req.state(), &mut res).await;
+    Ok(res)
+}
+
+async fn head_blocks(req: Request<State>) -> tide::Result<Response> {
+    let mut res = Response::new(StatusCode::Ok);
+    insert_chain_headers(req.state(), &mut res).await;
+    Ok(res)
+}
+
+// Lets a client paging through the chain notice it grew mid-sync.
+async fn insert_chain_headers(state: &State, res: &mut Response) {
+    let info = state.chain.info().await;
+    res.insert_header(CHAIN_HEIGHT_HEADER, info.height.to_string());
+    res.insert_header(CHAIN_TIP_HASH_HEADER, info.last_hash);
+}
+
+const NDJSON_MIME: &str = "application/x-ndjson";
+const NDJSON_BATCH_SIZE: u64 = 64;
+
+#[derive(Deserialize)]
+struct FormatQuery {
+    format: Option<String>,
+}
+
+fn wants_ndjson(req: &Request<State>) -> bool {
+    let by_query = match req.query::<FormatQuery>() {
+        Ok(FormatQuery { format: Some(format) }) => format == "ndjson",
+        _ => false,
+    };
+    let by_accept = req
+        .header("Accept")
+        .map_or(false, |accept| accept.as_str().contains(NDJSON_MIME));
+    by_query || by_accept
+}
+
+// Unpaged, one block per line. Blocks are copied out of the chain a batch at a
+// time by a producer task, so the lock is only held while a batch is cloned and
+// never while a slow client drains the response.
+async fn stream_ndjson(state: State, limits: Limits) -> tide::Result<Response> {
+    use futures::{StreamExt, TryStreamExt};
+    let from_index = limits.from_index as u64;
+    let height = state.chain.info().await.height;
+    let end = match limits.limit {
+        Some(limit) => height.min(from_index.saturating_add(limit as u64)),
+        None => height,
+    };
+    let (sender, receiver) = async_std::channel::bounded::<Vec<u8>>(4);
+    async_std::task::spawn(async move {
+        let mut next_index = from_index;
+        while next_index < end {
+            let batch_end = end.min(next_index + NDJSON_BATCH_SIZE);
+            let mut lines = Vec::new();
+            for block in state.chain.range(next_index, batch_end).await {
+                serde_json::to_writer(&mut lines, &block).unwrap();
+                lines.push(b'\n');
+            }
+            if sender.send(lines).await.is_err() {
+                break;
+            }
+            next_index = batch_end;
+        }
+    });
+    let reader = BufReader::new(receiver.map(Ok::<_, std::io::Error>).into_async_read());
+    let mut res = Response::new(StatusCode::Ok);
+    res.set_body(Body::from_reader(reader, None));
+    res.set_content_type(NDJSON_MIME);
     Ok(res)
 }
 
+fn malformed_block(reason: String) -> tide::Result<Response> {
+    error_response(APIErrorAndReason::malformed_block(reason))
+}
+
+// Bodies that are not valid JSON for `T` get a structured 400 instead of
+// tide's plain text error, so clients can always parse the failure.
+async fn read_json<T: DeserializeOwned>(req: &mut Request<State>) -> Result<T, APIErrorAndReason> {
+    let content = req
+        .body_string()
+        .await
+        .map_err(|error| APIErrorAndReason::malformed_body(error.to_string()))?;
+    serde_json::from_str(&content).map_err(|error| APIErrorAndReason::malformed_body(error.to_string()))
+}
+
 async fn add_block(mut req: Request<State>) -> tide::Result<Response> {
-    let block: Block = req.body_json().await?;
+    let block: Block = match read_json(&mut req).await {
+        Ok(block) => block,
+        Err(error) => return error_response(error),
+    };
+    if let Err(reason) = block.validate_shape() {
+        return malformed_block(reason);
+    }
+    let origin = req.header(NODE_ID_HEADER).map(|values| values.as_str().to_string());
     let state = req.state();
-    let added = state.append_block(block);
+    if let Some(tip) = state.chain.last().await {
+        if tip == block && tip.hash() == block.hash() {
+            let mut res = Response::new(StatusCode::Ok);
+            res.set_body(Body::from_json(&tip)?);
+            return Ok(res);
+        }
+    }
+    let pending = block.clone();
+    let index = block.index;
+    let added = state.append_block(block).await;
 
     match added {
         Ok(new_block) => {
-            let mut res = Response::new(StatusCode::Ok);
+            spawn_broadcast(state, new_block.clone(), origin);
+            let mut res = Response::new(StatusCode::Created);
+            res.insert_header("Location", format!("/blocks/{}", new_block.index));
             res.set_body(Body::from_json(&new_block)?);
             Ok(res)
         }
-        error => {
-            let mut res = Response::new(StatusCode::BadRequest);
-            let error_and_reason = APIErrorAndReason::from(error.unwrap_err());
-            res.set_body(Body::from_json(&error_and_reason)?);
+        Err(InvalidBlockErr::NotCorrelated(given, last)) if given > last + 1 => {
+            if !spawn_catch_up(state, pending, origin) {
+                let error = APIErrorAndReason::from(InvalidBlockErr::NotCorrelated(given, last));
+                return error_response(error.with_block_index(index));
+            }
+            let mut res = Response::new(StatusCode::Accepted);
+            res.set_body(Body::from_json(&SyncPending {
+                reason: format!("block {} is ahead of our tip at {}, syncing missing blocks", given, last),
+                from_index: last + 1,
+                pending_index: given,
+            })?);
             Ok(res)
         }
+        Err(error) => error_response(APIErrorAndReason::from(error).with_block_index(index)),
     }
 }
 
-async fn add_peer(mut req: Request<State>) -> tide::Result<Response> {
-    let addition: MemberEntry = req.body_json().await?;
-    let state = req.state();
-    let added = state.add_peer(addition);
-    match added {
-        Ok(_new_addition) => {
-            let res = Response::new(StatusCode::Created);
+async fn add_blocks_batch(mut req: Request<State>) -> tide::Result<Response> {
+    let list: BlockList = match read_json(&mut req).await {
+        Ok(list) => list,
+        Err(error) => return error_response(error),
+    };
+    if let Some(reason) = list.items.iter().find_map(|block| block.validate_shape().err()) {
+        return malformed_block(reason);
+    }
+    let blocks = list.sorted().items;
+    let indexes: Vec<u64> = blocks.iter().map(|block| block.index).collect();
+    match req.state().append_batch(blocks).await {
+        Ok(appended) => {
+            let status = match appended {
+                0 => StatusCode::Ok,
+                _ => StatusCode::Created,
+            };
+            let mut res = Response::new(status);
+            res.set_body(Body::from_json(&BatchReport { appended: appended })?);
             Ok(res)
         }
-        Err(error) => match error {
-            EntryRejectedErr::AlreadyPresent(_entry) => {
-                let res = Response::new(StatusCode::Ok);
-                Ok(res)
-            }
-            EntryRejectedErr::InvalidURL(reason) => {
-                let mut res = Response::new(StatusCode::BadRequest);
-                let rejected = APIErrorAndReason {
-                    error: String::from("Peer rejected"),
-                    reason: reason,
-                };
-                res.set_body(Body::from_json(&rejected)?);
-                Ok(res)
-            }
-            _ => {
-                let mut res = Response::new(StatusCode::BadRequest);
-                let unknown = APIErrorAndReason {
-                    error: String::from("Unknown error"),
-                    reason: String::from("reason"),
-                };
-                res.set_body(Body::from_json(&unknown)?);
-                Ok(res)
+        Err((offset, error)) => {
+            let located = APIErrorAndReason::at_batch_offset(offset, error);
+            match indexes.get(offset) {
+                Some(index) => error_response(located.with_block_index(*index)),
+                None => error_response(located),
             }
+        }
+    }
+}
+
+async fn post_peer(mut req: Request<State>) -> tide::Result<Response> {
+    let addition: MemberEntry = match read_json(&mut req).await {
+        Ok(addition) => addition,
+        Err(error) => return error_response(error),
+    };
+    let state = req.state();
+    let peer = addition.peer.clone();
+    match state.add_peer(addition) {
+        Ok(stored) => {
+            let mut res = Response::new(StatusCode::Created);
+            res.set_body(Body::from_json(&stored)?);
+            Ok(res)
+        }
+        Err(error) => error_response(APIErrorAndReason::from(error).with_peer(peer)),
+    }
+}
+
+#[derive(Deserialize)]
+struct StreamQuery {
+    from_index: Option<u64>,
+}
+
+// Subscribes before replaying so nothing appended in between is missed. A
+// closed connection surfaces as a failed send, which drops the subscription.
+async fn stream_blocks(req: Request<State>, sender: Sender) -> tide::Result<()> {
+    let query: StreamQuery = req.query().unwrap_or(StreamQuery { from_index: None });
+    let chain = &req.state().chain;
+    let receiver = chain.subscribe().await;
+    let mut next_index = chain.info().await.height;
+    if let Some(from_index) = query.from_index {
+        for block in chain.range(from_index, next_index).await {
+            sender.send("block", serde_json::to_string(&block)?, Some(&block.index.to_string())).await?;
+        }
+    }
+    while let Ok(block) = receiver.recv().await {
+        if block.index < next_index && query.from_index.is_some() {
+            continue;
+        }
+        next_index = block.index + 1;
+        sender.send("block", serde_json::to_string(&block)?, Some(&block.index.to_string())).await?;
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct PeerQuery {
+    peer: Option<String>,
+}
+
+async fn delete_peer(mut req: Request<State>) -> tide::Result<Response> {
+    let query: PeerQuery = req.query().unwrap_or(PeerQuery { peer: None });
+    let removal: MemberEntry = match query.peer {
+        Some(peer) => MemberEntry { peer: peer },
+        None => match read_json(&mut req).await {
+            Ok(removal) => removal,
+            Err(error) => return error_response(error),
         },
+    };
+    match req.state().remove_peer(&removal) {
+        Ok(removed) => {
+            let mut res = Response::new(StatusCode::Ok);
+            res.set_body(Body::from_json(&removed)?);
+            Ok(res)
+        }
+        Err(error) => error_response(APIErrorAndReason::from(error).with_peer(removal.peer)),
     }
 }
 
-async fn list_peers(req: Request<State>) -> tide::Result<Response> {
+async fn get_peers(req: Request<State>) -> tide::Result<Response> {
     let state = req.state();
-    let peers = &state.peers.lock().unwrap();
-    let items: Vec<MemberEntry> = peers.members.clone();
+    let items: Vec<MemberEntry> = state.lock_peers().members.clone();
     let peers = PeerList { items: items };
     let mut res = Response::new(tide::StatusCode::Ok);
     res.set_body(Body::from_json(&peers)?);
     Ok(res)
 }
 
-pub fn create_app(genesis_data: String) -> Server<State> {
+pub fn create_app_with_genesis(genesis_data: String, genesis_block: Option<Block>) -> Result<Server<State>, InvalidBlockErr> {
+    let mut config = AppConfig::builder().genesis_data(genesis_data).build();
+    config.genesis_block = genesis_block;
+    create_app(config)
+}
+
+// The genesis block comes from the operator, so a bad one is reported
+// rather than taking the process down.
+pub fn create_app(config: AppConfig) -> Result<Server<State>, InvalidBlockErr> {
     INIT.call_once(tide::log::start);
-    let mut app = tide::with_state(State::new(genesis_data));
-    app.at("/blocks/last").get(get_last_block);
-    app.at("/blocks").post(add_block).get(list_blocks);
-    app.at("/peers").post(add_peer).get(list_peers);
-    app
+    let mut chain = match config.genesis_block {
+        Some(block) => Chain::with_genesis(block)?,
+        None => Chain::new(config.genesis_data),
+    };
+    chain.set_difficulty(config.difficulty);
+    chain.set_max_data_bytes(config.max_data_bytes);
+    let mut state = State::with_chain(chain);
+    state.max_page_size = config.max_page_size;
+    state.max_body_bytes = config.max_body_bytes;
+    state.node_id = config.node_id;
+    state.tokens = Arc::new(config.tokens);
+    let mut app = tide::with_state(state.clone());
+    app.with(RequestMetrics);
+    if !config.cors_origins.is_empty() {
+        app.with(Cors::new(config.cors_origins));
+    }
+    if let Some(rate_limit) = config.rate_limit {
+        app.with(RateLimit::new(rate_limit));
+    }
+    app.with(RequireToken);
+    app.with(BodyLimit);
+    app.with(DeprecatedAliases);
+    let mut v1 = tide::with_state(state);
+    v1_routes(&mut v1);
+    app.at(V1_PREFIX).nest(v1);
+    // Unprefixed paths predate versioning and stay as deprecated aliases of v1.
+    v1_routes(&mut app);
+    Ok(app)
+}
+
+fn v1_routes(server: &mut Server<State>) {
+    server.at("/blocks/last").get(get_last_block);
+    server.at("/blocks/batch").post(add_blocks_batch);
+    server.at("/blocks/stream").get(tide::sse::endpoint(stream_blocks));
+    server.at("/blocks/:index").get(get_block);
+    server.at("/blocks").post(add_block).get(list_blocks).head(head_blocks);
+    server.at("/peers").post(post_peer).get(get_peers).delete(delete_peer);
+    server.at("/status").get(get_status);
+    server.at("/metrics").get(get_metrics);
+    server.at("/ws").get(crate::api::ws::upgrade);
+    server.at("/chain/validate").get(get_chain_validation).post(post_chain_validation);
 }
 
 #[cfg(test)]
@@ -116,17 +419,19 @@ mod tests {
 
     use super::*;
     use tide::http::{Method, Request, Response, Url};
+    use crate::api::structs::{NodeStatus, PagedBlockList};
+    use crate::blockchain::block::message_as_json;
+    use crate::peers::EntryRejectedErr;
+    use std::collections::HashMap;
 
-    fn arrange_second_block(app: &Server<State>) {
-        let mut chain = app.state().chain.lock().unwrap();
-        let first_block = &chain.blocks[0];
-        let second = Block {
-            index: 1,
-            previous_hash: first_block.hash(),
-            timestamp: first_block.timestamp + 100,
-            data: message_as_json("Second block data"),
-        };
-        chain.append(second).unwrap();
+    async fn arrange_second_block(app: &Server<State>) {
+        let chain = &app.state().chain;
+        let first_block = chain.get(0).await.unwrap();
+        let second = Block::builder()
+            .after(&first_block)
+            .data_message("Second block data")
+            .build();
+        chain.append(second).await.unwrap();
     }
 
     async fn request_get_block(position: &str, app: &Server<State>) -> tide::Result<Response> {
@@ -155,9 +460,16 @@ mod tests {
         Ok(res)
     }
 
+    async fn request_add_raw_block(content: &str, app: &Server<State>) -> tide::Result<Response> {
+        let url = Url::parse("https://example.com/blocks").unwrap();
+        let mut req = Request::new(Method::Post, url);
+        req.set_body(content);
+        let res: Response = app.respond(req).await?;
+        Ok(res)
+    }
+
     async fn get_block_from_server_status(app: &Server<State>, index: u32) -> Block {
-        let chain = &app.state().chain.lock().unwrap();
-        chain.blocks[index as usize].clone()
+        app.state().chain.get(index as u64).await.unwrap()
     }
 
     async fn block_from_body(mut response: Response) -> Result<Block, serde_json::Error> {
@@ -179,28 +491,40 @@ mod tests {
 
     #[async_std::test]
     async fn get_last_block_being_genesis() -> tide::Result<()> {
-        let app = create_app(String::from("Genesis block sample"));
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
         let confirmation: Response = request_get_block("last", &app).await?;
         let received_block: Block = block_from_body(confirmation).await?;
         assert_eq!(0, received_block.index);
         assert_eq!(
             "Genesis block sample",
-            received_block.data.get("message").unwrap()
+            received_block.message().unwrap()
         );
         assert_eq!("", received_block.previous_hash);
         Ok(())
     }
 
+    #[async_std::test]
+    async fn two_apps_share_a_custom_genesis() -> tide::Result<()> {
+        let genesis = Block::genesis(message_as_json("Shared genesis"), 1000);
+        let one = create_app_with_genesis(String::from(""), Some(genesis.clone())).unwrap();
+        let another = create_app_with_genesis(String::from(""), Some(genesis.clone())).unwrap();
+        let one_block = block_from_body(request_get_block("last", &one).await?).await?;
+        let another_block = block_from_body(request_get_block("last", &another).await?).await?;
+        assert_eq!(one_block, genesis);
+        assert_eq!(one_block.hash(), another_block.hash());
+        Ok(())
+    }
+
     #[async_std::test]
     async fn get_first_block_being_genesis() -> tide::Result<()> {
-        let app = create_app(String::from("Genesis block sample"));
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
         let confirmation = request_list_blocks("from_index=0", &app).await?;
         let received_list: BlockList = block_list_from_body(confirmation).await?;
         assert_eq!(1, received_list.items.len());
         let received_block: Block = received_list.items[0].clone();
         assert_eq!(
             "Genesis block sample",
-            received_block.data.get("message").unwrap()
+            received_block.message().unwrap()
         );
         assert_eq!("", received_block.previous_hash);
         assert_eq!(0, received_block.index);
@@ -209,37 +533,37 @@ mod tests {
 
     #[async_std::test]
     async fn get_last_block_being_second() -> tide::Result<()> {
-        let app = create_app(String::from("Genesis block sample"));
-        arrange_second_block(&app);
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        arrange_second_block(&app).await;
         let confirmation = request_get_block("last", &app).await?;
         let received_block = block_from_body(confirmation).await?;
         assert_eq!(1, received_block.index);
         assert_eq!(
             "Second block data",
-            received_block.data.get("message").unwrap()
+            received_block.message().unwrap()
         );
         Ok(())
     }
 
     #[async_std::test]
     async fn get_block_one_being_list_first() -> tide::Result<()> {
-        let app = create_app(String::from("Genesis block sample"));
-        arrange_second_block(&app);
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        arrange_second_block(&app).await;
         let confirmation = request_list_blocks("from_index=1", &app).await?;
         let received_list: BlockList = block_list_from_body(confirmation).await?;
         let obtained_block: Block = received_list.items[0].clone();
         assert_eq!(1, obtained_block.index);
         assert_eq!(
             "Second block data",
-            obtained_block.data.get("message").unwrap()
+            obtained_block.message().unwrap()
         );
         Ok(())
     }
 
     #[async_std::test]
     async fn get_genesis_block_being_list_first() -> tide::Result<()> {
-        let app = create_app(String::from("Genesis block sample"));
-        arrange_second_block(&app);
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        arrange_second_block(&app).await;
         let confirmation = request_list_blocks("from_index=0", &app).await?;
         let received_list: BlockList = block_list_from_body(confirmation).await?;
         let obtained_block: Block = received_list.items[0].clone();
@@ -247,50 +571,464 @@ mod tests {
         assert_eq!(0, obtained_block.index);
         assert_eq!(
             "Genesis block sample",
-            obtained_block.data.get("message").unwrap()
+            obtained_block.message().unwrap()
         );
         Ok(())
     }
 
     #[async_std::test]
     async fn get_no_blocks_from_one() -> tide::Result<()> {
-        let app = create_app(String::from("Genesis block sample"));
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
         let confirmation = request_list_blocks("from_index=1", &app).await?;
         let received_list: BlockList = block_list_from_body(confirmation).await?;
         assert_eq!(0, received_list.items.len());
         Ok(())
     }
 
+    #[async_std::test]
+    async fn get_no_blocks_from_chain_length() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        arrange_second_block(&app).await;
+        for limits in &["from_index=2", "from_index=10"] {
+            let confirmation = request_list_blocks(limits, &app).await?;
+            assert_eq!(200, confirmation.status());
+            let received_list = block_list_from_body(confirmation).await?;
+            assert_eq!(0, received_list.items.len());
+        }
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn get_blocks_with_garbage_index() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let confirmation = request_list_blocks("from_index=abc", &app).await?;
+        assert_eq!(400, confirmation.status());
+        let report = error_from_body(confirmation).await?;
+        assert_eq!("Invalid query parameters", report.error);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn get_block_by_index() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        arrange_second_block(&app).await;
+        let genesis = block_from_body(request_get_block("0", &app).await?).await?;
+        assert_eq!(genesis, get_block_from_server_status(&app, 0).await);
+        let tip = block_from_body(request_get_block("1", &app).await?).await?;
+        assert_eq!(tip, get_block_from_server_status(&app, 1).await);
+        let last = block_from_body(request_get_block("last", &app).await?).await?;
+        assert_eq!(last, tip);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn get_block_out_of_range() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let confirmation = request_get_block("7", &app).await?;
+        assert_eq!(404, confirmation.status());
+        let report = error_from_body(confirmation).await?;
+        assert_eq!("Block not found", report.error);
+        assert_eq!("no block with index 7, chain height is 1", report.reason);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn get_block_with_non_numeric_index() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let confirmation = request_get_block("first", &app).await?;
+        assert_eq!(400, confirmation.status());
+        let report = error_from_body(confirmation).await?;
+        assert!(report.reason.contains("\"first\""));
+        Ok(())
+    }
+
+    async fn arrange_ten_blocks(app: &Server<State>) {
+        for n in 1..10 {
+            app.state().chain.generate_and_append(&format!("Block {}", n)).await.unwrap();
+        }
+    }
+
+    #[async_std::test]
+    async fn page_through_blocks() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        arrange_ten_blocks(&app).await;
+        let mut from_index = Some(0);
+        let mut pages: Vec<PagedBlockList> = vec![];
+        while let Some(index) = from_index {
+            let mut response = request_list_blocks(&format!("from_index={}&limit=3", index), &app).await?;
+            let page: PagedBlockList = serde_json::from_str(&response.body_string().await?)?;
+            assert_eq!(page.total, 10);
+            assert_eq!(page.from_index, index);
+            from_index = page.next_index;
+            pages.push(page);
+        }
+        let sizes: Vec<usize> = pages.iter().map(|page| page.items.len()).collect();
+        assert_eq!(sizes, vec![3, 3, 3, 1]);
+        assert_eq!(pages[3].items[0].index, 9);
+        assert_eq!(pages[3].next_index, None);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn page_size_is_capped() -> tide::Result<()> {
+        let app = create_app(AppConfig::builder().genesis_data("Genesis block sample").max_page_size(4).build()).unwrap();
+        arrange_ten_blocks(&app).await;
+        let mut response = request_list_blocks("from_index=0&limit=50", &app).await?;
+        let page: PagedBlockList = serde_json::from_str(&response.body_string().await?)?;
+        assert_eq!(page.items.len(), 4);
+        assert_eq!(page.next_index, Some(4));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn body_limit_from_config_rejects_big_posts() -> tide::Result<()> {
+        let app = create_app(AppConfig::builder().genesis_data("Genesis block sample").max_body_bytes(512).build()).unwrap();
+        let first_block = get_block_from_server_status(&app, 0).await;
+        let small = Block::builder().after(&first_block).data_message("small").build();
+        assert_eq!(201, request_add_block(small.clone(), &app).await?.status());
+
+        let big = Block::builder().after(&small).data_message(&"x".repeat(1024)).build();
+        let response = request_add_block(big, &app).await?;
+        assert_eq!(413, response.status());
+        let report = error_from_body(response).await?;
+        assert_eq!(report.error, "Request body is too large");
+        assert_eq!(report.reason, "request body exceeds the limit of 512 bytes");
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn difficulty_from_config_rejects_unmined_blocks() -> tide::Result<()> {
+        let app = create_app(AppConfig::builder().genesis_data("Genesis block sample").difficulty(8).build()).unwrap();
+        let first_block = get_block_from_server_status(&app, 0).await;
+        let unmined = Block::builder().after(&first_block).data_message("lazy").build();
+        let response = request_add_block(unmined, &app).await?;
+        assert_eq!(400, response.status());
+        let report = error_from_body(response).await?;
+        assert_eq!(report.error, "Block does not meet the required difficulty");
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn empty_and_oversized_data_are_rejected() -> tide::Result<()> {
+        let app = create_app(AppConfig::builder().genesis_data("Genesis block sample").max_data_bytes(32).build()).unwrap();
+        let first_block = get_block_from_server_status(&app, 0).await;
+        let empty = Block::builder().after(&first_block).build();
+        let response = request_add_block(empty, &app).await?;
+        assert_eq!(400, response.status());
+        let report = error_from_body(response).await?;
+        assert_eq!(InvalidBlockErr::from(report), InvalidBlockErr::EmptyData);
+        let oversized = Block::builder().after(&first_block).data_message(&"x".repeat(32)).build();
+        let response = request_add_block(oversized, &app).await?;
+        assert_eq!(400, response.status());
+        let report = error_from_body(response).await?;
+        assert!(matches!(InvalidBlockErr::from(report), InvalidBlockErr::DataTooLarge(_, 32)));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn concurrent_reads_during_writes_stay_consistent() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let mut writers = vec![];
+        for worker in 0..4 {
+            let app = app.clone();
+            writers.push(async_std::task::spawn(async move {
+                for n in 0..10 {
+                    loop {
+                        let tip = app.state().chain.last().await.unwrap();
+                        let next = Block::builder()
+                            .after(&tip)
+                            .data_message(&format!("worker {} block {}", worker, n))
+                            .build();
+                        // Losing the race against another writer is expected; retry on the new tip.
+                        match request_add_block(next, &app).await.unwrap().status() {
+                            StatusCode::Created => break,
+                            status => assert_eq!(status, StatusCode::UnprocessableEntity),
+                        }
+                    }
+                }
+            }));
+        }
+        let mut readers = vec![];
+        for _ in 0..8 {
+            let app = app.clone();
+            readers.push(async_std::task::spawn(async move {
+                for _ in 0..25 {
+                    let last = request_get_block("last", &app).await.unwrap();
+                    assert_eq!(200, last.status());
+                    block_from_body(last).await.unwrap();
+                    let mut listed = request_list_blocks("from_index=0", &app).await.unwrap();
+                    assert_eq!(200, listed.status());
+                    let page: PagedBlockList = serde_json::from_str(&listed.body_string().await.unwrap()).unwrap();
+                    for (position, pair) in page.items.windows(2).enumerate() {
+                        assert_eq!(pair[0].index, position as u64);
+                        assert_eq!(pair[1].previous_hash, pair[0].hash());
+                    }
+                }
+            }));
+        }
+        for handle in writers.into_iter().chain(readers) {
+            handle.await;
+        }
+        assert_eq!(41, app.state().chain.len().await);
+        assert_eq!(None, app.state().chain.find_broken_link().await);
+        Ok(())
+    }
+
+    async fn request_ndjson(query: &str, accept: bool, app: &Server<State>) -> tide::Result<Response> {
+        let url = Url::parse(&format!("https://example.com/blocks?{}", query)).unwrap();
+        let mut req = Request::new(Method::Get, url);
+        if accept {
+            req.insert_header("Accept", "application/x-ndjson");
+        }
+        let res: Response = app.respond(req).await?;
+        Ok(res)
+    }
+
+    fn blocks_from_ndjson(content: &str) -> Vec<Block> {
+        content.lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+    }
+
+    #[async_std::test]
+    async fn ndjson_matches_json_list() -> tide::Result<()> {
+        let app = create_app(AppConfig::builder().genesis_data("Genesis block sample").max_page_size(1000).build()).unwrap();
+        for n in 0..299 {
+            app.state().chain.generate_and_append(&format!("Block {}", n)).await.unwrap();
+        }
+        let mut listed = request_list_blocks("limit=1000", &app).await?;
+        let page: PagedBlockList = serde_json::from_str(&listed.body_string().await?)?;
+        assert_eq!(page.items.len(), 300);
+
+        let mut streamed = request_ndjson("", true, &app).await?;
+        assert_eq!(200, streamed.status());
+        assert_eq!(streamed.content_type().unwrap().essence(), "application/x-ndjson");
+        assert_eq!(blocks_from_ndjson(&streamed.body_string().await?), page.items);
+
+        let mut ranged = request_ndjson("format=ndjson&from_index=250&limit=20", false, &app).await?;
+        assert_eq!(blocks_from_ndjson(&ranged.body_string().await?), page.items[250..270].to_vec());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn ndjson_stream_does_not_hold_the_lock() -> tide::Result<()> {
+        use async_std::io::prelude::BufReadExt;
+        use async_std::stream::StreamExt;
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        for n in 0..299 {
+            app.state().chain.generate_and_append(&format!("Block {}", n)).await.unwrap();
+        }
+        let streamed = request_ndjson("", true, &app).await?;
+        let mut lines = BufReader::new(streamed).lines();
+        let first: Block = serde_json::from_str(&lines.next().await.unwrap()?)?;
+        assert_eq!(first.index, 0);
+
+        let tip = app.state().chain.last().await.unwrap();
+        let next = Block::builder().after(&tip).data_message("mid-stream").build();
+        let posted = async_std::future::timeout(std::time::Duration::from_secs(5), request_add_block(next, &app)).await;
+        assert_eq!(201, posted.expect("POST blocked behind the stream")?.status());
+
+        let mut count = 1;
+        while let Some(line) = lines.next().await {
+            serde_json::from_str::<Block>(&line?)?;
+            count += 1;
+        }
+        assert_eq!(count, 300);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn versioned_and_legacy_paths_agree() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        arrange_second_block(&app).await;
+        for path in ["/blocks", "/blocks/1", "/blocks/last", "/peers", "/chain/validate"] {
+            let legacy_url = Url::parse(&format!("https://example.com{}", path)).unwrap();
+            let mut legacy: Response = app.respond(Request::new(Method::Get, legacy_url)).await?;
+            let versioned_url = Url::parse(&format!("https://example.com/v1{}", path)).unwrap();
+            let mut versioned: Response = app.respond(Request::new(Method::Get, versioned_url)).await?;
+            assert_eq!(200, versioned.status(), "GET /v1{}", path);
+            assert_eq!(legacy.status(), versioned.status());
+            assert_eq!(legacy.body_string().await?, versioned.body_string().await?);
+            assert_eq!(legacy.header("Deprecation").unwrap().as_str(), "true");
+            assert!(versioned.header("Deprecation").is_none());
+        }
+
+        let tip = get_block_from_server_status(&app, 1).await;
+        let third = Block::builder().after(&tip).data_message("Third block data").build();
+        let url = Url::parse("https://example.com/v1/blocks").unwrap();
+        let mut req = Request::new(Method::Post, url);
+        req.set_body(serde_json::to_string(&third)?);
+        let res: Response = app.respond(req).await?;
+        assert_eq!(201, res.status());
+        assert_eq!(3, app.state().chain.len().await);
+        Ok(())
+    }
+
     #[async_std::test]
     async fn post_new_block_results_ok() -> tide::Result<()> {
-        let app = create_app(String::from("Genesis block sample"));
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
         let first_block = get_block_from_server_status(&app, 0).await;
-        let second = Block {
-            index: 1,
-            previous_hash: first_block.hash(),
-            timestamp: first_block.timestamp + 100,
-            data: message_as_json("Second block data"),
-        };
+        let second = Block::builder()
+            .after(&first_block)
+            .data_message("Second block data")
+            .build();
         let confirmation = request_add_block(second, &app).await?;
+        assert_eq!(201, confirmation.status());
+        assert_eq!("/blocks/1", confirmation.header("Location").unwrap().as_str());
         let confirmed_block = block_from_body(confirmation).await?;
         assert_eq!(1, confirmed_block.index);
         assert_eq!(
             "Second block data",
-            confirmed_block.data.get("message").unwrap()
+            confirmed_block.message().unwrap()
         );
         Ok(())
     }
 
+    #[async_std::test]
+    async fn post_block_with_binary_attachment() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let first_block = get_block_from_server_status(&app, 0).await;
+        let payload: Vec<u8> = vec![0, 159, 146, 150, 255, 10, 13, 0];
+        let mut second = Block::builder()
+            .after(&first_block)
+            .data_message("With attachment")
+            .build();
+        second.attach_bytes("payload", &payload);
+        let confirmation = request_add_block(second, &app).await?;
+        assert_eq!(201, confirmation.status());
+        let received_block = block_from_body(request_get_block("last", &app).await?).await?;
+        assert_eq!(received_block.get_bytes("payload").unwrap(), payload);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn structured_block_survives_round_trip() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let first_block = get_block_from_server_status(&app, 0).await;
+        let mut data = HashMap::new();
+        data.insert(String::from("amount"), serde_json::json!(12.5));
+        data.insert(String::from("tags"), serde_json::json!(["a", "b"]));
+        data.insert(String::from("meta"), serde_json::json!({"nested": {"deep": true}}));
+        let second = first_block.generate_next_with_data(data.clone());
+        app.state().chain.append(second.clone()).await.unwrap();
+        let listed = block_list_from_body(request_list_blocks("from_index=1", &app).await?).await?;
+        assert_eq!(listed.items[0], second);
+        assert_eq!(listed.items[0].data, data);
+        Ok(())
+    }
+
+    async fn request_head_blocks(app: &Server<State>) -> tide::Result<Response> {
+        let url = Url::parse("https://example.com/blocks").unwrap();
+        let res: Response = app.respond(Request::new(Method::Head, url)).await?;
+        Ok(res)
+    }
+
+    #[async_std::test]
+    async fn head_blocks_reports_height_without_body() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let mut fresh = request_head_blocks(&app).await?;
+        assert_eq!(200, fresh.status());
+        assert_eq!(fresh.header(CHAIN_HEIGHT_HEADER).unwrap().as_str(), "1");
+        let genesis = get_block_from_server_status(&app, 0).await;
+        assert_eq!(fresh.header(CHAIN_TIP_HASH_HEADER).unwrap().as_str(), genesis.hash());
+        assert!(fresh.body_string().await?.is_empty());
+
+        arrange_second_block(&app).await;
+        let grown = request_head_blocks(&app).await?;
+        assert_eq!(grown.header(CHAIN_HEIGHT_HEADER).unwrap().as_str(), "2");
+        let tip = get_block_from_server_status(&app, 1).await;
+        assert_eq!(grown.header(CHAIN_TIP_HASH_HEADER).unwrap().as_str(), tip.hash());
+
+        let listed = request_list_blocks("from_index=0", &app).await?;
+        assert_eq!(listed.header(CHAIN_HEIGHT_HEADER).unwrap().as_str(), "2");
+        assert_eq!(listed.header(CHAIN_TIP_HASH_HEADER).unwrap().as_str(), tip.hash());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn post_malformed_blocks_are_bad_requests() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let first_block = get_block_from_server_status(&app, 0).await;
+        let uppercase = serde_json::json!({
+            "index": 1,
+            "previous_hash": first_block.hash().to_uppercase(),
+            "timestamp": first_block.timestamp + 1,
+            "data": {"message": "upper"}
+        });
+        let cases = vec![
+            (String::from(r#"{"index":1,"previous_hash":"","timestamp":1}"#), "Malformed request body", "missing field `data`"),
+            (String::from(r#"{"index":"one","previous_hash":"","timestamp":1,"data":{}}"#), "Malformed request body", "invalid type"),
+            (String::from(r#"{"index":1,"previous_hash":"","timestamp":1,"data":{},"admin":true}"#), "Malformed request body", "unknown field `admin`"),
+            (String::from(r#"{"index":1,"previous_hash":"#), "Malformed request body", "EOF"),
+            (String::from(r#"[{"index":1}]"#), "Malformed request body", "invalid type: map"),
+            (uppercase.to_string(), "Block is malformed", "lowercase hex"),
+        ];
+        for (content, label, expected) in cases {
+            let response = request_add_raw_block(&content, &app).await?;
+            assert_eq!(400, response.status());
+            let report = error_from_body(response).await?;
+            assert_eq!(label, report.error);
+            assert!(report.reason.contains(expected), "{}", report.reason);
+        }
+        assert_eq!(1, app.state().chain.len().await);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn post_malformed_peer_is_bad_request() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        for content in [r#"{"peer":"#, "[]", r#"{"peer":1}"#] {
+            let url = Url::parse("https://example.com/peers").unwrap();
+            let mut req = Request::new(Method::Post, url);
+            req.set_body(content);
+            let response: Response = app.respond(req).await?;
+            assert_eq!(400, response.status());
+            let report = error_from_body(response).await?;
+            assert_eq!("Malformed request body", report.error);
+            assert!(!report.reason.is_empty());
+        }
+        assert!(app.state().lock_peers().members.is_empty());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn repost_current_tip_is_ok() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        arrange_second_block(&app).await;
+        let tip = get_block_from_server_status(&app, 1).await;
+        let confirmation = request_add_block(tip.clone(), &app).await?;
+        assert_eq!(200, confirmation.status());
+        assert_eq!(block_from_body(confirmation).await?, tip);
+        assert_eq!(2, app.state().chain.len().await);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn post_block_with_special_characters() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let first_block = get_block_from_server_status(&app, 0).await;
+        let message = "quotes \" backslash \\ newline \n emoji 🦀";
+        let second = Block::builder()
+            .after(&first_block)
+            .data_message(message)
+            .build();
+        let confirmation = request_add_block(second.clone(), &app).await?;
+        assert_eq!(201, confirmation.status());
+        let confirmed_block = block_from_body(confirmation).await?;
+        assert_eq!(confirmed_block, second);
+        let listed = block_list_from_body(request_list_blocks("from_index=1", &app).await?).await?;
+        assert_eq!(message, listed.items[0].message().unwrap());
+        Ok(())
+    }
+
     #[async_std::test]
     async fn test_fails_to_append_by_hash() -> tide::Result<()> {
-        let app = create_app(String::from("Genesis block sample"));
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
         let first_block = get_block_from_server_status(&app, 0).await;
-        let second = Block {
-            index: 1,
-            previous_hash: String::from("c4f3c4f3c4f3"),
-            timestamp: first_block.timestamp + 100,
-            data: message_as_json("Second block data"),
-        };
+        let second = Block::builder()
+            .index(1)
+            .previous_hash("c4f3".repeat(16))
+            .timestamp(first_block.timestamp + 100)
+            .data_message("Second block data")
+            .build();
         let expected_reason = format!(
             "previous hash is {} but {} was provided",
             first_block.hash(),
@@ -299,7 +1037,7 @@ mod tests {
         let confirmation = request_add_block(second, &app).await?;
         let confirmation_status = confirmation.status();
         let report = error_from_body(confirmation).await?;
-        assert_eq!(400, confirmation_status);
+        assert_eq!(422, confirmation_status);
         assert_eq!(String::from("Previous hash not matching"), report.error);
         assert_eq!(String::from(expected_reason), report.reason);
         Ok(())
@@ -307,19 +1045,18 @@ mod tests {
 
     #[async_std::test]
     async fn test_fails_to_append_by_index() -> tide::Result<()> {
-        let app = create_app(String::from("Genesis block sample"));
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
         let first_block = get_block_from_server_status(&app, 0).await;
-        let second = Block {
-            index: 3,
-            previous_hash: first_block.hash(),
-            timestamp: first_block.timestamp + 100,
-            data: message_as_json("Second block data"),
-        };
+        let second = Block::builder()
+            .after(&first_block)
+            .index(3)
+            .data_message("Second block data")
+            .build();
         let expected_reason = "expected index 0 but received 3 which is not inmediate next";
         let confirmation = request_add_block(second, &app).await?;
         let confirmation_status = confirmation.status();
         let report = error_from_body(confirmation).await?;
-        assert_eq!(400, confirmation_status);
+        assert_eq!(422, confirmation_status);
         assert_eq!(
             String::from("New block index is not correlative"),
             report.error
@@ -330,14 +1067,13 @@ mod tests {
 
     #[async_std::test]
     async fn test_fails_to_append_by_timestamp() -> tide::Result<()> {
-        let app = create_app(String::from("Genesis block sample"));
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
         let first_block = get_block_from_server_status(&app, 0).await;
-        let second = Block {
-            index: 1,
-            previous_hash: first_block.hash(),
-            timestamp: first_block.timestamp - 100,
-            data: message_as_json("Second block data"),
-        };
+        let second = Block::builder()
+            .after(&first_block)
+            .timestamp(first_block.timestamp - 100)
+            .data_message("Second block data")
+            .build();
         let expected_reason = format!(
             "Given timestamp {} is not later to {}",
             second.timestamp, first_block.timestamp
@@ -345,7 +1081,7 @@ mod tests {
         let confirmation = request_add_block(second, &app).await?;
         let confirmation_status = confirmation.status();
         let report = error_from_body(confirmation).await?;
-        assert_eq!(400, confirmation_status);
+        assert_eq!(422, confirmation_status);
         assert_eq!(
             String::from("New block timestamp must be later to previous"),
             report.error
@@ -354,6 +1090,26 @@ mod tests {
         Ok(())
     }
 
+    #[async_std::test]
+    async fn test_fails_to_append_by_data_size() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let first_block = get_block_from_server_status(&app, 0).await;
+        let second = Block::builder()
+            .after(&first_block)
+            .data_message(&"x".repeat(70000))
+            .build();
+        let confirmation = request_add_block(second, &app).await?;
+        let confirmation_status = confirmation.status();
+        let report = error_from_body(confirmation).await?;
+        assert_eq!(400, confirmation_status);
+        assert_eq!(String::from("New block data is too large"), report.error);
+        assert_eq!(
+            String::from("Block data takes 70014 bytes but only 65536 are allowed"),
+            report.reason
+        );
+        Ok(())
+    }
+
     async fn get_peers_list_from_server_status(app: &Server<State>) -> PeerList {
         let peers = &app.state().peers.lock().unwrap();
         PeerList {
@@ -376,7 +1132,7 @@ mod tests {
 
     #[async_std::test]
     async fn test_add_new_peer_success() -> tide::Result<()> {
-        let app = create_app(String::from("Genesis block sample"));
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
         let new_member = MemberEntry {
             peer: String::from("ws://localhost:5055"),
         };
@@ -389,6 +1145,27 @@ mod tests {
         Ok(())
     }
 
+    #[async_std::test]
+    async fn already_present_peer_survives_the_trip_to_the_client() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let member = MemberEntry {
+            peer: String::from("ws://localhost:5055"),
+        };
+        assert_eq!(201, request_post_member(&member, &app).await?.status());
+        let repeated = request_post_member(&member, &app).await?;
+        assert_eq!(409, repeated.status());
+        let received = error_from_body(repeated).await?;
+        let older_node = APIErrorAndReason {
+            code: None,
+            params: None,
+            ..received.clone()
+        };
+        for api_error in [received, older_node] {
+            assert_eq!(EntryRejectedErr::from(api_error), EntryRejectedErr::AlreadyPresent(member.clone()));
+        }
+        Ok(())
+    }
+
     async fn request_list_peers(app: &Server<State>) -> tide::Result<Response> {
         let peers_url = "https://example.com/peers";
         let url = Url::parse(peers_url).unwrap();
@@ -404,7 +1181,7 @@ mod tests {
 
     #[async_std::test]
     async fn test_list_peers_empty() -> tide::Result<()> {
-        let app = create_app(String::from("Genesis block sample"));
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
         let confirmation = request_list_peers(&app).await?;
         let confirmation_status = confirmation.status();
         let received_list: PeerList = peer_list_from_body(confirmation).await?;
@@ -414,4 +1191,510 @@ mod tests {
         assert_eq!(get_peers_list_from_server_status(&app).await.items.len(), 0);
         Ok(())
     }
+
+    #[async_std::test]
+    async fn test_add_new_peer_returns_stored_entry() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let new_member = MemberEntry {
+            peer: String::from("http://localhost:5055"),
+        };
+        let mut confirmation = request_post_member(&new_member, &app).await?;
+        assert_eq!(201, confirmation.status());
+        let stored: MemberEntry = serde_json::from_str(&confirmation.body_string().await?)?;
+        assert_eq!(stored, new_member);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn test_add_duplicate_peer() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let new_member = MemberEntry {
+            peer: String::from("http://localhost:5055"),
+        };
+        request_post_member(&new_member, &app).await?;
+        let confirmation = request_post_member(&new_member, &app).await?;
+        assert_eq!(409, confirmation.status());
+        let report = error_from_body(confirmation).await?;
+        assert_eq!("Entry is already on list", report.error);
+        assert_eq!(get_peers_list_from_server_status(&app).await.items.len(), 1);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn test_add_invalid_peer() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let new_member = MemberEntry {
+            peer: String::from("not a url"),
+        };
+        let confirmation = request_post_member(&new_member, &app).await?;
+        assert_eq!(400, confirmation.status());
+        let report = error_from_body(confirmation).await?;
+        assert_eq!("Invalid entry URL", report.error);
+        assert_eq!(report.peer(), Some("not a url"));
+        assert_eq!(get_peers_list_from_server_status(&app).await.items.len(), 0);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn test_list_registered_peers() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        for peer in &["http://one:8080", "http://two:8080"] {
+            let member = MemberEntry {
+                peer: String::from(*peer),
+            };
+            request_post_member(&member, &app).await?;
+        }
+        let confirmation = request_list_peers(&app).await?;
+        assert_eq!(200, confirmation.status());
+        let received_list: PeerList = peer_list_from_body(confirmation).await?;
+        let peers: Vec<String> = received_list.items.into_iter().map(|entry| entry.peer).collect();
+        assert_eq!(peers, vec!["http://one:8080", "http://two:8080"]);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn test_peer_added_through_state_is_listed() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let member = MemberEntry {
+            peer: String::from("http://localhost:9090"),
+        };
+        app.state().add_peer(member.clone()).unwrap();
+        let received_list = peer_list_from_body(request_list_peers(&app).await?).await?;
+        assert_eq!(received_list.items, vec![member]);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn poisoned_peer_list_does_not_brick_the_node() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let peers = app.state().peers.clone();
+        let outcome = std::thread::spawn(move || {
+            let _guard = peers.lock().unwrap();
+            panic!("poisoning the peer list");
+        })
+        .join();
+        assert!(outcome.is_err());
+        assert!(app.state().peers.is_poisoned());
+
+        assert_eq!(200, request_list_blocks("", &app).await?.status());
+        assert_eq!(200, request_list_peers(&app).await?.status());
+        let member = MemberEntry {
+            peer: String::from("http://localhost:9090"),
+        };
+        assert_eq!(201, request_post_member(&member, &app).await?.status());
+        assert_eq!(request_status(&app).await?.peers, 1);
+        Ok(())
+    }
+
+    async fn request_status(app: &Server<State>) -> tide::Result<NodeStatus> {
+        let url = Url::parse("https://example.com/status").unwrap();
+        let mut res: Response = app.respond(Request::new(Method::Get, url)).await?;
+        assert_eq!(200, res.status());
+        Ok(serde_json::from_str(&res.body_string().await?)?)
+    }
+
+    #[async_std::test]
+    async fn status_of_fresh_node() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let genesis = get_block_from_server_status(&app, 0).await;
+        let status = request_status(&app).await?;
+        assert_eq!(status.height, 1);
+        assert_eq!(status.last_index, 0);
+        assert_eq!(status.last_hash, genesis.hash());
+        assert_eq!(status.genesis_hash, genesis.hash());
+        assert_eq!(status.peers, 0);
+        assert_eq!(status.started_at, app.state().started_at);
+        assert_eq!(status.version, env!("CARGO_PKG_VERSION"));
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn status_after_block_and_peer() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        arrange_second_block(&app).await;
+        let member = MemberEntry {
+            peer: String::from("http://localhost:9090"),
+        };
+        request_post_member(&member, &app).await?;
+        let genesis = get_block_from_server_status(&app, 0).await;
+        let tip = get_block_from_server_status(&app, 1).await;
+        let status = request_status(&app).await?;
+        assert_eq!(status.height, 2);
+        assert_eq!(status.last_index, 1);
+        assert_eq!(status.last_hash, tip.hash());
+        assert_eq!(status.genesis_hash, genesis.hash());
+        assert_eq!(status.peers, 1);
+        Ok(())
+    }
+
+    async fn request_validation(body: Option<&BlockList>, app: &Server<State>) -> tide::Result<Response> {
+        let url = Url::parse("https://example.com/chain/validate").unwrap();
+        let req = match body {
+            Some(list) => {
+                let mut req = Request::new(Method::Post, url);
+                req.set_body(serde_json::to_string(list).unwrap());
+                req
+            }
+            None => Request::new(Method::Get, url),
+        };
+        let res: Response = app.respond(req).await?;
+        Ok(res)
+    }
+
+    #[async_std::test]
+    async fn validate_healthy_chain() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        arrange_second_block(&app).await;
+        let mut response = request_validation(None, &app).await?;
+        assert_eq!(200, response.status());
+        let report: ValidationReport = serde_json::from_str(&response.body_string().await?)?;
+        assert_eq!(report, ValidationReport { valid: true, height: 2 });
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn validate_posted_list_breaking_at_second_block() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let tip = get_block_from_server_status(&app, 0).await;
+        let first = Block::builder().after(&tip).data_message("fine").build();
+        let second = Block::builder()
+            .index(2)
+            .previous_hash("ab".repeat(32))
+            .timestamp(first.timestamp + 1)
+            .data_message("broken")
+            .build();
+        let list = BlockList {
+            items: vec![first.clone()],
+        };
+        let mut response = request_validation(Some(&list), &app).await?;
+        assert_eq!(200, response.status());
+        let report: ValidationReport = serde_json::from_str(&response.body_string().await?)?;
+        assert_eq!(report, ValidationReport { valid: true, height: 2 });
+
+        let list = BlockList {
+            items: vec![first, second],
+        };
+        let response = request_validation(Some(&list), &app).await?;
+        assert_eq!(409, response.status());
+        let report = error_from_body(response).await?;
+        assert_eq!("Previous hash not matching", report.error);
+        assert!(report.reason.starts_with("block 2: "));
+        assert_eq!(report.block_index(), Some(2));
+        assert_eq!(1, app.state().chain.len().await);
+        Ok(())
+    }
+
+    async fn request_add_batch(list: &BlockList, app: &Server<State>) -> tide::Result<Response> {
+        let url = Url::parse("https://example.com/blocks/batch").unwrap();
+        let mut req = Request::new(Method::Post, url);
+        req.set_body(serde_json::to_string(list).unwrap());
+        let res: Response = app.respond(req).await?;
+        Ok(res)
+    }
+
+    async fn arrange_batch(app: &Server<State>, count: usize) -> Vec<Block> {
+        let mut parent = app.state().chain.last().await.unwrap();
+        let mut items = vec![];
+        for n in 0..count {
+            let next = Block::builder().after(&parent).data_message(&format!("Batch {}", n)).build();
+            items.push(next.clone());
+            parent = next;
+        }
+        items
+    }
+
+    #[async_std::test]
+    async fn post_valid_batch() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let list = BlockList {
+            items: arrange_batch(&app, 5).await,
+        };
+        let mut response = request_add_batch(&list, &app).await?;
+        assert_eq!(201, response.status());
+        let report: BatchReport = serde_json::from_str(&response.body_string().await?)?;
+        assert_eq!(report.appended, 5);
+        assert_eq!(6, app.state().chain.len().await);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn post_batch_failing_at_third_element() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let mut items = arrange_batch(&app, 5).await;
+        items[3].data = message_as_json("tampered");
+        let response = request_add_batch(&BlockList { items: items }, &app).await?;
+        assert_eq!(400, response.status());
+        let report = error_from_body(response).await?;
+        assert_eq!("Block hash does not match its content", report.error);
+        assert!(report.reason.starts_with("batch offset 3: "));
+        assert_eq!(report.block_index(), Some(4));
+        assert_eq!(1, app.state().chain.len().await);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn batch_rejection_names_the_second_block() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let mut items = arrange_batch(&app, 3).await;
+        items[1].previous_hash = "0".repeat(64);
+        items[1].hash = Some(items[1].hash());
+        let response = request_add_batch(&BlockList { items: items }, &app).await?;
+        assert_eq!(422, response.status());
+        let report = error_from_body(response).await?;
+        assert_eq!(report.block_index(), Some(2));
+        assert_eq!(report.peer(), None);
+        assert_eq!(report.context.as_ref().unwrap().batch_offset, Some(1));
+        let (offset, _) = report.batch_offset().unwrap();
+        assert_eq!(offset, 1);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn post_empty_batch() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let mut response = request_add_batch(&BlockList { items: vec![] }, &app).await?;
+        assert_eq!(200, response.status());
+        let report: BatchReport = serde_json::from_str(&response.body_string().await?)?;
+        assert_eq!(report.appended, 0);
+        Ok(())
+    }
+
+    async fn request_delete_member(
+        entry: &MemberEntry,
+        app: &Server<State>,
+    ) -> tide::Result<Response> {
+        let url = Url::parse("https://example.com/peers").unwrap();
+        let mut req = Request::new(Method::Delete, url);
+        req.set_body(serde_json::to_string(entry).unwrap());
+        let res: Response = app.respond(req).await?;
+        Ok(res)
+    }
+
+    #[async_std::test]
+    async fn test_delete_peer() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let member = MemberEntry {
+            peer: String::from("http://localhost:9090"),
+        };
+        request_post_member(&member, &app).await?;
+        let mut confirmation = request_delete_member(&member, &app).await?;
+        assert_eq!(200, confirmation.status());
+        let removed: MemberEntry = serde_json::from_str(&confirmation.body_string().await?)?;
+        assert_eq!(removed, member);
+        let received_list = peer_list_from_body(request_list_peers(&app).await?).await?;
+        assert_eq!(received_list.items.len(), 0);
+
+        let confirmation = request_delete_member(&member, &app).await?;
+        assert_eq!(404, confirmation.status());
+        let report = error_from_body(confirmation).await?;
+        assert_eq!("Peer not found", report.error);
+        assert_eq!("Entry is not a member: http://localhost:9090", report.reason);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn test_delete_peer_by_query() -> tide::Result<()> {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let member = MemberEntry {
+            peer: String::from("ws://localhost:5055"),
+        };
+        request_post_member(&member, &app).await?;
+        let url = Url::parse("https://example.com/peers?peer=ws%3A%2F%2Flocalhost%3A5055").unwrap();
+        let confirmation: Response = app.respond(Request::new(Method::Delete, url)).await?;
+        assert_eq!(200, confirmation.status());
+        assert_eq!(get_peers_list_from_server_status(&app).await.items.len(), 0);
+        Ok(())
+    }
+
+    async fn next_streamed_block<R>(lines: &mut R) -> Block
+    where
+        R: async_std::stream::Stream<Item = std::io::Result<String>> + Unpin,
+    {
+        use async_std::stream::StreamExt;
+        let wait = async {
+            while let Some(line) = lines.next().await {
+                if let Some(data) = line.unwrap().strip_prefix("data:") {
+                    return serde_json::from_str(data.trim()).unwrap();
+                }
+            }
+            panic!("stream closed before a block arrived");
+        };
+        async_std::future::timeout(std::time::Duration::from_secs(5), wait).await.unwrap()
+    }
+
+    #[async_std::test]
+    async fn stream_pushes_posted_blocks() -> tide::Result<()> {
+        use async_std::io::prelude::BufReadExt;
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let url = Url::parse("https://example.com/blocks/stream?from_index=0").unwrap();
+        let mut stream: Response = app.respond(Request::new(Method::Get, url)).await?;
+        assert_eq!(200, stream.status());
+        let mut lines = stream.take_body().lines();
+        let replayed = next_streamed_block(&mut lines).await;
+        assert_eq!(replayed.index, 0);
+
+        let first_block = get_block_from_server_status(&app, 0).await;
+        let second = Block::builder()
+            .after(&first_block)
+            .data_message("Streamed block")
+            .build();
+        let confirmation = request_add_block(second.clone(), &app).await?;
+        assert_eq!(201, confirmation.status());
+        let pushed = next_streamed_block(&mut lines).await;
+        assert_eq!(pushed.index, 1);
+        assert_eq!(pushed, second);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn last_block_of_an_empty_chain_is_an_error() -> tide::Result<()> {
+        let mut app = tide::with_state(State::with_chain(Chain::empty()));
+        v1_routes(&mut app);
+        let response = request_get_block("last", &app).await?;
+        assert_eq!(500, response.status());
+        let report = error_from_body(response).await?;
+        assert_eq!(InvalidBlockErr::from(report), InvalidBlockErr::GenesisBlockNotFound);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_configured_genesis_is_reported() {
+        let mut genesis = Block::genesis(message_as_json("Shared genesis"), 1000);
+        genesis.index = 3;
+        let config = AppConfig::builder().genesis_block(genesis).build();
+        assert_eq!(create_app(config).err(), Some(InvalidBlockErr::GenesisBlockNotFound));
+    }
+
+    fn arrange_app_on(chain: Chain) -> Server<State> {
+        let mut app = tide::with_state(State::with_chain(chain));
+        v1_routes(&mut app);
+        app
+    }
+
+    async fn request_to(method: Method, path: &str, body: &str, app: &Server<State>) -> tide::Result<Response> {
+        let url = Url::parse(&format!("https://example.com{}", path)).unwrap();
+        let mut req = Request::new(method, url);
+        req.set_body(body);
+        let res: Response = app.respond(req).await?;
+        Ok(res)
+    }
+
+    async fn rejection_from(response: Response) -> APIErrorAndReason {
+        let status = response.status();
+        assert!(status.is_client_error() || status.is_server_error(), "status {}", status);
+        error_from_body(response).await.unwrap()
+    }
+
+    // The client matches on the label constants, so every rejection is taken
+    // from an actual response rather than from the conversion that writes it.
+    #[async_std::test]
+    async fn server_labels_are_the_ones_the_client_parses() -> tide::Result<()> {
+        use crate::api::config::AppConfigBuilder;
+        use crate::api::errors::*;
+        use crate::blockchain::block::Entry;
+        use crate::blockchain::TimestampPolicy;
+        use std::mem::discriminant;
+
+        let app = create_app(
+            AppConfig::builder()
+                .genesis_data("Genesis block sample")
+                .max_data_bytes(64)
+                .build(),
+        ).unwrap();
+        let genesis = app.state().chain.last().await.unwrap();
+        let next = || Block::builder().after(&genesis).data_message("Next block");
+        let duplicated = vec![
+            Entry { id: String::from("tx-1"), payload: serde_json::json!(1) },
+            Entry { id: String::from("tx-1"), payload: serde_json::json!(2) },
+        ];
+        let mut forged = next().build();
+        forged.hash = Some("0".repeat(64));
+        let rejected_by_default = vec![
+            (next().previous_hash("0".repeat(64)).build(), InvalidBlockErr::HashNotMatching(String::new(), String::new()), HASH_NOT_MATCHING_LABEL),
+            (next().index(5).build(), InvalidBlockErr::NotCorrelated(0, 0), INDEX_NOT_CORRELATIVE_LABEL),
+            (next().timestamp(0).build(), InvalidBlockErr::NotPosterior(0, 0), TIMESTAMP_NOT_LATER_LABEL),
+            (next().data_map(HashMap::new()).build(), InvalidBlockErr::EmptyData, EMPTY_DATA_LABEL),
+            (next().data_message(&"x".repeat(64)).build(), InvalidBlockErr::DataTooLarge(0, 0), DATA_TOO_LARGE_LABEL),
+            (forged, InvalidBlockErr::SelfHashMismatch(String::new(), String::new()), SELF_HASH_MISMATCH_LABEL),
+            (next().data_entries(&duplicated).build(), InvalidBlockErr::InvalidEntries(String::new()), INVALID_ENTRIES_LABEL),
+            (next().version(9).build(), InvalidBlockErr::UnsupportedVersion(0), UNSUPPORTED_VERSION_LABEL),
+        ];
+        let mut apps: Vec<(Server<State>, Vec<(Block, InvalidBlockErr, &str)>)> = vec![(app, rejected_by_default)];
+
+        let mined = create_app(AppConfig::builder().genesis_data("Genesis block sample").difficulty(2).build()).unwrap();
+        let mut unmined = Block::builder().after(&mined.state().chain.last().await.unwrap()).data_message("unmined").build();
+        while unmined.meets_difficulty(2) {
+            unmined.nonce += 1;
+        }
+        apps.push((mined, vec![(unmined, InvalidBlockErr::InsufficientWork(0), INSUFFICIENT_WORK_LABEL)]));
+
+        let policy = TimestampPolicy { max_future_skew_ms: Some(1000), ..TimestampPolicy::default() };
+        let bounded = arrange_app_on(Chain::with_policy(String::from("Genesis block sample"), policy));
+        let last = bounded.state().chain.last().await.unwrap();
+        let future = Block::builder().after(&last).timestamp(last.timestamp + 3_600_000).data_message("future").build();
+        apps.push((bounded, vec![(future, InvalidBlockErr::TooFarInFuture(0, 0), TOO_FAR_IN_FUTURE_LABEL)]));
+
+        let mut signed_only = Chain::new(String::from("Genesis block sample"));
+        signed_only.set_require_signatures(true);
+        let unsigned = Block::builder().after(signed_only.get_last_block().unwrap()).data_message("unsigned").build();
+        apps.push((arrange_app_on(signed_only), vec![(unsigned, InvalidBlockErr::BadSignature, BAD_SIGNATURE_LABEL)]));
+
+        for (app, rejections) in apps {
+            for (block, expected, label) in rejections {
+                let report = rejection_from(request_add_block(block, &app).await?).await;
+                assert_eq!(report.error, label);
+                assert_eq!(discriminant(&InvalidBlockErr::from(report)), discriminant(&expected), "{}", label);
+            }
+        }
+
+        let empty_chain = arrange_app_on(Chain::empty());
+        let report = rejection_from(request_to(Method::Get, "/blocks/last", "", &empty_chain).await?).await;
+        assert_eq!(report.error, UNKNOWN_ERROR_LABEL);
+        assert_eq!(InvalidBlockErr::from(report), InvalidBlockErr::GenesisBlockNotFound);
+
+        let configured = |builder: AppConfigBuilder| create_app(builder.genesis_data("Genesis block sample").build()).unwrap();
+        let app = configured(AppConfig::builder());
+        let limited = configured(AppConfig::builder().max_body_bytes(256));
+        let guarded = configured(AppConfig::builder().token("s3cr3t"));
+        let throttled = configured(AppConfig::builder().rate_limit(0.001, 1));
+        request_to(Method::Get, "/blocks", "", &throttled).await?;
+        let requests = vec![
+            (&app, Method::Get, "/blocks/99", String::new(), BLOCK_NOT_FOUND_LABEL),
+            (&app, Method::Get, "/blocks/nine", String::new(), INVALID_QUERY_LABEL),
+            (&app, Method::Post, "/blocks", String::from("not json"), MALFORMED_BODY_LABEL),
+            (&limited, Method::Post, "/blocks", "x".repeat(512), BODY_TOO_LARGE_LABEL),
+            (&guarded, Method::Post, "/blocks", String::from("{}"), UNAUTHORIZED_LABEL),
+            (&throttled, Method::Get, "/blocks", String::new(), RATE_LIMITED_LABEL),
+        ];
+        for (app, method, path, body, label) in requests {
+            let report = rejection_from(request_to(method, path, &body, app).await?).await;
+            assert_eq!(report.error, label, "{}", path);
+        }
+        let mut malformed = Block::builder().after(&app.state().chain.last().await.unwrap()).data_message("Next block").build();
+        malformed.previous_hash = String::from("XYZ");
+        let report = rejection_from(request_add_block(malformed, &app).await?).await;
+        assert_eq!(report.error, MALFORMED_BLOCK_LABEL);
+
+        let member = MemberEntry { peer: String::from("http://localhost:9090") };
+        request_post_member(&member, &app).await?;
+        let rejected_peers = vec![
+            (request_post_member(&member, &app).await?, EntryRejectedErr::AlreadyPresent(member.clone()), ENTRY_ALREADY_PRESENT_LABEL),
+            (
+                request_post_member(&MemberEntry { peer: String::from("nope") }, &app).await?,
+                EntryRejectedErr::InvalidURL(String::new()),
+                ENTRY_URL_INVALID_LABEL,
+            ),
+            (
+                request_delete_member(&MemberEntry { peer: String::from("http://localhost:9191") }, &app).await?,
+                EntryRejectedErr::NotFound(member.clone()),
+                ENTRY_NOT_FOUND_LABEL,
+            ),
+        ];
+        for (response, expected, label) in rejected_peers {
+            let report = rejection_from(response).await;
+            assert_eq!(report.error, label);
+            assert_eq!(discriminant(&EntryRejectedErr::from(report)), discriminant(&expected), "{}", label);
+        }
+        Ok(())
+    }
 }
@@ -1,10 +1,15 @@
-use crate::blockchain::block::Block;
+use crate::api::broadcast::BroadcastStats;
+use crate::api::config::DEFAULT_MAX_BODY_BYTES;
+use crate::api::metrics::Metrics;
+use crate::blockchain::block::{get_epoch_ms, Block};
+use crate::blockchain::codec::{decode, encode, CodecErr};
+use crate::blockchain::shared::SharedChain;
 use crate::blockchain::{Chain, InvalidBlockErr};
 use crate::peers::{Peers, MemberEntry, EntryRejectedErr};
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard};
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct List<T> {
     pub items: Vec<T>,
 }
@@ -12,41 +17,236 @@ pub struct List<T> {
 pub type BlockList = List<Block>;
 pub type PeerList = List<MemberEntry>;
 
+impl BlockList {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, CodecErr> {
+        encode(self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CodecErr> {
+        decode(bytes)
+    }
+
+    pub fn sorted(mut self) -> Self {
+        self.items.sort();
+        self
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct PagedBlockList {
+    pub items: Vec<Block>,
+    #[serde(default)]
+    pub total: u64,
+    #[serde(default)]
+    pub from_index: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_index: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct NodeStatus {
+    pub height: u64,
+    pub last_index: u64,
+    pub last_hash: String,
+    // Everything below was added over time, older nodes leave it out.
+    #[serde(default)]
+    pub genesis_hash: String,
+    pub peers: usize,
+    #[serde(default)]
+    pub started_at: u128,
+    #[serde(default)]
+    pub uptime_ms: u128,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub broadcast_attempts: u64,
+    #[serde(default)]
+    pub broadcast_failures: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub height: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct BatchReport {
+    pub appended: usize,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct SyncPending {
+    pub reason: String,
+    pub from_index: u64,
+    pub pending_index: u64,
+}
+
+pub const DEFAULT_MAX_PAGE_SIZE: usize = 100;
+pub const CHAIN_HEIGHT_HEADER: &str = "X-Chain-Height";
+pub const CHAIN_TIP_HASH_HEADER: &str = "X-Chain-Tip-Hash";
+
 #[derive(Deserialize)]
 #[serde(default)]
 pub struct Limits {
     pub from_index: usize,
+    pub limit: Option<usize>,
 }
 impl Default for Limits {
     fn default() -> Self {
-        Self { from_index: 0 }
+        Self {
+            from_index: 0,
+            limit: None,
+        }
     }
 }
 impl Limits {
     pub fn as_query(&self) -> String {
-        format!("from_index={}", &self.from_index)
+        match self.limit {
+            Some(limit) => format!("from_index={}&limit={}", &self.from_index, limit),
+            None => format!("from_index={}", &self.from_index),
+        }
     }
 }
 
 #[derive(Clone)]
 pub struct State {
-    pub chain: Arc<Mutex<Chain>>,
+    pub chain: SharedChain,
     pub peers: Arc<Mutex<Peers>>,
+    pub max_page_size: usize,
+    pub max_body_bytes: usize,
+    pub started_at: u128,
+    pub node_id: Option<String>,
+    pub tokens: Arc<Vec<String>>,
+    pub broadcast: Arc<BroadcastStats>,
+    pub metrics: Arc<Metrics>,
 }
 
 impl State {
     pub fn new(genesis_data: String) -> Self {
+        Self::with_chain(Chain::new(genesis_data))
+    }
+    pub fn with_genesis(genesis: Block) -> Result<Self, InvalidBlockErr> {
+        Ok(Self::with_chain(Chain::with_genesis(genesis)?))
+    }
+    pub fn with_chain(chain: Chain) -> Self {
         Self {
-            chain: Arc::new(Mutex::new(Chain::new(genesis_data))),
+            chain: SharedChain::new(chain),
             peers: Arc::new(Mutex::new(Peers::new())),
+            max_page_size: DEFAULT_MAX_PAGE_SIZE,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            started_at: get_epoch_ms(),
+            node_id: None,
+            tokens: Arc::new(vec![]),
+            broadcast: Arc::new(BroadcastStats::default()),
+            metrics: Arc::new(Metrics::default()),
+        }
+    }
+    // Every peer list mutation is a single push or remove, so a poisoned
+    // guard still holds a consistent list.
+    pub fn lock_peers(&self) -> MutexGuard<'_, Peers> {
+        self.peers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    pub async fn page(&self, limits: &Limits) -> PagedBlockList {
+        let from_index = limits.from_index as u64;
+        let page_size = limits.limit.unwrap_or(self.max_page_size).min(self.max_page_size);
+        let items = self.chain.range(from_index, from_index.saturating_add(page_size as u64)).await;
+        let total = self.chain.info().await.height;
+        let next_index = match items.last() {
+            Some(last) if last.index + 1 < total => Some(last.index + 1),
+            _ => None,
+        };
+        PagedBlockList {
+            items: items,
+            total: total,
+            from_index: from_index,
+            next_index: next_index,
         }
     }
-    pub fn append_block(&self, block: Block) -> Result<Block, InvalidBlockErr> {
-        let mut chain = self.chain.lock().unwrap();
-        chain.append(block)
+    pub async fn status(&self) -> NodeStatus {
+        let info = self.chain.info().await;
+        let peers = self.lock_peers().len();
+        NodeStatus {
+            height: info.height,
+            last_index: info.last_index,
+            last_hash: info.last_hash,
+            genesis_hash: info.genesis_hash,
+            peers: peers,
+            started_at: self.started_at,
+            uptime_ms: get_epoch_ms().saturating_sub(self.started_at),
+            version: String::from(env!("CARGO_PKG_VERSION")),
+            broadcast_attempts: self.broadcast.attempts(),
+            broadcast_failures: self.broadcast.failures(),
+        }
+    }
+    pub async fn append_block(&self, block: Block) -> Result<Block, InvalidBlockErr> {
+        let added = self.chain.append(block).await;
+        match &added {
+            Ok(_) => self.metrics.record_appended(1),
+            Err(error) => self.metrics.record_rejection(error),
+        }
+        added
+    }
+    pub async fn append_batch(&self, blocks: Vec<Block>) -> Result<usize, (usize, InvalidBlockErr)> {
+        let added = self.chain.append_batch(blocks).await;
+        match &added {
+            Ok(appended) => self.metrics.record_appended(*appended),
+            Err((_, error)) => self.metrics.record_rejection(error),
+        }
+        added
     }
     pub fn add_peer(&self, entry: MemberEntry) -> Result<MemberEntry, EntryRejectedErr> {
-        let mut peers = self.peers.lock().unwrap();
-        peers.append(entry)
+        self.lock_peers().append(entry)
+    }
+    pub fn remove_peer(&self, entry: &MemberEntry) -> Result<MemberEntry, EntryRejectedErr> {
+        self.lock_peers().remove(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_list_binary_round_trip() {
+        let genesis = Block::genesis(crate::blockchain::block::message_as_json("genesis"), 0);
+        let next = Block::builder().after(&genesis).data_message("ünïcødé").build();
+        let list = BlockList {
+            items: vec![genesis, next],
+        };
+        let decoded = BlockList::from_bytes(&list.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.items, list.items);
+        assert_eq!(BlockList::from_bytes(&[2]).err(), Some(CodecErr::UnsupportedVersion(2)));
+    }
+
+    #[test]
+    fn limits_as_query() {
+        let limits = Limits {
+            from_index: 6,
+            limit: Some(3),
+        };
+        assert_eq!(limits.as_query(), "from_index=6&limit=3");
+        assert_eq!(Limits::default().as_query(), "from_index=0");
+    }
+
+    #[test]
+    fn test_sorted_batch_appends() {
+        let mut chain = Chain::new(String::from("genesis"));
+        let mut items = vec![];
+        let mut parent = chain.get_last_block().unwrap().clone();
+        for n in 0..5 {
+            let next = Block::builder().after(&parent).data_message(&format!("block {}", n)).build();
+            items.push(next.clone());
+            parent = next;
+        }
+        items.reverse();
+        items.swap(1, 3);
+        let batch = BlockList { items: items }.sorted();
+        for block in batch.items {
+            chain.append(block).unwrap();
+        }
+        assert_eq!(chain.len(), 6);
     }
 }
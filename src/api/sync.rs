@@ -0,0 +1,128 @@
+use crate::api::broadcast::spawn_broadcast;
+use crate::api::client::APIClient;
+use crate::api::structs::{Limits, State};
+use crate::blockchain::block::Block;
+use crate::peers::MemberEntry;
+use async_std::task;
+
+// Queues a catch-up for a block that is ahead of our tip. Returns false when
+// there is nobody to fetch the gap from.
+pub fn spawn_catch_up(state: &State, pending: Block, origin: Option<String>) -> bool {
+    let sources: Vec<MemberEntry> = state.lock_peers().members.clone();
+    if sources.is_empty() {
+        return false;
+    }
+    let state = state.clone();
+    task::spawn(async move {
+        for source in sources {
+            if catch_up_from(&state, &source, pending.index).await {
+                break;
+            }
+        }
+        match state.append_block(pending.clone()).await {
+            Ok(accepted) => spawn_broadcast(&state, accepted, origin),
+            Err(error) => tide::log::warn!("block {} still rejected after sync: {:?}", pending.index, error),
+        }
+    });
+    true
+}
+
+async fn catch_up_from(state: &State, source: &MemberEntry, until_index: u64) -> bool {
+    let client = APIClient::new(source.peer.clone());
+    let mut missing: Vec<Block> = vec![];
+    let mut next_index = Some(state.chain.info().await.height);
+    while let Some(from_index) = next_index.filter(|from| *from < until_index) {
+        let limits = Limits {
+            from_index: from_index as usize,
+            limit: None,
+        };
+        let page = match client.get_page(&limits).await {
+            Ok(page) => page,
+            Err(error) => {
+                tide::log::warn!("sync from {} failed: {}", source.peer, error);
+                return false;
+            }
+        };
+        next_index = page.next_index.filter(|next| *next > from_index);
+        missing.extend(page.items.into_iter().filter(|block| block.index < until_index));
+    }
+    // Someone else may have filled part of the gap while we were fetching.
+    let height = state.chain.info().await.height;
+    missing.retain(|block| block.index >= height);
+    match state.append_batch(missing).await {
+        Ok(_) => true,
+        Err((offset, error)) => {
+            tide::log::warn!("sync from {} rejected at offset {}: {:?}", source.peer, offset, error);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::server::create_app_with_genesis;
+    use crate::api::structs::{PagedBlockList, SyncPending};
+    use crate::blockchain::block::message_as_json;
+    use std::time::Duration;
+    use tide::http::{Method, Request, Response, Url};
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn arrange_blocks(genesis: &Block, count: usize) -> Vec<Block> {
+        let mut blocks = vec![genesis.clone()];
+        for n in 0..count {
+            let next = Block::builder()
+                .after(blocks.last().unwrap())
+                .data_message(&format!("block {}", n))
+                .build();
+            blocks.push(next);
+        }
+        blocks
+    }
+
+    async fn arrange_peer_serving(items: Vec<Block>) -> MockServer {
+        let mock_server = MockServer::start().await;
+        let page = PagedBlockList {
+            total: items.len() as u64 + 1,
+            from_index: 1,
+            items: items,
+            next_index: None,
+        };
+        Mock::given(method("GET"))
+            .and(path("/v1/blocks"))
+            .and(query_param("from_index", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(page))
+            .mount(&mock_server)
+            .await;
+        mock_server
+    }
+
+    #[async_std::test]
+    async fn block_ahead_of_tip_triggers_catch_up() -> tide::Result<()> {
+        let genesis = Block::genesis(message_as_json("Shared genesis"), 1000);
+        let blocks = arrange_blocks(&genesis, 3);
+        let peer = arrange_peer_serving(blocks[1..3].to_vec()).await;
+        let app = create_app_with_genesis(String::from(""), Some(genesis)).unwrap();
+        app.state().add_peer(MemberEntry { peer: peer.uri() }).unwrap();
+
+        let url = Url::parse("https://example.com/blocks").unwrap();
+        let mut req = Request::new(Method::Post, url);
+        req.set_body(serde_json::to_string(&blocks[3])?);
+        let mut res: Response = app.respond(req).await?;
+        assert_eq!(202, res.status());
+        let pending: SyncPending = serde_json::from_str(&res.body_string().await?)?;
+        assert_eq!(pending.from_index, 1);
+        assert_eq!(pending.pending_index, 3);
+
+        let chain = app.state().chain.clone();
+        for _ in 0..100 {
+            if chain.len().await == 4 {
+                break;
+            }
+            task::sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(chain.snapshot().await.iter().cloned().collect::<Vec<Block>>(), blocks);
+        Ok(())
+    }
+}
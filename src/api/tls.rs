@@ -0,0 +1,55 @@
+use http_client::isahc::IsahcClient;
+use isahc::config::{CaCertificate, Configurable, SslOption};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static PEM_FILES: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Default)]
+pub(crate) struct TlsOptions {
+    pub accept_invalid_certs: bool,
+    pub root_certificate: Option<PemFile>,
+}
+
+impl TlsOptions {
+    // Swaps surf's default backend for an isahc client carrying the options;
+    // without any it behaves as the default one.
+    pub fn client(&self) -> Result<surf::Client, surf::Error> {
+        let mut builder = isahc::HttpClient::builder();
+        if self.accept_invalid_certs {
+            builder = builder.ssl_options(SslOption::DANGER_ACCEPT_INVALID_CERTS | SslOption::DANGER_ACCEPT_INVALID_HOSTS);
+        }
+        if let Some(pem_file) = &self.root_certificate {
+            builder = builder.ssl_ca_certificate(CaCertificate::file(&pem_file.path));
+        }
+        let http = builder.build()?;
+        Ok(surf::Client::with_http_client(IsahcClient::from_client(http)))
+    }
+}
+
+// curl only loads CA bundles from disk, so PEM bytes handed to the client are
+// written out and the file is removed once the client lets go of it.
+pub(crate) struct PemFile {
+    path: PathBuf,
+}
+
+impl PemFile {
+    pub fn write(pem: &[u8]) -> io::Result<Self> {
+        let name = format!(
+            "rustychain-ca-{}-{}.pem",
+            std::process::id(),
+            PEM_FILES.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, pem)?;
+        Ok(PemFile { path: path })
+    }
+}
+
+impl Drop for PemFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,185 @@
+use crate::api::structs::{BlockList, State};
+use crate::blockchain::block::Block;
+use async_std::task;
+use async_tungstenite::tungstenite::handshake::derive_accept_key;
+use async_tungstenite::tungstenite::protocol::Role;
+use async_tungstenite::tungstenite::Message;
+use async_tungstenite::WebSocketStream;
+use futures::stream::{self, StreamExt};
+use futures::SinkExt;
+use serde::{Deserialize, Serialize};
+use tide::http::upgrade::Connection;
+use tide::{Request, Response, StatusCode};
+
+// Adjacently tagged: internal tagging buffers the payload, which can't hold
+// the u128 block timestamps.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Hello { height: u64 },
+    Block { block: Block },
+    Blocks(BlockList),
+    Error { reason: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    GetBlocks { from_index: u64 },
+}
+
+enum Event {
+    Received(Message),
+    Appended(Block),
+    Closed,
+}
+
+pub async fn upgrade(req: Request<State>) -> tide::Result<Response> {
+    let wants_websocket = req.header("Upgrade")
+        .map_or(false, |upgrade| upgrade.as_str().eq_ignore_ascii_case("websocket"));
+    let key = match (wants_websocket, req.header("Sec-WebSocket-Key")) {
+        (true, Some(key)) => key.as_str().to_string(),
+        _ => return Ok(Response::new(StatusCode::UpgradeRequired)),
+    };
+    let mut res = tide::http::Response::new(StatusCode::SwitchingProtocols);
+    res.insert_header("Upgrade", "websocket");
+    res.insert_header("Connection", "Upgrade");
+    res.insert_header("Sec-WebSocket-Accept", derive_accept_key(key.as_bytes()));
+    let upgrade_receiver = res.recv_upgrade().await;
+    let state = req.state().clone();
+    task::spawn(async move {
+        if let Some(connection) = upgrade_receiver.await {
+            let socket = WebSocketStream::from_raw_socket(connection, Role::Server, None).await;
+            serve(state, socket).await;
+        }
+    });
+    Ok(res.into())
+}
+
+async fn reply(state: &State, message: Message) -> Option<ServerMessage> {
+    let text = match message {
+        Message::Text(text) => text,
+        _ => return None,
+    };
+    let reply = match serde_json::from_str(&text) {
+        Ok(ClientMessage::GetBlocks { from_index }) => ServerMessage::Blocks(BlockList {
+            items: state.chain.range(from_index, u64::MAX).await,
+        }),
+        Err(error) => ServerMessage::Error {
+            reason: error.to_string(),
+        },
+    };
+    Some(reply)
+}
+
+async fn serve(state: State, socket: WebSocketStream<Connection>) {
+    let appended = state.chain.subscribe().await;
+    let (mut outgoing, incoming) = socket.split();
+    let hello = ServerMessage::Hello {
+        height: state.chain.info().await.height,
+    };
+    if send(&mut outgoing, &hello).await.is_err() {
+        return;
+    }
+    let incoming = incoming
+        .take_while(|message| futures::future::ready(message.is_ok()))
+        .map(|message| Event::Received(message.unwrap()))
+        .chain(stream::once(futures::future::ready(Event::Closed)));
+    let mut events = stream::select(incoming, appended.map(Event::Appended));
+    while let Some(event) = events.next().await {
+        let message = match event {
+            Event::Received(Message::Close(_)) | Event::Closed => break,
+            Event::Received(message) => match reply(&state, message).await {
+                Some(reply) => reply,
+                None => continue,
+            },
+            Event::Appended(block) => ServerMessage::Block { block: block },
+        };
+        if send(&mut outgoing, &message).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn send<S>(outgoing: &mut S, message: &ServerMessage) -> Result<(), S::Error>
+where
+    S: futures::Sink<Message> + Unpin,
+{
+    outgoing.send(Message::Text(serde_json::to_string(message).unwrap())).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::server::create_app_with_genesis;
+    use async_tungstenite::async_std::connect_async;
+    use async_tungstenite::tungstenite::Error;
+    use tide::listener::Listener;
+
+    async fn arrange_running_app() -> (String, State) {
+        let app = create_app_with_genesis(String::from("Genesis block sample"), None).unwrap();
+        let state = app.state().clone();
+        let mut listener = app.bind("127.0.0.1:0").await.unwrap();
+        let address = listener.info()[0].connection().replace("http://", "");
+        task::spawn(async move { listener.accept().await });
+        (address, state)
+    }
+
+    async fn next_message<S>(client: &mut S) -> ServerMessage
+    where
+        S: futures::Stream<Item = Result<Message, Error>> + Unpin,
+    {
+        let wait = async {
+            loop {
+                if let Message::Text(text) = client.next().await.unwrap().unwrap() {
+                    return serde_json::from_str(&text).unwrap();
+                }
+            }
+        };
+        async_std::future::timeout(std::time::Duration::from_secs(5), wait).await.unwrap()
+    }
+
+    #[async_std::test]
+    async fn hello_and_catch_up() {
+        let (address, state) = arrange_running_app().await;
+        state.chain.generate_and_append("Second block data").await.unwrap();
+        let (mut client, _) = connect_async(format!("ws://{}/ws", address)).await.unwrap();
+        assert_eq!(next_message(&mut client).await, ServerMessage::Hello { height: 2 });
+
+        let request = serde_json::to_string(&ClientMessage::GetBlocks { from_index: 1 }).unwrap();
+        client.send(Message::Text(request)).await.unwrap();
+        match next_message(&mut client).await {
+            ServerMessage::Blocks(list) => {
+                assert_eq!(list.items.len(), 1);
+                assert_eq!(list.items[0], state.chain.get(1).await.unwrap());
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    #[async_std::test]
+    async fn pushes_posted_blocks() {
+        let (address, state) = arrange_running_app().await;
+        let (mut client, _) = connect_async(format!("ws://{}/ws", address)).await.unwrap();
+        assert_eq!(next_message(&mut client).await, ServerMessage::Hello { height: 1 });
+
+        let second = Block::builder()
+            .after(&state.chain.last().await.unwrap())
+            .data_message("Pushed block")
+            .build();
+        let response = surf::post(format!("http://{}/blocks", address))
+            .body_json(&second)
+            .unwrap()
+            .await
+            .unwrap();
+        assert_eq!(201, response.status());
+        assert_eq!(next_message(&mut client).await, ServerMessage::Block { block: second });
+    }
+
+    #[async_std::test]
+    async fn plain_http_is_refused() {
+        let (address, _) = arrange_running_app().await;
+        let response = surf::get(format!("http://{}/ws", address)).await.unwrap();
+        assert_eq!(426, response.status());
+    }
+}
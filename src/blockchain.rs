@@ -1,5 +1,15 @@
 pub mod block;
-use block::{Block, get_epoch_ms, message_as_json};
+pub mod codec;
+pub mod hasher;
+pub mod merkle;
+pub mod shared;
+use block::{Block, get_epoch_ms, message_as_json, SUPPORTED_VERSIONS};
+use async_std::channel::{unbounded, Receiver, Sender};
+use serde::{Deserialize, Serialize};
+use hasher::{BlockHasher, Sha256Hasher};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
 
 
 #[derive(Debug, PartialEq, Clone)]
@@ -7,52 +17,733 @@ pub enum InvalidBlockErr {
     NotCorrelated(u64, u64),
     NotPosterior(u128, u128),
     HashNotMatching(String, String),
+    TooFarInFuture(u128, u128),
+    EmptyData,
+    DataTooLarge(usize, usize),
+    SelfHashMismatch(String, String),
+    InvalidEntries(String),
+    InsufficientWork(u32),
+    UnsupportedVersion(u16),
+    BadSignature,
     GenesisBlockNotFound,
-    Unkown
+    Unknown { error: String, reason: String },
 }
 
+// The API sends these same sentences as error reasons.
+impl fmt::Display for InvalidBlockErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidBlockErr::NotCorrelated(given, expected) => {
+                write!(f, "expected index {} but received {} which is not inmediate next", expected, given)
+            }
+            InvalidBlockErr::NotPosterior(given, expected) => {
+                write!(f, "Given timestamp {} is not later to {}", given, expected)
+            }
+            InvalidBlockErr::HashNotMatching(given, expected) => {
+                write!(f, "previous hash is {} but {} was provided", expected, given)
+            }
+            InvalidBlockErr::TooFarInFuture(given, now) => {
+                write!(f, "Given timestamp {} is too far ahead of {}", given, now)
+            }
+            InvalidBlockErr::EmptyData => write!(f, "Block data must not be empty"),
+            InvalidBlockErr::DataTooLarge(given, allowed) => {
+                write!(f, "Block data takes {} bytes but only {} are allowed", given, allowed)
+            }
+            InvalidBlockErr::SelfHashMismatch(given, computed) => {
+                write!(f, "block hash was given as {} but content hashes to {}", given, computed)
+            }
+            InvalidBlockErr::InvalidEntries(reason) => write!(f, "{}", reason),
+            InvalidBlockErr::InsufficientWork(difficulty) => {
+                write!(f, "block hash needs {} leading zeros", difficulty)
+            }
+            InvalidBlockErr::UnsupportedVersion(version) => write!(f, "block version {} is not supported", version),
+            InvalidBlockErr::BadSignature => write!(f, "blocks must be signed by their creator"),
+            InvalidBlockErr::GenesisBlockNotFound => write!(f, "chain has no genesis block"),
+            InvalidBlockErr::Unknown { reason, .. } => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for InvalidBlockErr {}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ChainParseErr {
+    Malformed(String),
+    InvalidBlock(u64, InvalidBlockErr),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum DiffErr {
+    Diverged(u64),
+    Ahead,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ReconcileOutcome {
+    AlreadyUpToDate,
+    Extended(usize),
+    Replaced { old_height: u64, new_height: u64 },
+    Lighter { our_work: u128, their_work: u128 },
+    Rejected(InvalidBlockErr),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum AppendOutcome {
+    Extended,
+    ForkedAt(u64),
+    Ignored,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct ChainInfo {
+    pub height: u64,
+    pub last_index: u64,
+    pub last_hash: String,
+    pub genesis_hash: String,
+    pub first_timestamp: u128,
+    pub last_timestamp: u128,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TimestampPolicy {
+    pub allow_equal: bool,
+    pub max_past_skew_ms: u128,
+    pub max_future_skew_ms: Option<u128>,
+}
+
+impl Default for TimestampPolicy {
+    fn default() -> Self {
+        Self {
+            allow_equal: true,
+            max_past_skew_ms: 0,
+            max_future_skew_ms: None,
+        }
+    }
+}
+
+impl TimestampPolicy {
+    pub fn check(&self, timestamp: u128, last_timestamp: u128) -> Result<(), InvalidBlockErr> {
+        if timestamp == last_timestamp && !self.allow_equal {
+            return Err(InvalidBlockErr::NotPosterior(timestamp, last_timestamp))
+        }
+        if timestamp < last_timestamp && last_timestamp - timestamp > self.max_past_skew_ms {
+            return Err(InvalidBlockErr::NotPosterior(timestamp, last_timestamp))
+        }
+        if let Some(max_future_skew_ms) = self.max_future_skew_ms {
+            let now = get_epoch_ms();
+            if timestamp > now + max_future_skew_ms {
+                return Err(InvalidBlockErr::TooFarInFuture(timestamp, now))
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Checkpoint {
+    pub index: u64,
+    pub hash: String,
+    pub timestamp: u128,
+}
+
+impl From<&Block> for Checkpoint {
+    fn from(block: &Block) -> Self {
+        Checkpoint {
+            index: block.index,
+            hash: block.hash(),
+            timestamp: block.timestamp,
+        }
+    }
+}
+
+pub const DEFAULT_MAX_DATA_BYTES: usize = 64 * 1024;
+
+#[derive(Serialize)]
 pub struct Chain {
-    pub blocks: Vec<Block>,
+    blocks: Vec<Block>,
+    #[serde(skip)]
+    policy: TimestampPolicy,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checkpoint: Option<Checkpoint>,
+    #[serde(skip)]
+    forks: HashMap<String, Vec<Block>>,
+    #[serde(skip)]
+    max_data_bytes: usize,
+    #[serde(skip)]
+    difficulty: Option<u32>,
+    #[serde(skip)]
+    require_signatures: bool,
+    #[serde(skip)]
+    subscribers: Vec<Sender<Block>>,
+    #[serde(skip)]
+    total_work: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checkpoint_interval: Option<u64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    checkpoints: Vec<Checkpoint>,
+    #[serde(skip)]
+    hasher: Arc<dyn BlockHasher>,
+}
+
+#[derive(Deserialize)]
+struct RawChain {
+    blocks: Vec<Block>,
+    #[serde(default)]
+    checkpoint: Option<Checkpoint>,
+    #[serde(default)]
+    checkpoint_interval: Option<u64>,
+    #[serde(default)]
+    checkpoints: Vec<Checkpoint>,
 }
 
 
 impl Chain {
     pub fn new(initial_message: String) -> Chain {
-        let data = message_as_json(&initial_message);
-        let genesis_block = Block{
-            index: 0,
-            data: data.clone(),
-            previous_hash: String::from(""),
-            timestamp: get_epoch_ms()
+        Chain::with_policy(initial_message, TimestampPolicy::default())
+    }
+    pub fn with_policy(initial_message: String, policy: TimestampPolicy) -> Chain {
+        let genesis_block = Block::genesis(message_as_json(&initial_message), get_epoch_ms());
+        let mut chain = Chain::from_blocks(vec![genesis_block]);
+        chain.policy = policy;
+        chain
+    }
+    pub fn with_hasher(initial_message: String, hasher: Box<dyn BlockHasher>) -> Chain {
+        let hasher: Arc<dyn BlockHasher> = Arc::from(hasher);
+        let genesis_block = Block::genesis(message_as_json(&initial_message), get_epoch_ms())
+            .seal_with(hasher.as_ref());
+        let mut chain = Chain::from_blocks(vec![genesis_block]);
+        chain.hasher = hasher;
+        chain
+    }
+    pub fn with_genesis(block: Block) -> Result<Chain, InvalidBlockErr> {
+        let mut chain = Chain::empty();
+        chain.bootstrap(block)?;
+        Ok(chain)
+    }
+    pub fn empty() -> Chain {
+        Chain::from_blocks(vec![])
+    }
+    pub fn bootstrap(&mut self, genesis: Block) -> Result<(), InvalidBlockErr> {
+        if let Some(last) = self.blocks.last() {
+            return Err(InvalidBlockErr::NotCorrelated(genesis.index, last.index));
+        }
+        if let Some(checkpoint) = &self.checkpoint {
+            return Err(InvalidBlockErr::NotCorrelated(genesis.index, checkpoint.index));
+        }
+        if !genesis.is_genesis() {
+            return Err(InvalidBlockErr::GenesisBlockNotFound);
+        }
+        genesis.validate_version(SUPPORTED_VERSIONS)?;
+        genesis.verify_own_hash_with(self.hasher.as_ref())
+            .map_err(|(given, computed)| InvalidBlockErr::SelfHashMismatch(given, computed))?;
+        self.total_work += genesis.work();
+        self.blocks.push(genesis);
+        Ok(())
+    }
+    fn hash_of(&self, block: &Block) -> String {
+        block.hash_with(self.hasher.as_ref())
+    }
+    fn checkpoint_of(&self, block: &Block) -> Checkpoint {
+        Checkpoint {
+            index: block.index,
+            hash: self.hash_of(block),
+            timestamp: block.timestamp,
+        }
+    }
+    fn from_blocks(blocks: Vec<Block>) -> Chain {
+        let total_work = blocks.iter().map(Block::work).sum();
+        Chain{
+            blocks: blocks,
+            policy: TimestampPolicy::default(),
+            checkpoint: None,
+            forks: HashMap::new(),
+            max_data_bytes: DEFAULT_MAX_DATA_BYTES,
+            difficulty: None,
+            require_signatures: false,
+            subscribers: vec![],
+            total_work: total_work,
+            checkpoint_interval: None,
+            checkpoints: vec![],
+            hasher: Arc::new(Sha256Hasher),
+        }
+    }
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+    pub fn from_json(serialized: &str) -> Result<Chain, ChainParseErr> {
+        let raw: RawChain = serde_json::from_str(serialized)
+            .map_err(|error| ChainParseErr::Malformed(error.to_string()))?;
+        let mut blocks = raw.blocks.into_iter();
+        let mut chain = match raw.checkpoint {
+            Some(checkpoint) => {
+                let mut chain = Chain::empty();
+                chain.checkpoint = Some(checkpoint);
+                chain
+            }
+            None => match blocks.next() {
+                Some(genesis) => {
+                    let index = genesis.index;
+                    Chain::with_genesis(genesis).map_err(|error| ChainParseErr::InvalidBlock(index, error))?
+                }
+                None => return Err(ChainParseErr::InvalidBlock(0, InvalidBlockErr::GenesisBlockNotFound)),
+            },
         };
-        Chain{ blocks: vec![genesis_block] }
+        for block in blocks {
+            let index = block.index;
+            chain.append(block).map_err(|error| ChainParseErr::InvalidBlock(index, error))?;
+        }
+        for checkpoint in &raw.checkpoints {
+            if let Some(block) = chain.get(checkpoint.index) {
+                let hash = chain.hash_of(block);
+                if hash != checkpoint.hash {
+                    let error = InvalidBlockErr::HashNotMatching(hash, checkpoint.hash.clone());
+                    return Err(ChainParseErr::InvalidBlock(checkpoint.index, error));
+                }
+            }
+        }
+        chain.checkpoint_interval = raw.checkpoint_interval;
+        chain.checkpoints = raw.checkpoints;
+        Ok(chain)
     }
-    pub fn append(&mut self, block: Block) -> Result<Block, InvalidBlockErr> {
-        if self.blocks.len() == 0 {
+    pub fn set_max_data_bytes(&mut self, max_data_bytes: usize) {
+        self.max_data_bytes = max_data_bytes;
+    }
+    pub fn set_difficulty(&mut self, difficulty: Option<u32>) {
+        self.difficulty = difficulty;
+    }
+    pub fn set_require_signatures(&mut self, require_signatures: bool) {
+        self.require_signatures = require_signatures;
+    }
+    fn verify_link(&self, block: &Block, last: &Checkpoint) -> Result<(), InvalidBlockErr> {
+        block.validate_version(SUPPORTED_VERSIONS)?;
+        block.verify_own_hash_with(self.hasher.as_ref())
+            .map_err(|(given, computed)| InvalidBlockErr::SelfHashMismatch(given, computed))?;
+        block.verify_entries().map_err(InvalidBlockErr::InvalidEntries)?;
+        if block.data.is_empty() {
+            return Err(InvalidBlockErr::EmptyData)
+        }
+        let data_bytes = serde_json::to_vec(&block.data).unwrap().len();
+        if data_bytes > self.max_data_bytes {
+            return Err(InvalidBlockErr::DataTooLarge(data_bytes, self.max_data_bytes))
+        }
+        if self.require_signatures && block.verify_signature_with(self.hasher.as_ref()).is_err() {
+            return Err(InvalidBlockErr::BadSignature)
+        }
+        if let Some(difficulty) = self.difficulty {
+            if !block.meets_difficulty_with(difficulty, self.hasher.as_ref()) {
+                return Err(InvalidBlockErr::InsufficientWork(difficulty))
+            }
+        }
+        block.verify_link_to(last, &self.policy)
+    }
+    pub fn verify_next(&self, block: &Block) -> Result<(), InvalidBlockErr> {
+        let last = match (self.blocks.last(), &self.checkpoint) {
+            (Some(last), _) => self.checkpoint_of(last),
+            (None, Some(checkpoint)) => checkpoint.clone(),
+            (None, None) => return Err(InvalidBlockErr::GenesisBlockNotFound),
+        };
+        self.verify_link(block, &last)
+    }
+    pub fn validate(&self) -> Result<(), InvalidBlockErr> {
+        if self.checkpoint.is_none() && self.blocks.is_empty() {
             return Err(InvalidBlockErr::GenesisBlockNotFound);
         }
-        let last = self.blocks.last().unwrap();
-        if block.index != (last.index + 1) {
-            return Err(InvalidBlockErr::NotCorrelated(block.index, last.index))
+        self.validate_blocks(self.checkpoint.clone(), &self.blocks)
+    }
+    pub fn verify_from_checkpoint(checkpoint: &Checkpoint, blocks: &[Block]) -> Result<(), InvalidBlockErr> {
+        Chain::empty().validate_blocks(Some(checkpoint.clone()), blocks)
+    }
+    pub fn find_broken_link(&self) -> Option<(u64, InvalidBlockErr)> {
+        let first_index = self.checkpoint.as_ref().map_or(0, |checkpoint| checkpoint.index + 1);
+        match self.checkpoint.is_none() && self.blocks.is_empty() {
+            true => Some((first_index, InvalidBlockErr::GenesisBlockNotFound)),
+            false => self.locate_invalid(self.checkpoint.clone(), &self.blocks).err(),
         }
-        if block.timestamp < last.timestamp {
-            return Err(InvalidBlockErr::NotPosterior(block.timestamp, last.timestamp))
+    }
+    pub fn dry_run(&self, blocks: &[Block]) -> Result<(), (u64, InvalidBlockErr)> {
+        let start = match blocks.first() {
+            None => return Ok(()),
+            Some(first) if first.is_genesis() => None,
+            Some(first) => match (self.blocks.last(), &self.checkpoint) {
+                (Some(last), _) => Some(self.checkpoint_of(last)),
+                (None, Some(checkpoint)) => Some(checkpoint.clone()),
+                (None, None) => return Err((first.index, InvalidBlockErr::GenesisBlockNotFound)),
+            },
+        };
+        self.locate_invalid(start, blocks)
+    }
+    fn validate_blocks(&self, start: Option<Checkpoint>, blocks: &[Block]) -> Result<(), InvalidBlockErr> {
+        self.locate_invalid(start, blocks).map_err(|(_, error)| error)
+    }
+    fn locate_invalid(&self, start: Option<Checkpoint>, blocks: &[Block]) -> Result<(), (u64, InvalidBlockErr)> {
+        let mut previous: Option<Checkpoint> = start;
+        for block in blocks {
+            match &previous {
+                Some(last) => self.verify_link(block, last).map_err(|error| (block.index, error))?,
+                None if !block.is_genesis() => {
+                    return Err((block.index, InvalidBlockErr::GenesisBlockNotFound))
+                }
+                None => {}
+            }
+            previous = Some(self.checkpoint_of(block));
         }
-        if block.previous_hash != last.hash() {
-            return Err(InvalidBlockErr::HashNotMatching(block.previous_hash, last.hash()))
+        Ok(())
+    }
+    pub fn prune_before(&mut self, index: u64) -> Result<Checkpoint, InvalidBlockErr> {
+        let (first_index, last_index) = match (self.blocks.first(), self.blocks.last()) {
+            (Some(first), Some(last)) => (first.index, last.index),
+            _ => return Err(InvalidBlockErr::GenesisBlockNotFound),
+        };
+        if index <= first_index || index > last_index {
+            return Err(InvalidBlockErr::NotCorrelated(index, last_index));
         }
-        self.blocks.push(block.clone());
+        let pruned: Vec<Block> = self.blocks.drain(..(index - first_index) as usize).collect();
+        let checkpoint = self.checkpoint_of(pruned.last().unwrap());
+        self.checkpoint = Some(checkpoint.clone());
+        self.forks.retain(|_, branch| branch[0].index > index);
+        Ok(checkpoint)
+    }
+    pub fn diff(&self, their_last_index: u64, their_last_hash: &str) -> Result<Vec<Block>, DiffErr> {
+        let our_last_index = match self.get_last_block() {
+            Some(last) => last.index,
+            None => return Err(DiffErr::Ahead),
+        };
+        if their_last_index > our_last_index {
+            return Err(DiffErr::Ahead);
+        }
+        let our_hash = match (self.get(their_last_index), &self.checkpoint) {
+            (Some(block), _) => self.hash_of(block),
+            (None, Some(checkpoint)) if checkpoint.index == their_last_index => checkpoint.hash.clone(),
+            _ => return Err(DiffErr::Diverged(their_last_index)),
+        };
+        if our_hash != their_last_hash {
+            return Err(DiffErr::Diverged(their_last_index));
+        }
+        Ok(self.get_range(their_last_index + 1, u64::MAX).to_vec())
+    }
+    pub fn checkpoint_every(&mut self, interval: u64) {
+        self.checkpoint_interval = match interval {
+            0 => None,
+            interval => Some(interval),
+        };
+    }
+    pub fn checkpoints(&self) -> &[Checkpoint] {
+        &self.checkpoints
+    }
+    fn record_checkpoint(&mut self, block: &Block) {
+        if let Some(interval) = self.checkpoint_interval {
+            if block.index > 0 && block.index % interval == 0 {
+                self.checkpoints.push(self.checkpoint_of(block));
+            }
+        }
+    }
+    pub fn get_checkpoint(&self) -> Option<&Checkpoint> {
+        self.checkpoint.as_ref()
+    }
+    pub fn get(&self, index: u64) -> Option<&Block> {
+        let first_index = self.blocks.first()?.index;
+        if index < first_index {
+            return None;
+        }
+        self.blocks.get((index - first_index) as usize)
+    }
+    pub fn get_range(&self, from_index: u64, to_index: u64) -> &[Block] {
+        let first_index = match self.blocks.first() {
+            Some(first) => first.index,
+            None => return &[],
+        };
+        let start = from_index.max(first_index) - first_index;
+        let end = to_index.max(first_index) - first_index;
+        let start = (start as usize).min(self.blocks.len());
+        let end = (end as usize).min(self.blocks.len());
+        if start >= end {
+            return &[];
+        }
+        &self.blocks[start..end]
+    }
+    pub fn append(&mut self, block: Block) -> Result<Block, InvalidBlockErr> {
+        self.verify_next(&block)?;
+        self.push(block.clone());
         Ok(block)
     }
+    pub fn append_batch(&mut self, blocks: Vec<Block>) -> Result<usize, (usize, InvalidBlockErr)> {
+        let mut previous = match (self.blocks.last(), &self.checkpoint) {
+            (Some(last), _) => self.checkpoint_of(last),
+            (None, Some(checkpoint)) => checkpoint.clone(),
+            (None, None) => return Err((0, InvalidBlockErr::GenesisBlockNotFound)),
+        };
+        for (offset, block) in blocks.iter().enumerate() {
+            self.verify_link(block, &previous).map_err(|error| (offset, error))?;
+            previous = self.checkpoint_of(block);
+        }
+        let appended = blocks.len();
+        for block in blocks {
+            self.push(block);
+        }
+        Ok(appended)
+    }
+    fn push(&mut self, block: Block) {
+        self.total_work += block.work();
+        self.blocks.push(block.clone());
+        self.record_checkpoint(&block);
+        self.notify(&block);
+    }
+    pub fn subscribe(&mut self) -> Receiver<Block> {
+        let (sender, receiver) = unbounded();
+        self.subscribers.push(sender);
+        receiver
+    }
+    fn notify(&mut self, block: &Block) {
+        self.subscribers.retain(|subscriber| subscriber.try_send(block.clone()).is_ok());
+    }
+    pub fn append_or_fork(&mut self, block: Block) -> Result<AppendOutcome, InvalidBlockErr> {
+        let rejection = match self.verify_next(&block) {
+            Ok(()) => {
+                self.push(block);
+                return Ok(AppendOutcome::Extended);
+            }
+            Err(error) => error,
+        };
+        let already_known = self.blocks.contains(&block)
+            || self.forks.values().any(|branch| branch.contains(&block));
+        if already_known {
+            return Ok(AppendOutcome::Ignored);
+        }
+        let extended_branch = self.forks.iter().find_map(|(parent_hash, branch)| {
+            let tip = branch.last().unwrap();
+            match block.previous_hash == self.hash_of(tip) {
+                true => Some((parent_hash.clone(), self.checkpoint_of(tip))),
+                false => None,
+            }
+        });
+        if let Some((parent_hash, tip)) = extended_branch {
+            self.verify_link(&block, &tip)?;
+            let branch = self.forks.get_mut(&parent_hash).unwrap();
+            let fork_index = branch[0].index - 1;
+            branch.push(block);
+            return Ok(AppendOutcome::ForkedAt(fork_index));
+        }
+        let parent = match block.index.checked_sub(1).and_then(|index| self.get(index)) {
+            Some(parent) if self.hash_of(parent) == block.previous_hash => self.checkpoint_of(parent),
+            _ => return Err(rejection),
+        };
+        self.verify_link(&block, &parent)?;
+        if self.forks.contains_key(&block.previous_hash) {
+            return Ok(AppendOutcome::Ignored);
+        }
+        self.forks.insert(block.previous_hash.clone(), vec![block]);
+        Ok(AppendOutcome::ForkedAt(parent.index))
+    }
+    pub fn resolve_forks(&mut self) -> bool {
+        let (first_index, tip_index) = match (self.blocks.first(), self.blocks.last()) {
+            (Some(first), Some(last)) => (first.index, last.index),
+            _ => return false,
+        };
+        let current = (self.total_work, tip_index);
+        let winner = self.forks.iter()
+            .filter(|(parent_hash, branch)| {
+                let parent = self.get(branch[0].index - 1);
+                parent.map_or(false, |parent| &&self.hash_of(parent) == parent_hash)
+            })
+            .map(|(parent_hash, branch)| {
+                let kept = (branch[0].index - first_index) as usize;
+                let displaced_work: u128 = self.blocks[kept..].iter().map(Block::work).sum();
+                let branch_work: u128 = branch.iter().map(Block::work).sum();
+                let candidate = (
+                    self.total_work - displaced_work + branch_work,
+                    branch.last().unwrap().index,
+                );
+                (candidate, parent_hash)
+            })
+            .filter(|(candidate, _)| candidate > &current)
+            .max_by_key(|(candidate, _)| *candidate)
+            .map(|(_, parent_hash)| parent_hash.clone());
+        let parent_hash = match winner {
+            Some(parent_hash) => parent_hash,
+            None => return false,
+        };
+        let branch = self.forks.remove(&parent_hash).unwrap();
+        let kept = (branch[0].index - first_index) as usize;
+        let displaced = self.blocks.split_off(kept);
+        self.total_work -= displaced.iter().map(Block::work).sum::<u128>();
+        let fork_index = branch[0].index;
+        self.checkpoints.retain(|checkpoint| checkpoint.index < fork_index);
+        if !displaced.is_empty() {
+            self.forks.insert(parent_hash, displaced);
+        }
+        for block in branch {
+            self.push(block);
+        }
+        true
+    }
+    pub fn reconcile(&mut self, mut their_blocks: Vec<Block>) -> ReconcileOutcome {
+        let (our_tip, our_first_index) = match (self.blocks.last(), self.blocks.first()) {
+            (Some(last), Some(first)) => (self.checkpoint_of(last), first.index),
+            _ => return ReconcileOutcome::Rejected(InvalidBlockErr::GenesisBlockNotFound),
+        };
+        let their_last_index = match their_blocks.last() {
+            Some(last) => last.index,
+            None => return ReconcileOutcome::AlreadyUpToDate,
+        };
+        let overlap_matches = their_blocks
+            .iter()
+            .take_while(|block| block.index <= our_tip.index)
+            .all(|block| block.index < our_first_index || self.get(block.index) == Some(block));
+        if overlap_matches {
+            let suffix: Vec<Block> = their_blocks
+                .into_iter()
+                .filter(|block| block.index > our_tip.index)
+                .collect();
+            if suffix.is_empty() {
+                return ReconcileOutcome::AlreadyUpToDate;
+            }
+            if let Err(error) = self.validate_blocks(Some(our_tip), &suffix) {
+                return ReconcileOutcome::Rejected(error);
+            }
+            let extended = suffix.len();
+            for block in suffix {
+                self.push(block);
+            }
+            return ReconcileOutcome::Extended(extended);
+        }
+        let our_genesis_hash = match self.get(0) {
+            Some(genesis) => self.hash_of(genesis),
+            None => return ReconcileOutcome::Rejected(InvalidBlockErr::GenesisBlockNotFound),
+        };
+        match their_blocks.first() {
+            Some(genesis) if genesis.is_genesis() && self.hash_of(genesis) == our_genesis_hash => {}
+            Some(other) => {
+                let error = InvalidBlockErr::HashNotMatching(self.hash_of(other), our_genesis_hash);
+                return ReconcileOutcome::Rejected(error);
+            }
+            None => return ReconcileOutcome::Rejected(InvalidBlockErr::GenesisBlockNotFound),
+        }
+        if let Err(error) = self.validate_blocks(None, &their_blocks) {
+            return ReconcileOutcome::Rejected(error);
+        }
+        // Same rule as resolve_forks: the heavier chain wins, height breaks ties.
+        let their_work: u128 = their_blocks.iter().map(Block::work).sum();
+        if (their_work, their_last_index) <= (self.total_work, our_tip.index) {
+            return ReconcileOutcome::Lighter {
+                our_work: self.total_work,
+                their_work: their_work,
+            };
+        }
+        let old_height = our_tip.index + 1;
+        // Only blocks we did not already hold are news to subscribers.
+        let shared = their_blocks
+            .iter()
+            .take_while(|block| block.index < our_first_index || self.get(block.index) == Some(block))
+            .count();
+        let adopted = their_blocks.split_off(shared);
+        self.total_work = their_blocks.iter().map(Block::work).sum();
+        self.blocks = their_blocks;
+        self.forks.clear();
+        self.checkpoints.clear();
+        for position in 0..self.blocks.len() {
+            let block = self.blocks[position].clone();
+            self.record_checkpoint(&block);
+        }
+        for block in adopted {
+            self.push(block);
+        }
+        ReconcileOutcome::Replaced {
+            old_height: old_height,
+            new_height: their_last_index + 1,
+        }
+    }
+    pub fn total_work(&self) -> u128 {
+        self.total_work
+    }
+    pub fn get_forks(&self) -> &HashMap<String, Vec<Block>> {
+        &self.forks
+    }
+    pub fn generate_and_append(&mut self, message: &str) -> Result<Block, InvalidBlockErr> {
+        let next_block = match self.get_last_block() {
+            Some(last) => last.generate_next_with(String::from(message), self.hasher.as_ref()),
+            None => return Err(InvalidBlockErr::GenesisBlockNotFound),
+        };
+        self.append(next_block)
+    }
     pub fn get_last_block(&self) -> Option<&Block> {
         self.blocks.last()
     }
+    pub fn info(&self) -> ChainInfo {
+        match (self.blocks.first(), self.blocks.last()) {
+            (Some(first), Some(last)) => ChainInfo {
+                height: last.index + 1,
+                last_index: last.index,
+                last_hash: self.hash_of(last),
+                genesis_hash: self.hash_of(first),
+                first_timestamp: first.timestamp,
+                last_timestamp: last.timestamp,
+            },
+            _ => ChainInfo::default(),
+        }
+    }
+    pub fn iter(&self) -> std::slice::Iter<'_, Block> {
+        self.blocks.iter()
+    }
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+}
+
+impl Clone for Chain {
+    fn clone(&self) -> Self {
+        Chain{
+            blocks: self.blocks.clone(),
+            policy: self.policy.clone(),
+            checkpoint: self.checkpoint.clone(),
+            forks: self.forks.clone(),
+            max_data_bytes: self.max_data_bytes,
+            difficulty: self.difficulty,
+            require_signatures: self.require_signatures,
+            subscribers: vec![],
+            total_work: self.total_work,
+            checkpoint_interval: self.checkpoint_interval,
+            checkpoints: self.checkpoints.clone(),
+            hasher: self.hasher.clone(),
+        }
+    }
+}
+
+impl Default for Chain {
+    fn default() -> Self {
+        Chain::new(String::from(""))
+    }
+}
+
+impl std::fmt::Debug for Chain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Chain")
+            .field("len", &self.blocks.len())
+            .field("last_index", &self.get_last_block().map(|last| last.index))
+            .field("last_hash", &self.get_last_block().map(|last| self.hash_of(last)))
+            .field("checkpoint", &self.checkpoint)
+            .field("forks", &self.forks.len())
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
+
+impl<'a> IntoIterator for &'a Chain {
+    type Item = &'a Block;
+    type IntoIter = std::slice::Iter<'a, Block>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
 }
 
 #[cfg(test)]
 #[allow(unused)]
 mod tests {
     use super::*;
+    use super::block::Entry;
 
     fn arrange_a_chain() -> Chain {
         Chain::new(String::from("Genesis block"))
@@ -60,12 +751,18 @@ mod tests {
 
     #[test]
     fn test_genesis_block_not_found() {
-        let mut chain = Chain{ blocks: vec![] };
+        let mut chain = Chain::empty();
         let next_block = Block{
             index: 1,
             timestamp: 0,
             data: message_as_json("another block"),
             previous_hash: String::from("c4f3c4f3c4f3"),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1,
         };
         let obtained_error = chain.append(next_block).unwrap_err();
         matches!(obtained_error, InvalidBlockErr::GenesisBlockNotFound);
@@ -78,7 +775,13 @@ mod tests {
             index: 5,
             timestamp: chain.blocks[0].timestamp + 100,
             data: message_as_json("another block"),
-            previous_hash: chain.blocks[0].hash()
+            previous_hash: chain.blocks[0].hash(),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
         };
         let obtained_error = chain.append(next_block).unwrap_err();
         let expected_error = InvalidBlockErr::NotCorrelated(0, 5);
@@ -94,7 +797,13 @@ mod tests {
             index: 1,
             timestamp: invalid_timestamp,
             data: message_as_json("another block"),
-            previous_hash: chain.blocks[0].hash()
+            previous_hash: chain.blocks[0].hash(),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
         };
         let expected_error = InvalidBlockErr::NotPosterior(genesis_timestamp, invalid_timestamp);
         assert!(matches!(
@@ -111,7 +820,13 @@ mod tests {
             index: 1,
             timestamp: chain.blocks[0].timestamp + 5,
             data: message_as_json("another block"),
-            previous_hash: invalid_hash.clone()
+            previous_hash: invalid_hash.clone(),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
         };
         let expected_hash = chain.blocks[0].hash();
         let expected_error = InvalidBlockErr::HashNotMatching(expected_hash, invalid_hash);
@@ -126,7 +841,13 @@ mod tests {
             index: 1,
             timestamp: chain.blocks[0].timestamp + 100,
             data: message_as_json("another block"),
-            previous_hash: chain.blocks[0].hash()
+            previous_hash: chain.blocks[0].hash(),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
         };
         let expected_block = next_block.clone();
         let added_block = chain.append(next_block);
@@ -134,4 +855,1086 @@ mod tests {
         assert!(chain.blocks.contains(&expected_block))
     }
 
+    #[test]
+    fn test_verify_next_genesis_block_not_found() {
+        let chain = Chain::empty();
+        let next_block = Block{
+            index: 1,
+            timestamp: 0,
+            data: message_as_json("another block"),
+            previous_hash: String::from("c4f3c4f3c4f3"),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1,
+        };
+        let obtained_error = chain.verify_next(&next_block).unwrap_err();
+        assert_eq!(obtained_error, InvalidBlockErr::GenesisBlockNotFound);
+        assert_eq!(chain.len(), 0);
+    }
+
+    #[test]
+    fn test_verify_next_invalid_index() {
+        let chain = arrange_a_chain();
+        let next_block = Block{
+            index: 5,
+            timestamp: chain.blocks[0].timestamp + 100,
+            data: message_as_json("another block"),
+            previous_hash: chain.blocks[0].hash(),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
+        };
+        let obtained_error = chain.verify_next(&next_block).unwrap_err();
+        assert_eq!(obtained_error, InvalidBlockErr::NotCorrelated(5, 0));
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn test_verify_next_invalid_timestamp() {
+        let chain = arrange_a_chain();
+        let genesis_timestamp = chain.blocks[0].timestamp;
+        let invalid_timestamp = genesis_timestamp - 5;
+        let next_block = Block{
+            index: 1,
+            timestamp: invalid_timestamp,
+            data: message_as_json("another block"),
+            previous_hash: chain.blocks[0].hash(),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
+        };
+        let obtained_error = chain.verify_next(&next_block).unwrap_err();
+        assert_eq!(obtained_error, InvalidBlockErr::NotPosterior(invalid_timestamp, genesis_timestamp));
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn test_verify_next_invalid_hash() {
+        let chain = arrange_a_chain();
+        let invalid_hash = String::from("cafecafecafe");
+        let next_block = Block{
+            index: 1,
+            timestamp: chain.blocks[0].timestamp + 5,
+            data: message_as_json("another block"),
+            previous_hash: invalid_hash.clone(),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
+        };
+        let expected_hash = chain.blocks[0].hash();
+        let obtained_error = chain.verify_next(&next_block).unwrap_err();
+        assert_eq!(obtained_error, InvalidBlockErr::HashNotMatching(invalid_hash, expected_hash));
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn test_verify_next_valid_block() {
+        let chain = arrange_a_chain();
+        let next_block = chain.blocks[0].generate_next(String::from("another block"));
+        assert_eq!(chain.verify_next(&next_block), Ok(()));
+        assert_eq!(chain.len(), 1);
+    }
+
+    fn arrange_block_at(chain: &Chain, timestamp: u128) -> Block {
+        let last = chain.get_last_block().unwrap();
+        Block{
+            index: last.index + 1,
+            timestamp: timestamp,
+            data: message_as_json("another block"),
+            previous_hash: last.hash(),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
+        }
+    }
+
+    #[test]
+    fn test_policy_allows_equal_timestamps_by_default() {
+        let mut chain = arrange_a_chain();
+        let next_block = arrange_block_at(&chain, chain.blocks[0].timestamp);
+        assert!(chain.append(next_block).is_ok());
+    }
+
+    #[test]
+    fn test_policy_rejects_equal_timestamps() {
+        let policy = TimestampPolicy{ allow_equal: false, ..TimestampPolicy::default() };
+        let mut chain = Chain::with_policy(String::from("Genesis block"), policy);
+        let genesis_timestamp = chain.blocks[0].timestamp;
+        let next_block = arrange_block_at(&chain, genesis_timestamp);
+        let obtained_error = chain.append(next_block).unwrap_err();
+        assert_eq!(obtained_error, InvalidBlockErr::NotPosterior(genesis_timestamp, genesis_timestamp));
+    }
+
+    #[test]
+    fn test_policy_tolerates_past_skew() {
+        let policy = TimestampPolicy{ max_past_skew_ms: 50, ..TimestampPolicy::default() };
+        let mut chain = Chain::with_policy(String::from("Genesis block"), policy);
+        let genesis_timestamp = chain.blocks[0].timestamp;
+        let within_tolerance = arrange_block_at(&chain, genesis_timestamp - 50);
+        assert!(chain.append(within_tolerance).is_ok());
+        let last_timestamp = chain.blocks[1].timestamp;
+        let beyond_tolerance = arrange_block_at(&chain, last_timestamp - 51);
+        let obtained_error = chain.append(beyond_tolerance).unwrap_err();
+        assert_eq!(obtained_error, InvalidBlockErr::NotPosterior(last_timestamp - 51, last_timestamp));
+    }
+
+    #[test]
+    fn test_policy_rejects_far_future() {
+        let policy = TimestampPolicy{ max_future_skew_ms: Some(1000), ..TimestampPolicy::default() };
+        let mut chain = Chain::with_policy(String::from("Genesis block"), policy);
+        let far_future = get_epoch_ms() + 60 * 60 * 1000;
+        let next_block = arrange_block_at(&chain, far_future);
+        let obtained_error = chain.append(next_block).unwrap_err();
+        assert!(matches!(obtained_error, InvalidBlockErr::TooFarInFuture(given, _) if given == far_future));
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn test_generate_and_append() {
+        let mut chain = arrange_a_chain();
+        for position in 1..=50 {
+            let previous_hash = chain.get_last_block().unwrap().hash();
+            let added = chain.generate_and_append("generated block").unwrap();
+            assert_eq!(added.index, position);
+            assert_eq!(added.previous_hash, previous_hash);
+            assert_eq!(chain.get_last_block().unwrap(), &added);
+        }
+        assert_eq!(chain.len(), 51);
+    }
+
+    #[test]
+    fn test_generate_and_append_without_genesis() {
+        let mut chain = Chain::empty();
+        let obtained_error = chain.generate_and_append("orphan block").unwrap_err();
+        assert_eq!(obtained_error, InvalidBlockErr::GenesisBlockNotFound);
+    }
+
+    #[test]
+    fn test_info_of_genesis_only() {
+        let chain = arrange_a_chain();
+        let genesis = &chain.blocks[0];
+        let expected = ChainInfo {
+            height: 1,
+            last_index: 0,
+            last_hash: genesis.hash(),
+            genesis_hash: genesis.hash(),
+            first_timestamp: genesis.timestamp,
+            last_timestamp: genesis.timestamp,
+        };
+        assert_eq!(chain.info(), expected);
+    }
+
+    #[test]
+    fn test_info_of_several_blocks() {
+        let mut chain = arrange_a_chain();
+        let genesis = chain.blocks[0].clone();
+        let mut last = genesis.clone();
+        for position in 1..4 {
+            let next_block = Block{
+                index: position,
+                timestamp: last.timestamp + 100,
+                data: message_as_json("another block"),
+                previous_hash: last.hash(),
+                difficulty: None,
+                hash: None,
+                nonce: 0,
+                signer: None,
+                signature: None,
+                version: 1
+            };
+            last = chain.append(next_block).unwrap();
+        }
+        let expected = ChainInfo {
+            height: 4,
+            last_index: 3,
+            last_hash: last.hash(),
+            genesis_hash: genesis.hash(),
+            first_timestamp: genesis.timestamp,
+            last_timestamp: genesis.timestamp + 300,
+        };
+        assert_eq!(chain.info(), expected);
+    }
+
+    fn arrange_a_long_chain(length: u64) -> Chain {
+        let mut chain = arrange_a_chain();
+        for position in 1..length {
+            let last = chain.get_last_block().unwrap();
+            let next_block = Block{
+                index: position,
+                timestamp: last.timestamp + 100,
+                data: message_as_json("another block"),
+                previous_hash: last.hash(),
+                difficulty: None,
+                hash: None,
+                nonce: 0,
+                signer: None,
+                signature: None,
+                version: 1
+            };
+            chain.append(next_block).unwrap();
+        }
+        chain
+    }
+
+    #[test]
+    fn test_with_genesis_shared_between_chains() {
+        let genesis = Block::genesis(message_as_json("Shared genesis"), 1000);
+        let one = Chain::with_genesis(genesis.clone()).unwrap();
+        let another = Chain::with_genesis(genesis).unwrap();
+        assert_eq!(one.info(), another.info());
+    }
+
+    #[test]
+    fn test_with_genesis_invalid_index() {
+        let mut genesis = Block::genesis(message_as_json("Shared genesis"), 1000);
+        genesis.index = 3;
+        let obtained_error = Chain::with_genesis(genesis).err().unwrap();
+        assert_eq!(obtained_error, InvalidBlockErr::GenesisBlockNotFound);
+    }
+
+    #[test]
+    fn test_with_genesis_invalid_previous_hash() {
+        let mut genesis = Block::genesis(message_as_json("Shared genesis"), 1000);
+        genesis.previous_hash = String::from("c4f3c4f3c4f3");
+        let obtained_error = Chain::with_genesis(genesis).err().unwrap();
+        assert_eq!(obtained_error, InvalidBlockErr::GenesisBlockNotFound);
+    }
+
+    #[test]
+    fn test_validate_full_chain() {
+        let chain = arrange_a_long_chain(4);
+        assert_eq!(chain.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_tampered_chain() {
+        let mut chain = arrange_a_long_chain(4);
+        chain.blocks[1].data = message_as_json("tampered block");
+        assert!(matches!(chain.validate(), Err(InvalidBlockErr::HashNotMatching(_, _))));
+    }
+
+    #[test]
+    fn test_prune_then_append_and_validate() {
+        let mut chain = arrange_a_long_chain(5);
+        let expected_checkpoint = Checkpoint::from(&chain.blocks[2]);
+        let checkpoint = chain.prune_before(3).unwrap();
+        assert_eq!(checkpoint, expected_checkpoint);
+        assert_eq!(chain.get_checkpoint(), Some(&expected_checkpoint));
+        assert_eq!(chain.len(), 2);
+        assert_eq!(chain.validate(), Ok(()));
+        let last = chain.get_last_block().unwrap();
+        let next_block = Block{
+            index: 5,
+            timestamp: last.timestamp + 100,
+            data: message_as_json("after pruning"),
+            previous_hash: last.hash(),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
+        };
+        chain.append(next_block).unwrap();
+        assert_eq!(chain.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_prune_rejects_out_of_range() {
+        let mut chain = arrange_a_long_chain(3);
+        assert_eq!(chain.prune_before(0), Err(InvalidBlockErr::NotCorrelated(0, 2)));
+        assert_eq!(chain.prune_before(3), Err(InvalidBlockErr::NotCorrelated(3, 2)));
+        assert_eq!(chain.len(), 3);
+    }
+
+    #[test]
+    fn test_read_pruned_indices() {
+        let mut chain = arrange_a_long_chain(5);
+        chain.prune_before(3).unwrap();
+        assert_eq!(chain.get(1), None);
+        assert_eq!(chain.get(3).unwrap().index, 3);
+        assert_eq!(chain.get(9), None);
+        assert!(chain.get_range(0, 3).is_empty());
+        let indexes: Vec<u64> = chain.get_range(1, 10).iter().map(|block| block.index).collect();
+        assert_eq!(indexes, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_pruned_json_round_trip() {
+        let mut chain = arrange_a_long_chain(5);
+        chain.prune_before(2).unwrap();
+        let restored = Chain::from_json(&chain.to_json()).unwrap();
+        assert_eq!(restored.blocks, chain.blocks);
+        assert_eq!(restored.get_checkpoint(), chain.get_checkpoint());
+        assert_eq!(restored.validate(), Ok(()));
+    }
+
+    fn arrange_fork_block(parent: &Block, message: &str) -> Block {
+        Block{
+            index: parent.index + 1,
+            timestamp: parent.timestamp + 50,
+            data: message_as_json(message),
+            previous_hash: parent.hash(),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
+        }
+    }
+
+    #[test]
+    fn test_fork_that_loses() {
+        let mut chain = arrange_a_long_chain(3);
+        let main_tip = chain.get_last_block().unwrap().clone();
+        let competing = arrange_fork_block(&chain.blocks[1], "competing block");
+        assert_eq!(chain.append_or_fork(competing.clone()), Ok(AppendOutcome::ForkedAt(1)));
+        assert_eq!(chain.append_or_fork(competing), Ok(AppendOutcome::Ignored));
+        assert!(!chain.resolve_forks());
+        assert_eq!(chain.get_last_block(), Some(&main_tip));
+        assert_eq!(chain.get_forks().len(), 1);
+        assert_eq!(chain.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_fork_that_overtakes() {
+        let mut chain = arrange_a_long_chain(3);
+        let displaced_tip = chain.get_last_block().unwrap().clone();
+        let first = arrange_fork_block(&chain.blocks[1], "fork block one");
+        let second = arrange_fork_block(&first, "fork block two");
+        assert_eq!(chain.append_or_fork(first.clone()), Ok(AppendOutcome::ForkedAt(1)));
+        assert_eq!(chain.append_or_fork(second.clone()), Ok(AppendOutcome::ForkedAt(1)));
+        assert!(chain.resolve_forks());
+        assert_eq!(chain.get_last_block(), Some(&second));
+        assert_eq!(chain.get(2), Some(&first));
+        assert_eq!(chain.validate(), Ok(()));
+        let displaced = chain.get_forks().get(&chain.blocks[1].hash()).unwrap();
+        assert_eq!(displaced, &vec![displaced_tip]);
+    }
+
+    #[test]
+    fn test_fork_unknown_parent_is_rejected() {
+        let mut chain = arrange_a_long_chain(3);
+        let orphan = Block{
+            index: 2,
+            timestamp: chain.blocks[1].timestamp + 50,
+            data: message_as_json("orphan block"),
+            previous_hash: String::from("c4f3c4f3c4f3"),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
+        };
+        assert!(matches!(chain.append_or_fork(orphan), Err(InvalidBlockErr::NotCorrelated(2, 2))));
+        assert!(chain.get_forks().is_empty());
+    }
+
+    #[test]
+    fn test_diff_returns_missing_blocks() {
+        let chain = arrange_a_long_chain(4);
+        let their_tip = &chain.blocks[1];
+        let missing = chain.diff(their_tip.index, &their_tip.hash()).unwrap();
+        assert_eq!(missing, chain.blocks[2..].to_vec());
+    }
+
+    #[test]
+    fn test_diff_when_up_to_date() {
+        let chain = arrange_a_long_chain(4);
+        let their_tip = chain.get_last_block().unwrap();
+        assert_eq!(chain.diff(their_tip.index, &their_tip.hash()), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_diff_diverged_at_genesis() {
+        let chain = arrange_a_long_chain(4);
+        let their_genesis = Block::genesis(message_as_json("Another genesis"), 0);
+        assert_eq!(chain.diff(0, &their_genesis.hash()), Err(DiffErr::Diverged(0)));
+    }
+
+    #[test]
+    fn test_diff_peer_ahead() {
+        let chain = arrange_a_long_chain(4);
+        assert_eq!(chain.diff(7, "c4f3c4f3c4f3"), Err(DiffErr::Ahead));
+    }
+
+    #[test]
+    fn test_bootstrap_then_append() {
+        let mut chain = Chain::empty();
+        let genesis = Block::genesis(message_as_json("Remote genesis"), 1000);
+        chain.bootstrap(genesis.clone()).unwrap();
+        let next_block = arrange_fork_block(&genesis, "after bootstrap");
+        assert!(chain.append(next_block).is_ok());
+        assert_eq!(chain.len(), 2);
+    }
+
+    #[test]
+    fn test_double_bootstrap_rejected() {
+        let mut chain = Chain::empty();
+        let genesis = Block::genesis(message_as_json("Remote genesis"), 1000);
+        chain.bootstrap(genesis.clone()).unwrap();
+        assert_eq!(chain.bootstrap(genesis), Err(InvalidBlockErr::NotCorrelated(0, 0)));
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn test_bootstrap_rejects_non_genesis() {
+        let mut chain = Chain::empty();
+        let mut not_genesis = Block::genesis(message_as_json("Remote genesis"), 1000);
+        not_genesis.index = 1;
+        assert_eq!(chain.bootstrap(not_genesis), Err(InvalidBlockErr::GenesisBlockNotFound));
+        assert!(chain.is_empty());
+    }
+
+    #[test]
+    fn test_cloned_chain_diverges_independently() {
+        let mut chain = arrange_a_long_chain(3);
+        let competing = arrange_fork_block(&chain.blocks[1], "competing block");
+        chain.append_or_fork(competing).unwrap();
+        let mut copy = chain.clone();
+        let next_block = arrange_fork_block(copy.get_last_block().unwrap(), "only on copy");
+        copy.append(next_block).unwrap();
+        copy.prune_before(2).unwrap();
+        assert_eq!(chain.len(), 3);
+        assert_eq!(chain.get_checkpoint(), None);
+        assert_eq!(chain.get_forks().len(), 1);
+        assert_eq!(copy.len(), 2);
+        assert!(copy.get_forks().is_empty());
+        assert_eq!(chain.validate(), Ok(()));
+        assert_eq!(copy.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_debug_is_summarized() {
+        let chain = arrange_a_long_chain(3);
+        let summary = format!("{:?}", chain);
+        assert!(summary.contains("len: 3"));
+        assert!(summary.contains(&chain.get_last_block().unwrap().hash()));
+        assert!(!summary.contains("another block"));
+    }
+
+    #[test]
+    fn test_default_has_genesis() {
+        let chain = Chain::default();
+        assert_eq!(chain.len(), 1);
+        assert_eq!(chain.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_data_size_at_limit_accepted() {
+        let mut chain = arrange_a_chain();
+        let next_block = arrange_fork_block(&chain.blocks[0], "exactly at the limit");
+        let data_bytes = serde_json::to_vec(&next_block.data).unwrap().len();
+        chain.set_max_data_bytes(data_bytes);
+        assert!(chain.append(next_block).is_ok());
+    }
+
+    #[test]
+    fn test_empty_data_rejected() {
+        let mut chain = arrange_a_chain();
+        let next_block = Block::builder().after(&chain.blocks[0]).build();
+        assert_eq!(chain.append(next_block).unwrap_err(), InvalidBlockErr::EmptyData);
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn test_data_size_over_limit_rejected() {
+        let mut chain = arrange_a_chain();
+        let next_block = arrange_fork_block(&chain.blocks[0], "one byte over the limit");
+        let data_bytes = serde_json::to_vec(&next_block.data).unwrap().len();
+        chain.set_max_data_bytes(data_bytes - 1);
+        let obtained_error = chain.append(next_block).unwrap_err();
+        assert_eq!(obtained_error, InvalidBlockErr::DataTooLarge(data_bytes, data_bytes - 1));
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn test_subscribers_receive_appended_blocks() {
+        let mut chain = arrange_a_chain();
+        let one = chain.subscribe();
+        let another = chain.subscribe();
+        let second = arrange_fork_block(&chain.blocks[0], "second block");
+        let third = arrange_fork_block(&second, "third block");
+        chain.append(second.clone()).unwrap();
+        chain.append(third.clone()).unwrap();
+        for receiver in [one, another] {
+            assert_eq!(receiver.try_recv(), Ok(second.clone()));
+            assert_eq!(receiver.try_recv(), Ok(third.clone()));
+            assert!(receiver.try_recv().is_err());
+        }
+    }
+
+    #[test]
+    fn test_rejected_block_is_not_notified() {
+        let mut chain = arrange_a_chain();
+        let receiver = chain.subscribe();
+        let mut invalid = arrange_fork_block(&chain.blocks[0], "invalid block");
+        invalid.previous_hash = String::from("c4f3c4f3c4f3");
+        assert!(chain.append(invalid).is_err());
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_dropped_subscribers_are_removed() {
+        let mut chain = arrange_a_chain();
+        let receiver = chain.subscribe();
+        drop(receiver);
+        let second = arrange_fork_block(&chain.blocks[0], "second block");
+        chain.append(second).unwrap();
+        assert!(chain.subscribers.is_empty());
+    }
+
+    #[test]
+    fn test_total_work_updated_incrementally() {
+        let mut chain = arrange_a_chain();
+        assert_eq!(chain.total_work(), 1);
+        let mut second = arrange_fork_block(&chain.blocks[0], "second block");
+        second.difficulty = Some(5);
+        chain.append(second.clone()).unwrap();
+        let third = arrange_fork_block(&second, "third block");
+        chain.append(third).unwrap();
+        assert_eq!(chain.total_work(), 7);
+        let recomputed: u128 = chain.iter().map(Block::work).sum();
+        assert_eq!(chain.total_work(), recomputed);
+        chain.prune_before(2).unwrap();
+        assert_eq!(chain.total_work(), 7);
+    }
+
+    #[test]
+    fn test_heavier_fork_of_equal_length_wins() {
+        let mut chain = arrange_a_long_chain(3);
+        let light = arrange_fork_block(&chain.blocks[1], "light block");
+        assert_eq!(chain.append_or_fork(light), Ok(AppendOutcome::ForkedAt(1)));
+        assert!(!chain.resolve_forks());
+        let mut chain = arrange_a_long_chain(3);
+        let mut heavy = arrange_fork_block(&chain.blocks[1], "heavy block");
+        heavy.difficulty = Some(3);
+        assert_eq!(chain.append_or_fork(heavy.clone()), Ok(AppendOutcome::ForkedAt(1)));
+        assert!(chain.resolve_forks());
+        assert_eq!(chain.get_last_block(), Some(&heavy));
+        assert_eq!(chain.total_work(), 5);
+        assert_eq!(chain.validate(), Ok(()));
+    }
+
+    fn arrange_checkpointed_chain(length: u64, interval: u64) -> Chain {
+        let mut chain = arrange_a_chain();
+        chain.checkpoint_every(interval);
+        for _ in 1..length {
+            let next_block = arrange_fork_block(chain.get_last_block().unwrap(), "another block");
+            chain.append(next_block).unwrap();
+        }
+        chain
+    }
+
+    #[test]
+    fn test_checkpoint_every() {
+        let chain = arrange_checkpointed_chain(8, 3);
+        let indexes: Vec<u64> = chain.checkpoints().iter().map(|checkpoint| checkpoint.index).collect();
+        assert_eq!(indexes, vec![3, 6]);
+        assert_eq!(chain.checkpoints()[1], Checkpoint::from(chain.get(6).unwrap()));
+    }
+
+    #[test]
+    fn test_verify_from_checkpoint() {
+        let chain = arrange_checkpointed_chain(8, 2);
+        let checkpoint = &chain.checkpoints()[1];
+        let suffix = chain.get_range(checkpoint.index + 1, u64::MAX);
+        assert_eq!(Chain::verify_from_checkpoint(checkpoint, suffix), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_from_checkpoint_detects_tampering() {
+        let chain = arrange_checkpointed_chain(8, 2);
+        let checkpoint = &chain.checkpoints()[1];
+        assert_eq!(checkpoint.index, 4);
+        let mut suffix = chain.get_range(5, u64::MAX).to_vec();
+        suffix[0].data = message_as_json("tampered block");
+        let obtained_error = Chain::verify_from_checkpoint(checkpoint, &suffix).unwrap_err();
+        assert!(matches!(obtained_error, InvalidBlockErr::HashNotMatching(_, _)));
+    }
+
+    #[test]
+    fn test_checkpoints_survive_serialization() {
+        let chain = arrange_checkpointed_chain(6, 2);
+        let mut restored = Chain::from_json(&chain.to_json()).unwrap();
+        assert_eq!(restored.checkpoints(), chain.checkpoints());
+        let next_block = arrange_fork_block(restored.get_last_block().unwrap(), "after restore");
+        restored.append(next_block).unwrap();
+        assert_eq!(restored.checkpoints().len(), 3);
+    }
+
+    #[test]
+    fn test_reconcile_already_up_to_date() {
+        let mut chain = arrange_a_long_chain(4);
+        let theirs = chain.blocks[..3].to_vec();
+        assert_eq!(chain.reconcile(theirs), ReconcileOutcome::AlreadyUpToDate);
+        assert_eq!(chain.reconcile(vec![]), ReconcileOutcome::AlreadyUpToDate);
+        assert_eq!(chain.len(), 4);
+    }
+
+    #[test]
+    fn test_reconcile_extends() {
+        let mut chain = arrange_a_long_chain(3);
+        let mut theirs = chain.clone();
+        for _ in 0..2 {
+            let next_block = arrange_fork_block(theirs.get_last_block().unwrap(), "their block");
+            theirs.append(next_block).unwrap();
+        }
+        let their_blocks = theirs.blocks[1..].to_vec();
+        assert_eq!(chain.reconcile(their_blocks), ReconcileOutcome::Extended(2));
+        assert_eq!(chain.blocks, theirs.blocks);
+        assert_eq!(chain.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_reconcile_replaces_with_longer_chain() {
+        let mut chain = arrange_a_long_chain(3);
+        let mut theirs = chain.clone();
+        theirs.blocks.truncate(2);
+        for _ in 0..3 {
+            let next_block = arrange_fork_block(theirs.get_last_block().unwrap(), "their block");
+            theirs.append(next_block).unwrap();
+        }
+        let outcome = chain.reconcile(theirs.blocks.clone());
+        assert_eq!(outcome, ReconcileOutcome::Replaced { old_height: 3, new_height: 5 });
+        assert_eq!(chain.blocks, theirs.blocks);
+        assert_eq!(chain.total_work(), 5);
+        assert_eq!(chain.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_reconcile_rejects_invalid_blocks() {
+        let mut chain = arrange_a_long_chain(3);
+        let original = chain.blocks.clone();
+        let mut first = arrange_fork_block(chain.get_last_block().unwrap(), "their block");
+        let second = arrange_fork_block(&first, "their block");
+        first.data = message_as_json("tampered block");
+        let outcome = chain.reconcile(vec![first, second]);
+        assert!(matches!(outcome, ReconcileOutcome::Rejected(InvalidBlockErr::HashNotMatching(_, _))));
+        assert_eq!(chain.blocks, original);
+    }
+
+    #[test]
+    fn test_reconcile_rejects_foreign_genesis() {
+        let mut chain = arrange_a_long_chain(2);
+        let mut theirs = Chain::with_genesis(Block::genesis(message_as_json("Another genesis"), 0)).unwrap();
+        for _ in 0..4 {
+            let next_block = arrange_fork_block(theirs.get_last_block().unwrap(), "their block");
+            theirs.append(next_block).unwrap();
+        }
+        let outcome = chain.reconcile(theirs.blocks);
+        assert!(matches!(outcome, ReconcileOutcome::Rejected(InvalidBlockErr::HashNotMatching(_, _))));
+        assert_eq!(chain.len(), 2);
+    }
+
+    #[test]
+    fn test_reconcile_rejects_shorter_divergent_chain() {
+        let mut chain = arrange_a_long_chain(4);
+        let mut theirs = chain.clone();
+        theirs.blocks.truncate(2);
+        let next_block = arrange_fork_block(theirs.get_last_block().unwrap(), "their block");
+        theirs.append(next_block).unwrap();
+        let outcome = chain.reconcile(theirs.blocks);
+        assert_eq!(outcome, ReconcileOutcome::Lighter { our_work: 4, their_work: 3 });
+        assert_eq!(chain.len(), 4);
+    }
+
+    #[test]
+    fn test_append_with_correct_own_hash() {
+        let mut chain = arrange_a_chain();
+        let next_block = arrange_fork_block(&chain.blocks[0], "sealed block").seal();
+        assert!(next_block.hash.is_some());
+        assert!(chain.append(next_block).is_ok());
+    }
+
+    #[test]
+    fn test_append_with_wrong_own_hash() {
+        let mut chain = arrange_a_chain();
+        let mut next_block = arrange_fork_block(&chain.blocks[0], "sealed block").seal();
+        next_block.hash = Some(String::from("c4f3c4f3c4f3"));
+        let computed = next_block.hash();
+        let obtained_error = chain.append(next_block).unwrap_err();
+        assert_eq!(obtained_error, InvalidBlockErr::SelfHashMismatch(String::from("c4f3c4f3c4f3"), computed));
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn test_append_without_own_hash() {
+        let mut chain = arrange_a_chain();
+        let next_block = arrange_fork_block(&chain.blocks[0], "legacy block");
+        assert_eq!(next_block.hash, None);
+        assert!(chain.append(next_block).is_ok());
+    }
+
+    #[test]
+    fn test_mixed_message_and_entry_blocks() {
+        let mut chain = arrange_a_chain();
+        let entries = vec![
+            Entry { id: String::from("tx-1"), payload: serde_json::json!({"amount": 5}) },
+            Entry { id: String::from("tx-2"), payload: serde_json::json!({"amount": 7}) },
+        ];
+        let entry_block = Block::builder()
+            .after(&chain.blocks[0])
+            .data_entries(&entries)
+            .build();
+        let message_block = Block::builder()
+            .after(&entry_block)
+            .data_message("message block")
+            .build();
+        chain.append(entry_block).unwrap();
+        chain.append(message_block).unwrap();
+        assert_eq!(chain.validate(), Ok(()));
+        let found: Vec<Option<Vec<Entry>>> = chain.iter().map(Block::entries).collect();
+        assert_eq!(found, vec![None, Some(entries), None]);
+    }
+
+    #[test]
+    fn test_duplicate_entry_ids_rejected() {
+        let mut chain = arrange_a_chain();
+        let entries = vec![
+            Entry { id: String::from("tx-1"), payload: serde_json::json!(1) },
+            Entry { id: String::from("tx-1"), payload: serde_json::json!(2) },
+        ];
+        let entry_block = Block::builder()
+            .after(&chain.blocks[0])
+            .data_entries(&entries)
+            .build();
+        let obtained_error = chain.append(entry_block).unwrap_err();
+        assert_eq!(obtained_error, InvalidBlockErr::InvalidEntries(String::from("entry id tx-1 is duplicated")));
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn test_difficulty_rejects_unmined_blocks() {
+        let mut chain = arrange_a_chain();
+        chain.set_difficulty(Some(2));
+        let mut unmined = Block::builder().after(&chain.blocks[0]).data_message("unmined").build();
+        while unmined.meets_difficulty(2) {
+            unmined.nonce += 1;
+        }
+        let obtained_error = chain.append(unmined.seal()).unwrap_err();
+        assert_eq!(obtained_error, InvalidBlockErr::InsufficientWork(2));
+        let mut mined = Block::builder().after(&chain.blocks[0]).data_message("mined").build();
+        mined.mine(2);
+        assert!(chain.append(mined).is_ok());
+        assert_eq!(chain.total_work(), 3);
+    }
+
+    #[test]
+    fn test_unsigned_block_rejected_only_when_required() {
+        let mut chain = arrange_a_chain();
+        let unsigned = Block::builder().after(&chain.blocks[0]).data_message("unsigned").build();
+        let mut strict = chain.clone();
+        strict.set_require_signatures(true);
+        assert_eq!(strict.append(unsigned.clone()), Err(InvalidBlockErr::BadSignature));
+        assert!(chain.append(unsigned).is_ok());
+    }
+
+    #[test]
+    fn test_signed_block_accepted_when_required() {
+        let mut chain = arrange_a_chain();
+        chain.set_require_signatures(true);
+        let keypair = ed25519_dalek::SigningKey::from_bytes(&[3; 32]);
+        let mut signed = Block::builder().after(&chain.blocks[0]).data_message("signed").build();
+        signed.sign(&keypair);
+        let mut forged = signed.clone();
+        forged.signature = Some(hex::encode([0u8; 64]));
+        assert_eq!(chain.append(forged), Err(InvalidBlockErr::BadSignature));
+        assert!(chain.append(signed).is_ok());
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let chain = arrange_a_long_chain(4);
+        let serialized = chain.to_json();
+        let restored = Chain::from_json(&serialized).unwrap();
+        assert_eq!(restored.blocks, chain.blocks);
+        assert_eq!(restored.info(), chain.info());
+    }
+
+    #[test]
+    fn test_from_json_tampered_block() {
+        let mut chain = arrange_a_long_chain(4);
+        chain.blocks[2].data = message_as_json("tampered block");
+        let serialized = chain.to_json();
+        let obtained_error = Chain::from_json(&serialized).err().unwrap();
+        assert!(matches!(
+            obtained_error,
+            ChainParseErr::InvalidBlock(3, InvalidBlockErr::HashNotMatching(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_from_json_without_genesis() {
+        let obtained_error = Chain::from_json(r#"{"blocks": []}"#).err().unwrap();
+        assert_eq!(obtained_error, ChainParseErr::InvalidBlock(0, InvalidBlockErr::GenesisBlockNotFound));
+    }
+
+    #[test]
+    fn test_from_json_malformed() {
+        let obtained_error = Chain::from_json(r#"{"blocks": [{"index": "#).err().unwrap();
+        assert!(matches!(obtained_error, ChainParseErr::Malformed(_)));
+    }
+
+    #[test]
+    fn test_iterates_by_index() {
+        let mut chain = arrange_a_chain();
+        let second = chain.blocks[0].generate_next(String::from("second block"));
+        chain.append(second.clone()).unwrap();
+        let third = Block{
+            index: 2,
+            timestamp: second.timestamp + 100,
+            data: message_as_json("third block"),
+            previous_hash: second.hash(),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
+        };
+        chain.append(third).unwrap();
+        let indexes: Vec<u64> = chain.iter().map(|block| block.index).collect();
+        assert_eq!(indexes, vec![0, 1, 2]);
+        let mut count = 0;
+        for block in &chain {
+            assert_eq!(block.index, count);
+            count += 1;
+        }
+        assert_eq!(chain.len(), 3);
+    }
+
+    #[test]
+    fn test_len_and_is_empty() {
+        let empty = Chain::empty();
+        assert!(empty.is_empty());
+        assert_eq!(empty.len(), 0);
+        let chain = arrange_a_chain();
+        assert!(!chain.is_empty());
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn test_generate_in_tight_loop_is_strictly_increasing() {
+        let policy = TimestampPolicy {
+            allow_equal: false,
+            ..TimestampPolicy::default()
+        };
+        let mut chain = Chain::with_policy(String::from("genesis"), policy);
+        for n in 0..1000 {
+            chain.generate_and_append(&format!("block {}", n)).unwrap();
+        }
+        let timestamps: Vec<u128> = chain.iter().map(|block| block.timestamp).collect();
+        assert!(timestamps.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_generate_next_after_future_parent() {
+        let parent = Block::genesis(message_as_json("future"), get_epoch_ms() + 60_000);
+        let next = parent.generate_next(String::from("next"));
+        assert_eq!(next.timestamp, parent.timestamp + 1);
+        assert!(next.verify_against(&parent).is_ok());
+    }
+
+    struct SaltedHasher;
+
+    impl BlockHasher for SaltedHasher {
+        fn digest(&self, bytes: &[u8]) -> Vec<u8> {
+            Sha256Hasher.digest(&[b"salted:", bytes].concat())
+        }
+    }
+
+    #[test]
+    fn test_chains_with_different_hashers() {
+        let mut default_chain = Chain::new(String::from("genesis"));
+        let mut salted_chain = Chain::with_hasher(String::from("genesis"), Box::new(SaltedHasher));
+        for n in 0..3 {
+            default_chain.generate_and_append(&format!("block {}", n)).unwrap();
+            salted_chain.generate_and_append(&format!("block {}", n)).unwrap();
+        }
+        assert_eq!(default_chain.validate(), Ok(()));
+        assert_eq!(salted_chain.validate(), Ok(()));
+        let salted_genesis = salted_chain.get(0).unwrap();
+        assert_eq!(salted_genesis.hash, Some(salted_genesis.hash_with(&SaltedHasher)));
+
+        let foreign = default_chain.get(1).unwrap().clone();
+        let obtained_error = salted_chain.clone().append(foreign).err().unwrap();
+        assert!(matches!(obtained_error, InvalidBlockErr::SelfHashMismatch(_, _)));
+        let foreign = salted_chain.get(1).unwrap().clone();
+        let obtained_error = default_chain.clone().append(foreign).err().unwrap();
+        assert!(matches!(obtained_error, InvalidBlockErr::SelfHashMismatch(_, _)));
+    }
+
+    #[test]
+    fn test_chain_hasher_applies_to_previous_hash() {
+        let mut chain = Chain::with_hasher(String::from("genesis"), Box::new(SaltedHasher));
+        let last = chain.get_last_block().unwrap().clone();
+        let linked_by_sha = Block::new(1, last.hash(), last.timestamp + 1, message_as_json("next"))
+            .seal_with(&SaltedHasher);
+        let obtained_error = chain.append(linked_by_sha).err().unwrap();
+        assert!(matches!(obtained_error, InvalidBlockErr::HashNotMatching(_, _)));
+        let linked = Block::new(1, last.hash_with(&SaltedHasher), last.timestamp + 1, message_as_json("next"))
+            .seal_with(&SaltedHasher);
+        assert!(chain.append(linked).is_ok());
+    }
+
+    #[test]
+    fn test_append_rejects_future_version() {
+        let mut chain = arrange_a_chain();
+        let last = chain.get_last_block().unwrap().clone();
+        let future = Block::builder().after(&last).data_message("from the future").version(99).build();
+        assert_eq!(chain.append(future).err(), Some(InvalidBlockErr::UnsupportedVersion(99)));
+        let v2 = Block::builder().after(&last).data_message("v2").version(2).build();
+        assert!(chain.append(v2).is_ok());
+    }
+
+    #[test]
+    fn test_dry_run_names_offending_index() {
+        let chain = arrange_a_chain();
+        let last = chain.get_last_block().unwrap().clone();
+        let next = Block::builder().after(&last).data_message("next").build();
+        let broken = Block::builder()
+            .index(next.index + 1)
+            .previous_hash("00".repeat(32))
+            .timestamp(next.timestamp + 1)
+            .data_message("broken")
+            .build();
+        assert_eq!(chain.dry_run(&[next.clone()]), Ok(()));
+        let (index, error) = chain.dry_run(&[next.clone(), broken]).err().unwrap();
+        assert_eq!(index, next.index + 1);
+        assert!(matches!(error, InvalidBlockErr::HashNotMatching(_, _)));
+        assert_eq!(chain.len(), last.index as usize + 1);
+        let standalone: Vec<Block> = chain.iter().cloned().collect();
+        assert_eq!(Chain::empty().dry_run(&standalone), Ok(()));
+        assert_eq!(chain.find_broken_link(), None);
+    }
+
+    #[test]
+    fn test_append_batch_is_atomic() {
+        let mut chain = arrange_a_chain();
+        let height = chain.len();
+        let mut parent = chain.get_last_block().unwrap().clone();
+        let mut batch = vec![];
+        for n in 0..4 {
+            let next = Block::builder().after(&parent).data_message(&format!("batch {}", n)).build();
+            batch.push(next.clone());
+            parent = next;
+        }
+        let mut broken = batch.clone();
+        broken[2].timestamp = 0;
+        let (offset, error) = chain.append_batch(broken).err().unwrap();
+        assert_eq!(offset, 2);
+        assert!(matches!(error, InvalidBlockErr::SelfHashMismatch(_, _)));
+        assert_eq!(chain.len(), height);
+        assert_eq!(chain.append_batch(batch), Ok(4));
+        assert_eq!(chain.len(), height + 4);
+        assert_eq!(chain.append_batch(vec![]), Ok(0));
+    }
+
+    #[test]
+    fn test_subscribers_receive_blocks_adopted_in_a_reorg() {
+        let mut chain = arrange_a_long_chain(3);
+        let receiver = chain.subscribe();
+        let first = arrange_fork_block(&chain.blocks[1], "fork block one");
+        let second = arrange_fork_block(&first, "fork block two");
+        chain.append_or_fork(first.clone()).unwrap();
+        chain.append_or_fork(second.clone()).unwrap();
+        assert!(receiver.try_recv().is_err());
+        assert!(chain.resolve_forks());
+        assert_eq!(receiver.try_recv(), Ok(first));
+        assert_eq!(receiver.try_recv(), Ok(second));
+        assert!(receiver.try_recv().is_err());
+        let recomputed: u128 = chain.iter().map(Block::work).sum();
+        assert_eq!(chain.total_work(), recomputed);
+    }
+
+    #[test]
+    fn test_subscribers_receive_reconciled_blocks() {
+        let mut chain = arrange_a_long_chain(3);
+        let original = chain.clone();
+        let mut longer = chain.clone();
+        let mut diverging = chain.clone();
+        diverging.blocks.truncate(2);
+        for _ in 0..2 {
+            let next_block = arrange_fork_block(longer.get_last_block().unwrap(), "their block");
+            longer.append(next_block).unwrap();
+        }
+        for _ in 0..3 {
+            let next_block = arrange_fork_block(diverging.get_last_block().unwrap(), "diverging block");
+            diverging.append(next_block).unwrap();
+        }
+        let receiver = chain.subscribe();
+        assert_eq!(chain.reconcile(longer.blocks[1..].to_vec()), ReconcileOutcome::Extended(2));
+        for block in &longer.blocks[3..] {
+            assert_eq!(receiver.try_recv().as_ref(), Ok(block));
+        }
+        assert!(receiver.try_recv().is_err());
+
+        let mut chain = original;
+        let receiver = chain.subscribe();
+        let outcome = chain.reconcile(diverging.blocks.clone());
+        assert_eq!(outcome, ReconcileOutcome::Replaced { old_height: 3, new_height: 5 });
+        for block in &diverging.blocks[2..] {
+            assert_eq!(receiver.try_recv().as_ref(), Ok(block));
+        }
+        assert!(receiver.try_recv().is_err());
+        let recomputed: u128 = chain.iter().map(Block::work).sum();
+        assert_eq!(chain.total_work(), recomputed);
+    }
+
+
+    #[test]
+    fn test_mine_and_sign_with_the_chain_hasher() {
+        let mut chain = Chain::with_hasher(String::from("genesis"), Box::new(SaltedHasher));
+        chain.set_difficulty(Some(2));
+        chain.set_require_signatures(true);
+        let keypair = ed25519_dalek::SigningKey::from_bytes(&[3; 32]);
+        let last = chain.get_last_block().unwrap().clone();
+        let next = Block::new(1, last.hash_with(&SaltedHasher), last.timestamp + 1, message_as_json("mined"));
+        let mut signed_with_sha = next.clone();
+        signed_with_sha.mine_with(2, &SaltedHasher);
+        signed_with_sha.sign(&keypair);
+        assert_eq!(chain.append(signed_with_sha), Err(InvalidBlockErr::BadSignature));
+        let mut mined = next.seal_with(&SaltedHasher);
+        mined.mine_with(2, &SaltedHasher);
+        mined.sign_with(&keypair, &SaltedHasher);
+        assert!(mined.meets_difficulty_with(2, &SaltedHasher));
+        assert_eq!(mined.verify_own_hash_with(&SaltedHasher), Ok(()));
+        assert!(chain.append(mined).is_ok());
+    }
+
+    #[test]
+    fn test_reconcile_rejects_longer_lighter_chain() {
+        let mut chain = arrange_a_chain();
+        let mut theirs = chain.clone();
+        let mut mined = arrange_fork_block(&chain.blocks[0], "mined block");
+        mined.mine(3);
+        chain.append(mined).unwrap();
+        for _ in 0..2 {
+            let next_block = arrange_fork_block(theirs.get_last_block().unwrap(), "their block");
+            theirs.append(next_block).unwrap();
+        }
+        let original = chain.blocks.clone();
+        let outcome = chain.reconcile(theirs.blocks);
+        assert_eq!(outcome, ReconcileOutcome::Lighter { our_work: 4, their_work: 3 });
+        assert_eq!(chain.blocks, original);
+    }
+
 }
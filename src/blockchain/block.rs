@@ -1,18 +1,98 @@
+use super::codec::{decode, encode, CodecErr};
+use super::hasher::{BlockHasher, Sha256Hasher};
+use super::merkle::{merkle_proof, merkle_root, MerkleProof};
+use super::{Checkpoint, InvalidBlockErr, TimestampPolicy};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::{Value};
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::Hasher;
+use std::ops::RangeInclusive;
 use sha2::{Digest, Sha256};
 extern crate base64;
 extern crate hex;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Block {
     pub index: u64,
     pub previous_hash: String,
     pub timestamp: u128,
     pub data: HashMap<String, Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    #[serde(default)]
+    pub nonce: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    #[serde(default = "default_version")]
+    pub version: u16,
+}
+
+pub const CURRENT_VERSION: u16 = 1;
+pub const SUPPORTED_VERSIONS: RangeInclusive<u16> = 1..=2;
+
+fn default_version() -> u16 {
+    1
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BlockSummary {
+    pub index: u64,
+    pub hash: String,
+    pub previous_hash: String,
+    pub timestamp: u128,
+    pub data_keys: usize,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct BlockTooLarge {
+    pub size: usize,
+    pub max: usize,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum AttachmentErr {
+    Missing(String),
+    InvalidBase64(String),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum SignatureErr {
+    Unsigned,
+    MalformedSigner(String),
+    MalformedSignature(String),
+    Invalid,
+}
+
+impl fmt::Display for BlockSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let keys = match self.data_keys {
+            1 => "key",
+            _ => "keys",
+        };
+        write!(
+            f,
+            "#{} {} <- {} @{} ({} {})",
+            self.index, self.hash, self.previous_hash, self.timestamp, self.data_keys, keys
+        )
+    }
+}
+
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.summary().fmt(f)
+    }
 }
 
 impl PartialEq for Block {
@@ -20,54 +100,511 @@ impl PartialEq for Block {
         self.index == other.index &&
         self.previous_hash == other.previous_hash &&
         self.timestamp == other.timestamp &&
-        self.data == other.data
+        self.data == other.data &&
+        self.difficulty == other.difficulty &&
+        self.nonce == other.nonce &&
+        self.version == other.version
+    }
+}
+
+impl Eq for Block {}
+
+impl std::hash::Hash for Block {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write(&self.hash_bytes());
+    }
+}
+
+impl Ord for Block {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.index.cmp(&other.index)
+            .then(self.timestamp.cmp(&other.timestamp))
+            .then_with(|| Block::hash(self).cmp(&Block::hash(other)))
+    }
+}
+
+impl PartialOrd for Block {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub id: String,
+    pub payload: Value,
+}
+
+pub const ENTRIES_KEY: &str = "entries";
+
+pub fn entries_as_json(entries: &[Entry]) -> HashMap<String, Value> {
+    let mut data = HashMap::new();
+    data.insert(String::from(ENTRIES_KEY), serde_json::to_value(entries).unwrap());
+    data
+}
+
+// Structs become the data map itself; anything that isn't a JSON object is
+// stored under a single "value" key.
+pub fn data_from<T: Serialize>(value: &T) -> HashMap<String, Value> {
+    match serde_json::to_value(value).unwrap() {
+        Value::Object(map) => map.into_iter().collect(),
+        other => {
+            let mut data = HashMap::new();
+            data.insert(String::from("value"), other);
+            data
+        }
     }
 }
 
 pub fn message_as_json(message: &str) -> HashMap<String, Value> {
-    let data_str = format!(r#"
-    {{
-        "message": "{message}"
-    }}"#, message=message);
-    serde_json::from_str(&data_str).unwrap()
-}
-
-fn calculate_hash(index: u64, timestamp: u128, previous_hash: &str, data: &str) -> Vec<u8> {
-    let data = serde_json::json!({
-        "index": index,
-        "previous_hash": previous_hash,
-        "data": data,
-        "timestamp": timestamp.to_string()
+    let mut data = HashMap::new();
+    data.insert(String::from("message"), Value::String(String::from(message)));
+    data
+}
+
+// Keys are sorted before serializing so the digest doesn't depend on HashMap
+// iteration order. Nested objects are serde_json maps, which are already sorted.
+fn canonical_json(data: &HashMap<String, Value>) -> String {
+    let sorted: BTreeMap<&String, &Value> = data.iter().collect();
+    serde_json::to_string(&sorted).unwrap()
+}
+
+fn hash_preimage(block: &Block) -> String {
+    let mut data = serde_json::json!({
+        "index": block.index,
+        "previous_hash": block.previous_hash,
+        "data": canonical_json(&block.data),
+        "timestamp": block.timestamp.to_string()
     });
-    let mut hasher = Sha256::new();
-    hasher.update(data.to_string().as_bytes());
-    hasher.finalize().as_slice().to_owned()
+    if let Some(difficulty) = block.difficulty {
+        data["difficulty"] = serde_json::json!(difficulty);
+    }
+    if block.nonce != 0 {
+        data["nonce"] = serde_json::json!(block.nonce);
+    }
+    if let Some(merkle_root) = block.merkle_root() {
+        data["merkle_root"] = serde_json::json!(merkle_root);
+    }
+    // Version 1 blocks predate this field, so it only joins the digest from 2 on.
+    if block.version >= 2 {
+        data["version"] = serde_json::json!(block.version);
+    }
+    data.to_string()
+}
+
+fn truncate_hash(hash: &str) -> String {
+    match hash.chars().count() {
+        0 => String::from("none"),
+        length if length <= 8 => hash.to_string(),
+        length => {
+            let head: String = hash.chars().take(4).collect();
+            let tail: String = hash.chars().skip(length - 4).collect();
+            format!("{}…{}", head, tail)
+        }
+    }
 }
 
-fn as_hex(bytes: Vec<u8>) -> String {
-    hex::encode(&bytes)
+pub fn as_hex(bytes: &[u8]) -> String {
+    hex::encode(bytes)
 }
 
+// Last timestamp handed out in this process, so a clock stepping backwards
+// never produces an earlier time than one we already used.
+static LAST_EPOCH_MS: AtomicU64 = AtomicU64::new(0);
+
 pub fn get_epoch_ms() -> u128 {
-    SystemTime::now()
+    let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_millis()
+        .map_or(0, |elapsed| elapsed.as_millis() as u64);
+    let previous = LAST_EPOCH_MS.fetch_max(now, AtomicOrdering::SeqCst);
+    now.max(previous) as u128
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BlockBuilder {
+    index: u64,
+    previous_hash: String,
+    timestamp: Option<u128>,
+    data: HashMap<String, Value>,
+    difficulty: Option<u32>,
+    version: Option<u16>,
+}
+
+impl BlockBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn index(mut self, index: u64) -> Self {
+        self.index = index;
+        self
+    }
+    pub fn previous_hash(mut self, previous_hash: impl Into<String>) -> Self {
+        self.previous_hash = previous_hash.into();
+        self
+    }
+    pub fn timestamp(mut self, timestamp: u128) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+    pub fn data_message(mut self, message: &str) -> Self {
+        self.data = message_as_json(message);
+        self
+    }
+    pub fn data_entries(mut self, entries: &[Entry]) -> Self {
+        self.data = entries_as_json(entries);
+        self
+    }
+    pub fn data_map(mut self, data: HashMap<String, Value>) -> Self {
+        self.data = data;
+        self
+    }
+    pub fn difficulty(mut self, difficulty: u32) -> Self {
+        self.difficulty = Some(difficulty);
+        self
+    }
+    pub fn version(mut self, version: u16) -> Self {
+        self.version = Some(version);
+        self
+    }
+    pub fn after(mut self, parent: &Block) -> Self {
+        self.index = parent.index + 1;
+        self.previous_hash = parent.hash();
+        self.timestamp = Some(get_epoch_ms().max(parent.timestamp + 1));
+        self
+    }
+    pub fn build(self) -> Block {
+        Block{
+            index: self.index,
+            previous_hash: self.previous_hash,
+            timestamp: self.timestamp.unwrap_or_else(get_epoch_ms),
+            data: self.data,
+            difficulty: self.difficulty,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: self.version.unwrap_or(CURRENT_VERSION)
+        }.seal()
+    }
 }
 
 impl Block {
+    pub fn new(index: u64, previous_hash: String, timestamp: u128, data: HashMap<String, Value>) -> Block {
+        Block{
+            index: index,
+            previous_hash: previous_hash,
+            timestamp: timestamp,
+            data: data,
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: CURRENT_VERSION
+        }.seal()
+    }
+
+    pub fn builder() -> BlockBuilder {
+        BlockBuilder::new()
+    }
+
+    pub fn genesis(data: HashMap<String, Value>, timestamp: u128) -> Block {
+        Block{
+            index: 0,
+            previous_hash: String::from(""),
+            timestamp: timestamp,
+            data: data,
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: CURRENT_VERSION
+        }.seal()
+    }
+
+    pub fn validate_version(&self, supported: RangeInclusive<u16>) -> Result<(), InvalidBlockErr> {
+        match supported.contains(&self.version) {
+            true => Ok(()),
+            false => Err(InvalidBlockErr::UnsupportedVersion(self.version)),
+        }
+    }
+
+    // Digest width depends on the chain's hasher, so only the encoding is
+    // checked here; whether the hash links up is for the chain to decide.
+    pub fn validate_shape(&self) -> Result<(), String> {
+        let well_formed = self.previous_hash.len() % 2 == 0 &&
+            self.previous_hash.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f'));
+        match well_formed {
+            true => Ok(()),
+            false => Err(format!(
+                "previous_hash must be empty or an even number of lowercase hex characters, got {:?}",
+                self.previous_hash
+            )),
+        }
+    }
+
+    pub fn is_genesis(&self) -> bool {
+        self.index == 0 && self.previous_hash.is_empty()
+    }
+
     pub fn hash(&self) -> String {
-        let serialized_data = serde_json::to_string(&self.data).unwrap();
-        as_hex(calculate_hash(self.index, self.timestamp, &self.previous_hash, &serialized_data))
+        as_hex(&self.hash_bytes())
+    }
+
+    pub fn hash_bytes(&self) -> [u8; 32] {
+        Sha256::digest(hash_preimage(self).as_bytes()).into()
+    }
+
+    pub fn hash_with(&self, hasher: &dyn BlockHasher) -> String {
+        as_hex(&hasher.digest(hash_preimage(self).as_bytes()))
+    }
+
+    pub fn verify_against(&self, prev: &Block) -> Result<(), InvalidBlockErr> {
+        self.verify_against_with(prev, &TimestampPolicy::default())
+    }
+
+    pub fn verify_against_with(&self, prev: &Block, policy: &TimestampPolicy) -> Result<(), InvalidBlockErr> {
+        self.verify_link_to(&Checkpoint::from(prev), policy)
+    }
+
+    pub(crate) fn verify_link_to(&self, last: &Checkpoint, policy: &TimestampPolicy) -> Result<(), InvalidBlockErr> {
+        if self.index != (last.index + 1) {
+            return Err(InvalidBlockErr::NotCorrelated(self.index, last.index))
+        }
+        policy.check(self.timestamp, last.timestamp)?;
+        if self.previous_hash != last.hash {
+            return Err(InvalidBlockErr::HashNotMatching(self.previous_hash.clone(), last.hash.clone()))
+        }
+        Ok(())
+    }
+
+    pub fn meets_difficulty(&self, difficulty: u32) -> bool {
+        self.meets_difficulty_with(difficulty, &Sha256Hasher)
+    }
+
+    pub fn meets_difficulty_with(&self, difficulty: u32, hasher: &dyn BlockHasher) -> bool {
+        self.hash_with(hasher).chars().take_while(|c| *c == '0').count() >= difficulty as usize
+    }
+
+    pub fn mine(&mut self, difficulty: u32) {
+        self.mine_with(difficulty, &Sha256Hasher)
+    }
+
+    pub fn mine_with(&mut self, difficulty: u32, hasher: &dyn BlockHasher) {
+        if difficulty > 0 {
+            self.difficulty = Some(difficulty);
+        }
+        self.nonce = 0;
+        while !self.meets_difficulty_with(difficulty, hasher) {
+            self.nonce += 1;
+        }
+        if self.hash.is_some() {
+            self.hash = Some(self.hash_with(hasher));
+        }
+    }
+
+    pub fn sign(&mut self, keypair: &SigningKey) {
+        self.sign_with(keypair, &Sha256Hasher)
+    }
+
+    pub fn sign_with(&mut self, keypair: &SigningKey, hasher: &dyn BlockHasher) {
+        let signature = keypair.sign(self.hash_with(hasher).as_bytes());
+        self.signer = Some(hex::encode(keypair.verifying_key().as_bytes()));
+        self.signature = Some(hex::encode(signature.to_bytes()));
+    }
+
+    pub fn verify_signature(&self) -> Result<(), SignatureErr> {
+        self.verify_signature_with(&Sha256Hasher)
+    }
+
+    pub fn verify_signature_with(&self, hasher: &dyn BlockHasher) -> Result<(), SignatureErr> {
+        let (signer, signature) = match (&self.signer, &self.signature) {
+            (Some(signer), Some(signature)) => (signer, signature),
+            _ => return Err(SignatureErr::Unsigned),
+        };
+        let signer_bytes: [u8; 32] = hex::decode(signer).ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| SignatureErr::MalformedSigner(signer.clone()))?;
+        let verifying_key = VerifyingKey::from_bytes(&signer_bytes)
+            .map_err(|_| SignatureErr::MalformedSigner(signer.clone()))?;
+        let signature_bytes: [u8; 64] = hex::decode(signature).ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| SignatureErr::MalformedSignature(signature.clone()))?;
+        let signature = Signature::from_bytes(&signature_bytes);
+        verifying_key
+            .verify(self.hash_with(hasher).as_bytes(), &signature)
+            .map_err(|_| SignatureErr::Invalid)
+    }
+
+    pub fn seal(self) -> Block {
+        self.seal_with(&Sha256Hasher)
+    }
+
+    pub fn seal_with(mut self, hasher: &dyn BlockHasher) -> Block {
+        self.hash = Some(self.hash_with(hasher));
+        self
+    }
+
+    pub fn verify_own_hash(&self) -> Result<(), (String, String)> {
+        self.verify_own_hash_with(&Sha256Hasher)
+    }
+
+    pub fn verify_own_hash_with(&self, hasher: &dyn BlockHasher) -> Result<(), (String, String)> {
+        match &self.hash {
+            Some(given) => {
+                let computed = self.hash_with(hasher);
+                match given == &computed {
+                    true => Ok(()),
+                    false => Err((given.clone(), computed)),
+                }
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub fn with_entries(mut self, entries: &[Entry]) -> Block {
+        self.data.insert(String::from(ENTRIES_KEY), serde_json::to_value(entries).unwrap());
+        match self.hash {
+            Some(_) => self.seal(),
+            None => self,
+        }
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.data.get("message")?.as_str()
+    }
+
+    pub fn data_as<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        let data: serde_json::Map<String, Value> = self.data.clone().into_iter().collect();
+        serde_json::from_value(Value::Object(data))
+    }
+
+    pub fn set_data<T: Serialize>(&mut self, value: &T) {
+        self.data = data_from(value);
+        if self.hash.is_some() {
+            self.hash = Some(self.hash());
+        }
+    }
+
+    // Attachments travel base64-encoded, so the size limits, which measure the
+    // serialized block, always count at least the decoded size.
+    pub fn attach_bytes(&mut self, key: &str, bytes: &[u8]) {
+        self.data.insert(String::from(key), Value::String(base64::encode(bytes)));
+        if self.hash.is_some() {
+            self.hash = Some(self.hash());
+        }
+    }
+
+    pub fn get_bytes(&self, key: &str) -> Result<Vec<u8>, AttachmentErr> {
+        let encoded = self.data.get(key)
+            .ok_or_else(|| AttachmentErr::Missing(String::from(key)))?;
+        encoded.as_str()
+            .and_then(|encoded| base64::decode(encoded).ok())
+            .ok_or_else(|| AttachmentErr::InvalidBase64(String::from(key)))
+    }
+
+    pub fn entries(&self) -> Option<Vec<Entry>> {
+        let entries = self.data.get(ENTRIES_KEY)?;
+        serde_json::from_value(entries.clone()).ok()
+    }
+
+    fn entry_leaves(&self) -> Option<Vec<Vec<u8>>> {
+        let entries = self.entries()?;
+        Some(entries.iter().map(|entry| serde_json::to_vec(entry).unwrap()).collect())
+    }
+
+    pub fn merkle_root(&self) -> Option<String> {
+        self.entry_leaves().map(|leaves| merkle_root(&leaves))
+    }
+
+    pub fn prove_entry(&self, index: usize) -> Option<MerkleProof> {
+        merkle_proof(&self.entry_leaves()?, index)
+    }
+
+    pub fn verify_entries(&self) -> Result<(), String> {
+        if !self.data.contains_key(ENTRIES_KEY) {
+            return Ok(());
+        }
+        let entries = match self.entries() {
+            Some(entries) => entries,
+            None => return Err(String::from("entries are not a list of id and payload")),
+        };
+        let mut seen = std::collections::HashSet::new();
+        for (position, entry) in entries.iter().enumerate() {
+            if entry.id.is_empty() {
+                return Err(format!("entry at position {} has an empty id", position));
+            }
+            if !seen.insert(&entry.id) {
+                return Err(format!("entry id {} is duplicated", entry.id));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, CodecErr> {
+        encode(self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CodecErr> {
+        decode(bytes)
+    }
+
+    pub fn size_bytes(&self) -> usize {
+        serde_json::to_vec(self).unwrap().len()
+    }
+
+    pub fn validate_size(&self, max: usize) -> Result<(), BlockTooLarge> {
+        let size = self.size_bytes();
+        match size > max {
+            true => Err(BlockTooLarge { size: size, max: max }),
+            false => Ok(()),
+        }
+    }
+
+    pub fn summary(&self) -> BlockSummary {
+        BlockSummary {
+            index: self.index,
+            hash: truncate_hash(&self.hash()),
+            previous_hash: truncate_hash(&self.previous_hash),
+            timestamp: self.timestamp,
+            data_keys: self.data.len(),
+        }
+    }
+
+    pub fn work(&self) -> u128 {
+        self.difficulty.map_or(1, |difficulty| difficulty as u128)
     }
 
     pub fn generate_next(&self, message:String) -> Block {
+        self.generate_next_with_data(message_as_json(&message))
+    }
+
+    pub fn generate_next_with(&self, message: String, hasher: &dyn BlockHasher) -> Block {
+        self.next_sealed_with(message_as_json(&message), hasher)
+    }
+
+    pub fn generate_next_with_data(&self, data: HashMap<String, Value>) -> Block {
+        self.next_sealed_with(data, &Sha256Hasher)
+    }
+
+    pub fn generate_next_from<T: Serialize>(&self, value: &T) -> Block {
+        self.generate_next_with_data(data_from(value))
+    }
+
+    fn next_sealed_with(&self, data: HashMap<String, Value>, hasher: &dyn BlockHasher) -> Block {
         Block{
             index: self.index + 1,
-            previous_hash: self.hash(),
-            timestamp: get_epoch_ms(),
-            data: message_as_json(&message)
-        }
+            previous_hash: self.hash_with(hasher),
+            timestamp: get_epoch_ms().max(self.timestamp + 1),
+            data: data,
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: CURRENT_VERSION
+        }.seal_with(hasher)
     }
     
 }
@@ -82,7 +619,13 @@ mod tests {
             index: 0,
             previous_hash: String::from(""),
             timestamp: 0,
-            data: message_as_json("not important")
+            data: message_as_json("not important"),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
         };
         assert_eq!(genesis.index, 0)
     }
@@ -93,7 +636,13 @@ mod tests {
             index: 0,
             previous_hash: String::from(""),
             timestamp: 0,
-            data: message_as_json("This data has to match")
+            data: message_as_json("This data has to match"),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
         };
         let expected_data = message_as_json("This data has to match");
         assert_eq!(genesis.data, expected_data);
@@ -106,7 +655,13 @@ mod tests {
             index: 0,
             previous_hash: String::from(""),
             timestamp: now,
-            data: message_as_json("This timestamp has to match")
+            data: message_as_json("This timestamp has to match"),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
         };
         assert_eq!(genesis.timestamp, now)
     }
@@ -117,7 +672,13 @@ mod tests {
             index: 0,
             previous_hash: String::from(""),
             timestamp: 0,
-            data: message_as_json("not important")
+            data: message_as_json("not important"),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
         };
         let expected_hash = "ffd175853d16c15f4a97051c906bdb60fafd2e67a6ed6e179a66cdc91876156f";
         assert_eq!(expected_hash, genesis.hash())
@@ -129,13 +690,25 @@ mod tests {
             index: 0,
             previous_hash: String::from(""),
             timestamp: 0,
-            data: message_as_json("not important")
+            data: message_as_json("not important"),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
         };
         let another = Block{
             index: 0,
             previous_hash: String::from(""),
             timestamp: 0,
-            data: message_as_json("not important")
+            data: message_as_json("not important"),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
         };
         assert_eq!(one == another, true)
     }
@@ -146,13 +719,25 @@ mod tests {
             index: 0,
             previous_hash: String::from(""),
             timestamp: 0,
-            data: message_as_json("Not important")
+            data: message_as_json("Not important"),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
         };
         let another = Block{
             index: one.index + 1,
             previous_hash: String::from(""),
             timestamp: 0,
-            data: message_as_json("Not important")
+            data: message_as_json("Not important"),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
         };
         assert_eq!(one != another, true)
     }
@@ -163,13 +748,25 @@ mod tests {
             index: 0,
             previous_hash: String::from("000000000000000"),
             timestamp: 0,
-            data: message_as_json("Not important")
+            data: message_as_json("Not important"),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
         };
         let another = Block{
             index: 0,
             previous_hash: String::from("fffffffffffffff"),
             timestamp: 0,
-            data: message_as_json("Not important")
+            data: message_as_json("Not important"),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
         };
         assert_eq!(one != another, true)
     }
@@ -180,13 +777,25 @@ mod tests {
             index: 0,
             previous_hash: String::from(""),
             timestamp: 0,
-            data: message_as_json("Not important")
+            data: message_as_json("Not important"),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
         };
         let another = Block{
             index: 0,
             previous_hash: String::from(""),
             timestamp: 123456789,
-            data: message_as_json("Not important")
+            data: message_as_json("Not important"),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
         };
         assert_eq!(one != another, true)
     }
@@ -197,26 +806,543 @@ mod tests {
             index: 0,
             previous_hash: String::from(""),
             timestamp: 0,
-            data: message_as_json("Not important")
+            data: message_as_json("Not important"),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
         };
         let another = Block{
             index: 0,
             previous_hash: String::from(""),
             timestamp: 0,
-            data: message_as_json("This is a different data")
+            data: message_as_json("This is a different data"),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
         };
         assert_eq!(one != another, true)
     }
 
+    #[test]
+    fn test_hash_covers_difficulty() {
+        let plain = Block::genesis(message_as_json("not important"), 0);
+        let mut weighted = plain.clone();
+        weighted.difficulty = Some(4);
+        assert_eq!(plain.hash(), "ffd175853d16c15f4a97051c906bdb60fafd2e67a6ed6e179a66cdc91876156f");
+        assert_ne!(plain.hash(), weighted.hash());
+        assert_eq!(plain.work(), 1);
+        assert_eq!(weighted.work(), 4);
+    }
+
+    #[test]
+    fn test_genesis_is_deterministic() {
+        let one = Block::genesis(message_as_json("Shared genesis"), 1000);
+        let another = Block::genesis(message_as_json("Shared genesis"), 1000);
+        assert_eq!(one.index, 0);
+        assert_eq!(one.previous_hash, "");
+        assert_eq!(one.hash(), another.hash())
+    }
+
+    #[test]
+    fn test_message_with_special_characters() {
+        let messages = [
+            "with \"double quotes\"",
+            "with \\ backslashes \\",
+            "with\nnew lines\r\nand\ttabs",
+            "with emoji 🦀⛓",
+            "",
+        ];
+        for message in messages.iter() {
+            let data = message_as_json(message);
+            assert_eq!(data.get("message").unwrap(), &Value::String(String::from(*message)));
+            let serialized = serde_json::to_string(&data).unwrap();
+            let restored: HashMap<String, Value> = serde_json::from_str(&serialized).unwrap();
+            assert_eq!(restored, data);
+        }
+    }
+
+    #[test]
+    fn test_new_sets_every_field() {
+        let block = Block::new(3, String::from("c4f3c4f3c4f3"), 1000, message_as_json("Not important"));
+        assert_eq!(block.index, 3);
+        assert_eq!(block.previous_hash, "c4f3c4f3c4f3");
+        assert_eq!(block.timestamp, 1000);
+        assert_eq!(block.data, message_as_json("Not important"));
+        assert_eq!(block.difficulty, None);
+    }
+
+    #[test]
+    fn test_builder_after_parent() {
+        let parent = Block::genesis(message_as_json("Parent"), get_epoch_ms() + 5000);
+        let child = Block::builder()
+            .after(&parent)
+            .data_message("Child")
+            .build();
+        assert_eq!(child.index, 1);
+        assert_eq!(child.previous_hash, parent.hash());
+        assert!(child.timestamp > parent.timestamp);
+        assert_eq!(child.data, message_as_json("Child"));
+    }
+
+    #[test]
+    fn test_builder_explicit_fields() {
+        let mut data = HashMap::new();
+        data.insert(String::from("amount"), Value::from(10));
+        let block = Block::builder()
+            .index(7)
+            .previous_hash("c4f3c4f3c4f3")
+            .timestamp(1000)
+            .data_map(data.clone())
+            .difficulty(2)
+            .build();
+        assert_eq!(block.index, 7);
+        assert_eq!(block.previous_hash, "c4f3c4f3c4f3");
+        assert_eq!(block.timestamp, 1000);
+        assert_eq!(block.data, data);
+        assert_eq!(block.difficulty, Some(2));
+    }
+
+    #[test]
+    fn test_generated_blocks_carry_own_hash() {
+        let genesis = Block::genesis(message_as_json("Not important"), 0);
+        let next_block = genesis.generate_next(String::from("New data"));
+        assert_eq!(genesis.hash, Some(genesis.hash()));
+        assert_eq!(next_block.hash, Some(next_block.hash()));
+        assert_eq!(next_block.verify_own_hash(), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_own_hash_detects_tampering() {
+        let mut block = Block::genesis(message_as_json("Not important"), 0);
+        let given = block.hash.clone().unwrap();
+        block.data = message_as_json("Tampered");
+        assert_eq!(block.verify_own_hash(), Err((given, block.hash())));
+    }
+
+    #[test]
+    fn test_missing_hash_still_parses() {
+        let serialized = r#"{"index":0,"previous_hash":"","timestamp":0,"data":{"message":"not important"}}"#;
+        let block: Block = serde_json::from_str(serialized).unwrap();
+        assert_eq!(block.hash, None);
+        assert_eq!(block.verify_own_hash(), Ok(()));
+    }
+
+    #[test]
+    fn test_hash_is_stable_for_multiple_keys() {
+        let mut data = HashMap::new();
+        for key in ["zeta", "alpha", "mu", "beta", "omega", "kappa"].iter() {
+            data.insert(String::from(*key), Value::String(key.to_uppercase()));
+        }
+        data.insert(String::from("nested"), serde_json::json!({"b": 1, "a": [1, 2], "c": {"y": 0, "x": 1}}));
+        let block = Block::genesis(data.clone(), 0);
+        let expected_hash = block.hash();
+        for _ in 0..50 {
+            let rebuilt: HashMap<String, Value> = data.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+            assert_eq!(Block::genesis(rebuilt, 0).hash(), expected_hash);
+            let serialized = serde_json::to_string(&block).unwrap();
+            let restored: Block = serde_json::from_str(&serialized).unwrap();
+            assert_eq!(restored.hash(), expected_hash);
+        }
+        assert_eq!(expected_hash, "f4b03df2d7aa93b72b135aa3d25b9b81f940b20c153bc7f29533330ff927c2dd");
+    }
+
+    fn arrange_entries(ids: &[&str]) -> Vec<Entry> {
+        ids.iter()
+            .map(|id| Entry { id: String::from(*id), payload: serde_json::json!({"amount": 1}) })
+            .collect()
+    }
+
+    #[test]
+    fn test_entries_round_trip() {
+        let entries = arrange_entries(&["a", "b"]);
+        let block = Block::builder().data_entries(&entries).build();
+        assert_eq!(block.entries(), Some(entries));
+        assert_eq!(block.verify_entries(), Ok(()));
+        let message_block = Block::genesis(message_as_json("not important"), 0);
+        assert_eq!(message_block.entries(), None);
+        assert_eq!(message_block.verify_entries(), Ok(()));
+    }
+
+    #[test]
+    fn test_with_entries_keeps_message_and_reseals() {
+        let block = Block::genesis(message_as_json("not important"), 0);
+        let entries = arrange_entries(&["a"]);
+        let block = block.with_entries(&entries);
+        assert_eq!(block.data.get("message").unwrap(), "not important");
+        assert_eq!(block.entries(), Some(entries));
+        assert_eq!(block.verify_own_hash(), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_entries_rejects_duplicates_and_empty_ids() {
+        let duplicated = Block::builder().data_entries(&arrange_entries(&["a", "b", "a"])).build();
+        assert_eq!(duplicated.verify_entries(), Err(String::from("entry id a is duplicated")));
+        let empty = Block::builder().data_entries(&arrange_entries(&["a", ""])).build();
+        assert_eq!(empty.verify_entries(), Err(String::from("entry at position 1 has an empty id")));
+        let mut data = HashMap::new();
+        data.insert(String::from(ENTRIES_KEY), Value::from("not a list"));
+        let malformed = Block::builder().data_map(data).build();
+        assert!(malformed.verify_entries().is_err());
+    }
+
+    fn arrange_parent() -> Block {
+        Block::genesis(message_as_json("Parent"), 1000)
+    }
+
+    #[test]
+    fn test_verify_against_valid_child() {
+        let parent = arrange_parent();
+        let child = Block::builder().after(&parent).data_message("Child").build();
+        assert_eq!(child.verify_against(&parent), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_against_invalid_index() {
+        let parent = arrange_parent();
+        let child = Block::builder().after(&parent).index(5).data_message("Child").build();
+        assert_eq!(child.verify_against(&parent), Err(InvalidBlockErr::NotCorrelated(5, 0)));
+    }
+
+    #[test]
+    fn test_verify_against_invalid_timestamp() {
+        let parent = arrange_parent();
+        let child = Block::builder().after(&parent).timestamp(995).data_message("Child").build();
+        assert_eq!(child.verify_against(&parent), Err(InvalidBlockErr::NotPosterior(995, 1000)));
+    }
+
+    #[test]
+    fn test_verify_against_invalid_hash() {
+        let parent = arrange_parent();
+        let child = Block::builder()
+            .after(&parent)
+            .previous_hash("cafecafecafe")
+            .data_message("Child")
+            .build();
+        let expected_error = InvalidBlockErr::HashNotMatching(String::from("cafecafecafe"), parent.hash());
+        assert_eq!(child.verify_against(&parent), Err(expected_error));
+    }
+
+    #[test]
+    fn test_verify_against_with_policy() {
+        let parent = arrange_parent();
+        let child = Block::builder().after(&parent).timestamp(1000).data_message("Child").build();
+        let policy = TimestampPolicy { allow_equal: false, ..TimestampPolicy::default() };
+        assert_eq!(child.verify_against(&parent), Ok(()));
+        assert_eq!(child.verify_against_with(&parent, &policy), Err(InvalidBlockErr::NotPosterior(1000, 1000)));
+    }
+
+    #[test]
+    fn test_mine_meets_difficulty() {
+        for difficulty in 1..=2 {
+            let mut block = Block::builder().after(&arrange_parent()).data_message("Mined").build();
+            block.mine(difficulty);
+            assert!(block.hash().starts_with(&"0".repeat(difficulty as usize)));
+            assert!(block.meets_difficulty(difficulty));
+            assert_eq!(block.difficulty, Some(difficulty));
+            assert_eq!(block.verify_own_hash(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_zero_nonce_keeps_hash() {
+        let mut genesis = Block::genesis(message_as_json("not important"), 0);
+        genesis.mine(0);
+        assert_eq!(genesis.nonce, 0);
+        assert_eq!(genesis.hash(), "ffd175853d16c15f4a97051c906bdb60fafd2e67a6ed6e179a66cdc91876156f");
+        let legacy: Block = serde_json::from_str(r#"{"index":0,"previous_hash":"","timestamp":0,"data":{}}"#).unwrap();
+        assert_eq!(legacy.nonce, 0);
+    }
+
+    fn arrange_keypair(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let mut block = Block::builder().after(&arrange_parent()).data_message("Signed").build();
+        let keypair = arrange_keypair(7);
+        let hash_before = block.hash();
+        block.sign(&keypair);
+        assert_eq!(block.hash(), hash_before);
+        assert_eq!(block.signer, Some(hex::encode(keypair.verifying_key().as_bytes())));
+        assert_eq!(block.verify_signature(), Ok(()));
+        let serialized = serde_json::to_string(&block).unwrap();
+        let restored: Block = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(restored.verify_signature(), Ok(()));
+    }
+
+    #[test]
+    fn test_tampered_signed_block_fails() {
+        let mut block = Block::builder().after(&arrange_parent()).data_message("Signed").build();
+        block.sign(&arrange_keypair(7));
+        block.data = message_as_json("Tampered");
+        assert_eq!(block.verify_signature(), Err(SignatureErr::Invalid));
+        let mut impersonated = Block::builder().after(&arrange_parent()).data_message("Signed").build();
+        impersonated.sign(&arrange_keypair(7));
+        impersonated.signer = Some(hex::encode(arrange_keypair(8).verifying_key().as_bytes()));
+        assert_eq!(impersonated.verify_signature(), Err(SignatureErr::Invalid));
+    }
+
+    #[test]
+    fn test_unsigned_or_malformed_signature() {
+        let mut block = Block::builder().after(&arrange_parent()).data_message("Unsigned").build();
+        assert_eq!(block.verify_signature(), Err(SignatureErr::Unsigned));
+        block.signer = Some(String::from("c4f3"));
+        block.signature = Some(String::from("c4f3"));
+        assert_eq!(block.verify_signature(), Err(SignatureErr::MalformedSigner(String::from("c4f3"))));
+    }
+
+    #[test]
+    fn test_entry_inclusion_proof() {
+        let entries = arrange_entries(&["a", "b", "c"]);
+        let block = Block::builder().data_entries(&entries).build();
+        let root = block.merkle_root().unwrap();
+        for (index, entry) in entries.iter().enumerate() {
+            let proof = block.prove_entry(index).unwrap();
+            assert!(proof.verify(&root, &serde_json::to_vec(entry).unwrap()));
+        }
+        assert_eq!(block.prove_entry(3), None);
+        assert_eq!(Block::genesis(message_as_json("no entries"), 0).merkle_root(), None);
+    }
+
+    #[test]
+    fn test_mutated_entry_breaks_proof_and_hash() {
+        let mut entries = arrange_entries(&["a", "b", "c"]);
+        let block = Block::builder().data_entries(&entries).build();
+        let root = block.merkle_root().unwrap();
+        let proof = block.prove_entry(1).unwrap();
+        entries[1].payload = serde_json::json!({"amount": 1000});
+        assert!(!proof.verify(&root, &serde_json::to_vec(&entries[1]).unwrap()));
+        let tampered = block.clone().with_entries(&entries);
+        assert_ne!(tampered.merkle_root().unwrap(), root);
+        assert_ne!(tampered.hash(), block.hash());
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Transfer {
+        from: String,
+        to: String,
+        amount: u64,
+    }
+
+    #[test]
+    fn test_strict_deserialization() {
+        let missing_data = r#"{"index":0,"previous_hash":"","timestamp":0}"#;
+        let error = serde_json::from_str::<Block>(missing_data).unwrap_err();
+        assert!(error.to_string().contains("missing field `data`"));
+        let wrong_type = r#"{"index":"zero","previous_hash":"","timestamp":0,"data":{}}"#;
+        assert!(serde_json::from_str::<Block>(wrong_type).is_err());
+        let unknown_key = r#"{"index":0,"previous_hash":"","timestamp":0,"data":{},"extra":1}"#;
+        let error = serde_json::from_str::<Block>(unknown_key).unwrap_err();
+        assert!(error.to_string().contains("unknown field `extra`"));
+    }
+
+    #[test]
+    fn test_validate_shape() {
+        let parent = arrange_parent();
+        assert_eq!(parent.validate_shape(), Ok(()));
+        assert_eq!(Block::builder().after(&parent).build().validate_shape(), Ok(()));
+        let uppercase = Block::new(1, parent.hash().to_uppercase(), 1001, message_as_json("up"));
+        assert!(uppercase.validate_shape().unwrap_err().contains("lowercase hex"));
+        let short = Block::new(1, String::from("abc"), 1001, message_as_json("short"));
+        assert!(short.validate_shape().is_err());
+    }
+
+    #[test]
+    fn test_hash_bytes_match_hex_hash() {
+        let block = Block::genesis(message_as_json("not important"), 0);
+        assert_eq!(hex::encode(block.hash_bytes()), block.hash());
+        assert_eq!(as_hex(&block.hash_bytes()), "ffd175853d16c15f4a97051c906bdb60fafd2e67a6ed6e179a66cdc91876156f");
+        assert_eq!(block.hash_with(&Sha256Hasher), block.hash());
+    }
+
+    #[test]
+    fn test_v1_json_without_version() {
+        let serialized = r#"{"index":0,"previous_hash":"","timestamp":0,"data":{"message":"not important"}}"#;
+        let block: Block = serde_json::from_str(serialized).unwrap();
+        assert_eq!(block.version, 1);
+        assert_eq!(block.hash(), "ffd175853d16c15f4a97051c906bdb60fafd2e67a6ed6e179a66cdc91876156f");
+    }
+
+    #[test]
+    fn test_version_joins_hash_from_v2() {
+        let v1 = Block::builder().timestamp(0).data_message("not important").build();
+        let v2 = Block::builder().timestamp(0).data_message("not important").version(2).build();
+        assert_eq!(v1.hash(), "ffd175853d16c15f4a97051c906bdb60fafd2e67a6ed6e179a66cdc91876156f");
+        assert_ne!(v1.hash(), v2.hash());
+        assert_ne!(v1, v2);
+    }
+
+    #[test]
+    fn test_validate_version() {
+        let block = Block::builder().version(99).build();
+        assert_eq!(block.validate_version(SUPPORTED_VERSIONS), Err(InvalidBlockErr::UnsupportedVersion(99)));
+        assert!(arrange_parent().validate_version(SUPPORTED_VERSIONS).is_ok());
+    }
+
+    #[test]
+    fn test_size_limits() {
+        let block = arrange_parent();
+        let size = block.size_bytes();
+        assert_eq!(size, serde_json::to_string(&block).unwrap().len());
+        assert!(block.validate_size(size * 2).is_ok());
+        assert!(block.validate_size(size).is_ok());
+        assert_eq!(block.validate_size(size - 1), Err(BlockTooLarge { size: size, max: size - 1 }));
+    }
+
+    #[test]
+    fn test_attachment_round_trip() {
+        let payload: Vec<u8> = (0..=255).collect();
+        let mut block = arrange_parent();
+        block.attach_bytes("blob", &payload);
+        assert_eq!(block.get_bytes("blob"), Ok(payload.clone()));
+        assert!(block.verify_own_hash().is_ok());
+        assert_eq!(block.get_bytes("other"), Err(AttachmentErr::Missing(String::from("other"))));
+        block.data.insert(String::from("broken"), serde_json::json!("not base64!"));
+        assert_eq!(block.get_bytes("broken"), Err(AttachmentErr::InvalidBase64(String::from("broken"))));
+        assert_eq!(block.get_bytes("message"), Err(AttachmentErr::InvalidBase64(String::from("message"))));
+    }
+
+    #[test]
+    fn test_attachment_counts_towards_size() {
+        let payload = vec![7u8; 4096];
+        let mut block = arrange_parent();
+        block.attach_bytes("blob", &payload);
+        assert!(block.size_bytes() > payload.len());
+        assert!(block.validate_size(payload.len()).is_err());
+    }
+
+    #[test]
+    fn test_message_accessor() {
+        let block = Block::genesis(message_as_json("hello"), 0);
+        assert_eq!(block.message(), Some("hello"));
+        assert_eq!(Block::genesis(HashMap::new(), 0).message(), None);
+        let mut data = HashMap::new();
+        data.insert(String::from("message"), serde_json::json!(42));
+        assert_eq!(Block::genesis(data, 0).message(), None);
+    }
+
+    #[test]
+    fn test_typed_data_round_trip() {
+        let transfer = Transfer {
+            from: String::from("alice"),
+            to: String::from("bob"),
+            amount: 10,
+        };
+        let mut block = arrange_parent();
+        let previous_hash = block.hash.clone();
+        block.set_data(&transfer);
+        assert_eq!(block.data_as::<Transfer>().unwrap(), transfer);
+        assert_ne!(block.hash, previous_hash);
+        assert!(block.verify_own_hash().is_ok());
+        assert!(arrange_parent().data_as::<Transfer>().is_err());
+    }
+
+    #[test]
+    fn test_generate_next_from_struct() {
+        let parent = arrange_parent();
+        let transfer = Transfer {
+            from: String::from("alice"),
+            to: String::from("bob"),
+            amount: 3,
+        };
+        let next = parent.generate_next_from(&transfer);
+        assert_eq!(next.index, 1);
+        assert_eq!(next.previous_hash, parent.hash());
+        assert!(next.timestamp > parent.timestamp);
+        assert_eq!(next.data_as::<Transfer>().unwrap(), transfer);
+        assert!(next.verify_against(&parent).is_ok());
+    }
+
+    #[test]
+    fn test_dedup_with_hash_set() {
+        let genesis = arrange_parent();
+        let next = Block::builder().after(&genesis).data_message("gossip").build();
+        let mut unsealed = next.clone();
+        unsealed.hash = None;
+        let incoming = vec![genesis.clone(), next.clone(), genesis.clone(), unsealed];
+        let unique: std::collections::HashSet<Block> = incoming.into_iter().collect();
+        assert_eq!(unique.len(), 2);
+        assert!(unique.contains(&genesis));
+        assert!(unique.contains(&next));
+    }
+
+    #[test]
+    fn test_ordering_by_index_then_timestamp() {
+        let genesis = arrange_parent();
+        let early = Block::new(1, genesis.hash(), 2000, message_as_json("early"));
+        let late = Block::new(1, genesis.hash(), 3000, message_as_json("late"));
+        assert!(genesis < early);
+        assert!(early < late);
+        let twin = Block::new(1, genesis.hash(), 2000, message_as_json("twin"));
+        assert_eq!(early.cmp(&twin), early.hash().cmp(&twin.hash()));
+    }
+
+    #[test]
+    fn test_is_genesis() {
+        assert!(Block::genesis(message_as_json("genesis"), 0).is_genesis());
+        assert!(!Block::new(3, String::new(), 0, message_as_json("orphan")).is_genesis());
+        assert!(!Block::new(0, String::from("abc"), 0, message_as_json("linked")).is_genesis());
+    }
+
+    #[test]
+    fn test_display_genesis() {
+        let genesis = Block::genesis(message_as_json("not important"), 0);
+        assert_eq!(genesis.to_string(), "#0 ffd1…156f <- none @0 (1 key)");
+    }
+
+    #[test]
+    fn test_display_block() {
+        let mut data = message_as_json("hello");
+        data.insert(String::from("author"), serde_json::json!("me"));
+        let block = Block::new(42, String::from("77aa00000000000001ef"), 1700000000000, data);
+        let hash = block.hash();
+        let expected = format!("#42 {}…{} <- 77aa…01ef @1700000000000 (2 keys)", &hash[..4], &hash[60..]);
+        assert_eq!(block.to_string(), expected);
+        let summary = block.summary();
+        assert_eq!(summary.previous_hash, "77aa…01ef");
+        assert_eq!(summary.data_keys, 2);
+    }
+
+    #[test]
+    fn test_summary_keeps_short_previous_hash() {
+        let block = Block::new(1, String::from("abc"), 5, HashMap::new());
+        assert_eq!(block.summary().previous_hash, "abc");
+        assert!(block.to_string().ends_with("<- abc @5 (0 keys)"));
+    }
+
     #[test]
     fn test_genesis_valid_next() {
         let genesis = Block{
             index: 0,
             previous_hash: String::from(""),
             timestamp: 0,
-            data: message_as_json("Not important")
+            data: message_as_json("Not important"),
+            difficulty: None,
+            hash: None,
+            nonce: 0,
+            signer: None,
+            signature: None,
+            version: 1
         };
         let next_block = genesis.generate_next(String::from("New data"));
         assert_eq!(next_block.previous_hash == genesis.hash(), true)
     }
+
+    #[test]
+    fn test_validate_shape_accepts_any_digest_width() {
+        let parent = arrange_parent();
+        let wide = Block::new(1, "ab".repeat(64), 1001, message_as_json("sha3-512"));
+        assert_eq!(wide.validate_shape(), Ok(()));
+        let narrow = Block::new(1, "ab".repeat(10), 1001, message_as_json("ripemd160"));
+        assert_eq!(narrow.validate_shape(), Ok(()));
+        let odd = Block::new(1, format!("{}a", parent.hash()), 1001, message_as_json("odd"));
+        assert!(odd.validate_shape().is_err());
+    }
 }
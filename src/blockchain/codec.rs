@@ -0,0 +1,82 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+pub const WIRE_VERSION: u8 = 1;
+
+#[derive(Debug, PartialEq)]
+pub enum CodecErr {
+    Empty,
+    UnsupportedVersion(u8),
+    Encode(String),
+    Malformed(String),
+}
+
+pub fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, CodecErr> {
+    let mut bytes = vec![WIRE_VERSION];
+    ciborium::ser::into_writer(value, &mut bytes).map_err(|err| CodecErr::Encode(err.to_string()))?;
+    Ok(bytes)
+}
+
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CodecErr> {
+    match bytes.split_first() {
+        None => Err(CodecErr::Empty),
+        Some((&WIRE_VERSION, body)) => {
+            ciborium::de::from_reader(body).map_err(|err| CodecErr::Malformed(err.to_string()))
+        }
+        Some((&version, _)) => Err(CodecErr::UnsupportedVersion(version)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::block::Block;
+    use serde_json::Value;
+    use std::collections::HashMap;
+
+    fn arrange_block(message: &str, timestamp: u128) -> Block {
+        Block::builder()
+            .index(1)
+            .previous_hash("abc")
+            .timestamp(timestamp)
+            .data_message(message)
+            .build()
+    }
+
+    #[test]
+    fn test_unicode_message_round_trip() {
+        let block = arrange_block("ünïcødé ✓ 区块链 🦀", 1000);
+        let decoded = Block::from_bytes(&block.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded, block);
+        assert_eq!(decoded.hash(), block.hash());
+    }
+
+    #[test]
+    fn test_large_data_map_round_trip() {
+        let mut data: HashMap<String, Value> = HashMap::new();
+        for n in 0..2000 {
+            data.insert(format!("key {}", n), serde_json::json!({"n": n, "nested": [n, "x"]}));
+        }
+        let block = Block::builder().data_map(data).build();
+        let decoded = Block::from_bytes(&block.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded, block);
+    }
+
+    #[test]
+    fn test_timestamp_near_max_round_trip() {
+        for timestamp in [u128::MAX, u128::MAX - 1, u64::MAX as u128 + 1] {
+            let block = arrange_block("late", timestamp);
+            let decoded = Block::from_bytes(&block.to_bytes().unwrap()).unwrap();
+            assert_eq!(decoded.timestamp, timestamp);
+        }
+    }
+
+    #[test]
+    fn test_malformed_input_is_an_error() {
+        assert_eq!(Block::from_bytes(&[]).err(), Some(CodecErr::Empty));
+        assert_eq!(Block::from_bytes(&[9, 1, 2]).err(), Some(CodecErr::UnsupportedVersion(9)));
+        let mut bytes = arrange_block("cut", 1).to_bytes().unwrap();
+        bytes.truncate(bytes.len() / 2);
+        assert!(matches!(Block::from_bytes(&bytes), Err(CodecErr::Malformed(_))));
+    }
+}
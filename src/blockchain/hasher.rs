@@ -0,0 +1,14 @@
+use sha2::{Digest, Sha256};
+
+pub trait BlockHasher: Send + Sync {
+    fn digest(&self, bytes: &[u8]) -> Vec<u8>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256Hasher;
+
+impl BlockHasher for Sha256Hasher {
+    fn digest(&self, bytes: &[u8]) -> Vec<u8> {
+        Sha256::digest(bytes).as_slice().to_owned()
+    }
+}
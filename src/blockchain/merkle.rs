@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MerkleStep {
+    pub sibling: String,
+    pub sibling_is_left: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MerkleProof {
+    pub leaf_index: usize,
+    pub steps: Vec<MerkleStep>,
+}
+
+fn hash_leaf(leaf: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(leaf);
+    hasher.finalize().as_slice().to_owned()
+}
+
+fn hash_node(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().as_slice().to_owned()
+}
+
+// Odd levels pair their last node with itself.
+fn next_level(level: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => hash_node(left, right),
+            [single] => hash_node(single, single),
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+pub fn merkle_root(leaves: &[Vec<u8>]) -> String {
+    if leaves.is_empty() {
+        return hex::encode(Sha256::digest(&[]));
+    }
+    let mut level: Vec<Vec<u8>> = leaves.iter().map(|leaf| hash_leaf(leaf)).collect();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    hex::encode(&level[0])
+}
+
+pub fn merkle_proof(leaves: &[Vec<u8>], leaf_index: usize) -> Option<MerkleProof> {
+    if leaf_index >= leaves.len() {
+        return None;
+    }
+    let mut level: Vec<Vec<u8>> = leaves.iter().map(|leaf| hash_leaf(leaf)).collect();
+    let mut position = leaf_index;
+    let mut steps = vec![];
+    while level.len() > 1 {
+        let sibling_position = match position % 2 {
+            0 => (position + 1).min(level.len() - 1),
+            _ => position - 1,
+        };
+        steps.push(MerkleStep {
+            sibling: hex::encode(&level[sibling_position]),
+            sibling_is_left: sibling_position < position,
+        });
+        level = next_level(&level);
+        position /= 2;
+    }
+    Some(MerkleProof {
+        leaf_index: leaf_index,
+        steps: steps,
+    })
+}
+
+impl MerkleProof {
+    pub fn verify(&self, root: &str, leaf: &[u8]) -> bool {
+        let mut current = hash_leaf(leaf);
+        for step in &self.steps {
+            let sibling = match hex::decode(&step.sibling) {
+                Ok(sibling) => sibling,
+                Err(_) => return false,
+            };
+            current = match step.sibling_is_left {
+                true => hash_node(&sibling, &current),
+                false => hash_node(&current, &sibling),
+            };
+        }
+        hex::encode(current) == root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arrange_leaves(count: usize) -> Vec<Vec<u8>> {
+        (0..count).map(|n| format!("leaf {}", n).into_bytes()).collect()
+    }
+
+    #[test]
+    fn test_single_leaf() {
+        let leaves = arrange_leaves(1);
+        let root = merkle_root(&leaves);
+        assert_eq!(root, hex::encode(hash_leaf(&leaves[0])));
+        let proof = merkle_proof(&leaves, 0).unwrap();
+        assert!(proof.steps.is_empty());
+        assert!(proof.verify(&root, &leaves[0]));
+    }
+
+    #[test]
+    fn test_two_leaves() {
+        let leaves = arrange_leaves(2);
+        let root = merkle_root(&leaves);
+        let expected = hash_node(&hash_leaf(&leaves[0]), &hash_leaf(&leaves[1]));
+        assert_eq!(root, hex::encode(expected));
+        for index in 0..2 {
+            let proof = merkle_proof(&leaves, index).unwrap();
+            assert!(proof.verify(&root, &leaves[index]));
+        }
+    }
+
+    #[test]
+    fn test_odd_number_of_leaves() {
+        let leaves = arrange_leaves(5);
+        let root = merkle_root(&leaves);
+        for index in 0..5 {
+            let proof = merkle_proof(&leaves, index).unwrap();
+            assert!(proof.verify(&root, &leaves[index]));
+            assert!(!proof.verify(&root, b"not a leaf"));
+        }
+        assert_eq!(merkle_proof(&leaves, 5), None);
+    }
+}
@@ -0,0 +1,132 @@
+use super::block::Block;
+use super::{Chain, ChainInfo, InvalidBlockErr};
+use async_std::channel::Receiver;
+use async_std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::Arc;
+
+// Readers share the lock; every method hands back owned data so no guard
+// outlives the call.
+#[derive(Clone, Default)]
+pub struct SharedChain {
+    inner: Arc<RwLock<Chain>>,
+}
+
+impl SharedChain {
+    pub fn new(chain: Chain) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(chain)),
+        }
+    }
+    async fn read(&self) -> RwLockReadGuard<'_, Chain> {
+        self.inner.read().await
+    }
+    async fn write(&self) -> RwLockWriteGuard<'_, Chain> {
+        self.inner.write().await
+    }
+    pub async fn append(&self, block: Block) -> Result<Block, InvalidBlockErr> {
+        self.write().await.append(block)
+    }
+    pub async fn append_batch(&self, blocks: Vec<Block>) -> Result<usize, (usize, InvalidBlockErr)> {
+        self.write().await.append_batch(blocks)
+    }
+    pub async fn generate_and_append(&self, message: &str) -> Result<Block, InvalidBlockErr> {
+        self.write().await.generate_and_append(message)
+    }
+    pub async fn set_difficulty(&self, difficulty: Option<u32>) {
+        self.write().await.set_difficulty(difficulty)
+    }
+    pub async fn verify_next(&self, block: &Block) -> Result<(), InvalidBlockErr> {
+        self.read().await.verify_next(block)
+    }
+    pub async fn find_broken_link(&self) -> Option<(u64, InvalidBlockErr)> {
+        self.read().await.find_broken_link()
+    }
+    pub async fn dry_run(&self, blocks: &[Block]) -> Result<(), (u64, InvalidBlockErr)> {
+        self.read().await.dry_run(blocks)
+    }
+    pub async fn last(&self) -> Option<Block> {
+        self.read().await.get_last_block().cloned()
+    }
+    pub async fn get(&self, index: u64) -> Option<Block> {
+        self.read().await.get(index).cloned()
+    }
+    pub async fn range(&self, from_index: u64, to_index: u64) -> Vec<Block> {
+        self.read().await.get_range(from_index, to_index).to_vec()
+    }
+    pub async fn info(&self) -> ChainInfo {
+        self.read().await.info()
+    }
+    pub async fn len(&self) -> usize {
+        self.read().await.len()
+    }
+    pub async fn is_empty(&self) -> bool {
+        self.read().await.is_empty()
+    }
+    pub async fn subscribe(&self) -> Receiver<Block> {
+        self.write().await.subscribe()
+    }
+    pub async fn snapshot(&self) -> Chain {
+        self.read().await.clone()
+    }
+}
+
+impl From<Chain> for SharedChain {
+    fn from(chain: Chain) -> Self {
+        SharedChain::new(chain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::task;
+
+    #[async_std::test]
+    async fn test_parallel_appends_keep_chain_valid() {
+        let shared = SharedChain::new(Chain::new(String::from("Genesis block")));
+        let mut handles = vec![];
+        for worker in 0..8 {
+            let writer = shared.clone();
+            handles.push(task::spawn(async move {
+                for _ in 0..25 {
+                    writer
+                        .generate_and_append(&format!("block from worker {}", worker))
+                        .await
+                        .unwrap();
+                    task::yield_now().await;
+                }
+            }));
+        }
+        for _ in 0..4 {
+            let reader = shared.clone();
+            handles.push(task::spawn(async move {
+                for _ in 0..50 {
+                    let last = reader.last().await.unwrap();
+                    let info = reader.info().await;
+                    assert!(info.last_index >= last.index);
+                    assert_eq!(reader.range(last.index, last.index + 1).await.len(), 1);
+                    task::yield_now().await;
+                }
+            }));
+        }
+        for handle in handles {
+            handle.await;
+        }
+        let snapshot = shared.snapshot().await;
+        assert_eq!(snapshot.len(), 201);
+        assert_eq!(snapshot.validate(), Ok(()));
+        let indexes: Vec<u64> = snapshot.iter().map(|block| block.index).collect();
+        assert_eq!(indexes, (0..201).collect::<Vec<u64>>());
+    }
+
+    #[async_std::test]
+    async fn test_returns_owned_copies() {
+        let shared = SharedChain::new(Chain::new(String::from("Genesis block")));
+        let mut last = shared.last().await.unwrap();
+        last.index = 42;
+        assert_eq!(shared.last().await.unwrap().index, 0);
+        assert_eq!(shared.get(0).await.unwrap().index, 0);
+        assert_eq!(shared.get(1).await, None);
+        assert_eq!(shared.len().await, 1);
+    }
+}
@@ -0,0 +1,13 @@
+mod api;
+mod blockchain;
+mod peers;
+
+pub use api::client::{APIClient, APIClientError, BroadcastReport, MirrorError, PeerRegistration, SyncError};
+pub use api::config::AppConfig;
+pub use api::errors::{APIErrorAndReason, ErrorContext};
+pub use api::server::{create_app, create_app_with_genesis, run};
+pub use api::structs::{BlockList, Limits, NodeStatus, PagedBlockList, PeerList, State};
+pub use blockchain::block::Block;
+pub use blockchain::shared::SharedChain;
+pub use blockchain::{Chain, InvalidBlockErr};
+pub use peers::{EntryRejectedErr, MemberEntry, Peers};
@@ -1,7 +1,5 @@
-mod blockchain;
-mod peers;
-mod api;
+use rustychain::{run, AppConfig};
 
-fn main() {
-    println!("Hello, world!");
+fn main() -> tide::Result<()> {
+    async_std::task::block_on(run(AppConfig::from_env()))
 }
@@ -1,20 +1,56 @@
 use surf::Url;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct MemberEntry {
     pub peer: String,
 }
 
+#[derive(Debug, Clone, Default)]
 pub struct Peers {
     pub members: Vec<MemberEntry>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum EntryRejectedErr {
     AlreadyPresent(MemberEntry),
     InvalidURL(String),
-    Unknown,
+    NotFound(MemberEntry),
+    Unknown { error: String, reason: String },
+}
+
+impl fmt::Display for EntryRejectedErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntryRejectedErr::AlreadyPresent(entry) => write!(f, "Entry is already a member: {}", entry.peer),
+            EntryRejectedErr::InvalidURL(url) => write!(f, "Entry URL is invalid: {}", url),
+            EntryRejectedErr::NotFound(entry) => write!(f, "Entry is not a member: {}", entry.peer),
+            EntryRejectedErr::Unknown { reason, .. } => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for EntryRejectedErr {}
+
+// Url already lowercases scheme and host and drops default ports; the
+// trailing slash and fragment would still make one node look like two.
+fn normalized(peer: &str) -> Option<String> {
+    let mut url = Url::parse(peer).ok()?;
+    url.set_fragment(None);
+    let mut normalized = url.to_string();
+    if url.query().is_none() && normalized.ends_with('/') {
+        normalized.pop();
+    }
+    Some(normalized)
+}
+
+impl MemberEntry {
+    pub fn normalized(&self) -> MemberEntry {
+        MemberEntry {
+            peer: normalized(&self.peer).unwrap_or_else(|| self.peer.clone()),
+        }
+    }
 }
 
 impl Peers {
@@ -22,15 +58,32 @@ impl Peers {
         Peers { members: vec![] }
     }
     pub fn append(&mut self, entry: MemberEntry) -> Result<MemberEntry, EntryRejectedErr> {
-        if Url::parse(&*entry.peer).is_ok() == false {
-            return Err(EntryRejectedErr::InvalidURL(entry.peer));
-        }
-        if self.members.contains(&entry) {
+        let entry = match normalized(&entry.peer) {
+            Some(peer) => MemberEntry { peer: peer },
+            None => return Err(EntryRejectedErr::InvalidURL(entry.peer)),
+        };
+        if self.contains(&entry) {
             return Err(EntryRejectedErr::AlreadyPresent(entry));
         }
         self.members.push(entry.clone());
         Ok(entry)
     }
+    pub fn remove(&mut self, entry: &MemberEntry) -> Result<MemberEntry, EntryRejectedErr> {
+        let wanted = entry.normalized();
+        match self.members.iter().position(|member| *member == wanted) {
+            Some(position) => Ok(self.members.remove(position)),
+            None => Err(EntryRejectedErr::NotFound(entry.clone())),
+        }
+    }
+    pub fn contains(&self, entry: &MemberEntry) -> bool {
+        self.members.contains(&entry.normalized())
+    }
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
 }
 
 
@@ -39,3 +92,60 @@ impl PartialEq for MemberEntry {
         self.peer == other.peer
     }
 }
+
+impl Eq for MemberEntry {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(peer: &str) -> MemberEntry {
+        MemberEntry {
+            peer: String::from(peer),
+        }
+    }
+
+    #[test]
+    fn removed_peer_is_no_longer_a_member() {
+        let mut peers = Peers::new();
+        peers.append(entry("http://localhost:9090")).unwrap();
+        peers.append(entry("http://localhost:9091")).unwrap();
+        assert_eq!(peers.remove(&entry("http://localhost:9090")), Ok(entry("http://localhost:9090")));
+        assert!(!peers.contains(&entry("http://localhost:9090")));
+        assert!(peers.contains(&entry("http://localhost:9091")));
+        assert_eq!(peers.len(), 1);
+        peers.append(entry("http://localhost:9090")).unwrap();
+        assert_eq!(peers.len(), 2);
+    }
+
+    #[test]
+    fn removing_a_missing_peer_is_not_found() {
+        let mut peers = Peers::new();
+        assert!(peers.is_empty());
+        let missing = entry("http://localhost:9090");
+        assert_eq!(peers.remove(&missing), Err(EntryRejectedErr::NotFound(missing.clone())));
+        peers.append(missing.clone()).unwrap();
+        peers.remove(&missing).unwrap();
+        assert_eq!(peers.remove(&missing), Err(EntryRejectedErr::NotFound(missing)));
+        assert!(peers.is_empty());
+    }
+
+    #[test]
+    fn spellings_of_the_same_url_are_one_member() {
+        let mut peers = Peers::new();
+        assert_eq!(peers.append(entry("HTTP://NODE:8080/#status")), Ok(entry("http://node:8080")));
+        for duplicate in ["http://node:8080", "http://node:8080/", "HTTP://NODE:8080"] {
+            assert_eq!(
+                peers.append(entry(duplicate)),
+                Err(EntryRejectedErr::AlreadyPresent(entry("http://node:8080")))
+            );
+            assert!(peers.contains(&entry(duplicate)));
+        }
+        assert_eq!(peers.append(entry("http://node:80/")), Ok(entry("http://node")));
+        assert!(peers.contains(&entry("http://node:80")));
+        assert_eq!(peers.append(entry("http://node:8080/other/")), Ok(entry("http://node:8080/other")));
+        assert_eq!(peers.len(), 3);
+        assert_eq!(peers.remove(&entry("http://NODE:8080/")), Ok(entry("http://node:8080")));
+        assert_eq!(peers.len(), 2);
+    }
+}